@@ -5,8 +5,15 @@
 #define CSAL_VALIDATOR_TYPE 1
 #include "validator.h"
 #include "secp256k1_helper.h"
+#ifdef ETH_ADDRESS_MODE
+#include <ethash/keccak.h>
+#endif
 
 #define ONE_CKB ((uint64_t)100000000)
+/* Must match the `chain_id` of the polyjuice run config */
+#ifndef CHAIN_ID
+#define CHAIN_ID 1
+#endif
 #define MIN_CONTRACT_CAPACITY ((uint64_t)(ONE_CKB * (uint64_t)158))
 
 int check_script_code(const uint8_t *script_data_a,
@@ -112,17 +119,39 @@ int check_eoa_script(blake2b_state *blake2b_ctx,
     debug_print_int("Invalid lock script args length", lock_args_bytes_seg->size);
     return -99;
   }
+#ifdef ETH_ADDRESS_MODE
+  /* The lock args is the Ethereum address: keccak256(uncompressed_pubkey)[12..] */
+  memcpy(address->bytes, lock_args_bytes_seg->ptr, 20);
+#else
   uint8_t buf[32];
   blake2b_init(blake2b_ctx, 32);
   blake2b_update(blake2b_ctx, type_args_bytes_seg->ptr, type_args_bytes_seg->size);
   blake2b_update(blake2b_ctx, lock_args_bytes_seg->ptr, lock_args_bytes_seg->size);
   blake2b_final(blake2b_ctx, buf, 32);
   memcpy(address->bytes, buf, 20);
+#endif
 
   *matched = true;
   return 0;
 }
 
+#ifdef ETH_ADDRESS_MODE
+/* The data of an EoA cell is empty or the nonce of the next signed Ethereum
+ * transaction of the owner (uint64 little endian) */
+int parse_eoa_nonce(const uint8_t *cell_data, const uint64_t cell_data_size, uint64_t *nonce) {
+  if (cell_data_size == 0) {
+    *nonce = 0;
+    return 0;
+  }
+  if (cell_data_size != 8) {
+    debug_print_int("invalid eoa cell data length", cell_data_size);
+    return -116;
+  }
+  memcpy(nonce, cell_data, 8);
+  return 0;
+}
+#endif
+
 uint64_t script_occupied_capacity(uint8_t* script, uint64_t size) {
   mol_seg_t script_seg;
   script_seg.ptr = script;
@@ -277,6 +306,11 @@ typedef struct {
   uint64_t output_capacity;
   /* check if the eoa account balance is changed */
   bool is_used;
+  /* the cell is merged into tx_origin output */
+  bool is_merged;
+  /* the nonce of the next Ethereum transaction (cell data) */
+  uint64_t input_nonce;
+  uint64_t output_nonce;
 } eoa_account;
 
 static bool global_touched = false;
@@ -284,6 +318,8 @@ static contract_info global_info_list[MAX_CONTRACT_COUNT];
 static size_t global_info_count = 0;
 static eoa_account global_eoa_list[MAX_EOA_COUNT];
 static size_t global_eoa_count = 0;
+/* The entrance program is authorized by a signed Ethereum transaction */
+static bool global_has_eth_tx = false;
 static evmc_address global_current_contract;
 static bool global_current_is_main = false;
 static struct evmc_tx_context global_tx_context;
@@ -518,6 +554,9 @@ void eoa_account_init(eoa_account *account,
                       uint64_t input_capacity,
                       int input_index) {
   account->is_used = false;
+  account->is_merged = false;
+  account->input_nonce = 0;
+  account->output_nonce = 0;
   account->address = *address;
   memcpy(account->type_args, type_args->ptr, type_args->size);
   memcpy(account->lock_args, lock_args->ptr, lock_args->size);
@@ -550,7 +589,45 @@ int eoa_account_sub_balance(eoa_account *account, uint64_t value) {
   return 0;
 }
 
+/* The other EoA cells of the tx_origin owner (same lock args) without output
+ * are merged into tx_origin output, when the tx_origin cell don't have enough
+ * balance. The whole capacity of the merged cells is added to tx_origin. The
+ * nonce of a merged cell can't be larger than the nonce of tx_origin. */
+int merge_tx_origin_eoa_accounts() {
+  eoa_account *tx_origin_account = NULL;
+  find_eoa_account(&tx_origin_account, global_eoa_list, global_eoa_count, &global_tx_context.tx_origin);
+  if (tx_origin_account == NULL) {
+    debug_print("can not find tx_origin account");
+    return -99;
+  }
+  for (size_t i = 0; i < global_eoa_count; i++) {
+    eoa_account *account = global_eoa_list + i;
+    if (account != tx_origin_account
+        && account->input_index >= 0
+        && account->output_index < 0
+        && memcmp(account->lock_args, tx_origin_account->lock_args, 20) == 0) {
+      debug_print_data("merge eoa account into tx_origin", account->address.bytes, 20);
+      debug_print_int("merged capacity", account->input_capacity);
+      if (account->input_nonce > tx_origin_account->input_nonce) {
+        debug_print_int("merged eoa account nonce too large", account->input_nonce);
+        return -116;
+      }
+      tx_origin_account->input_balance += account->input_capacity;
+      tx_origin_account->input_capacity += account->input_capacity;
+      account->is_merged = true;
+    }
+  }
+  return 0;
+}
+
 int verify_eoa_account(const eoa_account *account) {
+  if (account->is_merged) {
+    if (account->is_used) {
+      debug_print_data("merged eoa account is used", account->address.bytes, 20);
+      return -109;
+    }
+    return 0;
+  }
   if (account->output_index < 0) {
     debug_print_int("invalid eoa account output index", account->output_index);
     return -109;
@@ -572,6 +649,13 @@ int verify_eoa_account(const eoa_account *account) {
       /* Remove this later */
       debug_print_int("[WARN]: too much transaction fee", tx_fee);
     }
+    /* The signed Ethereum transaction increases the nonce by one */
+    uint64_t expected_nonce = account->input_nonce + (global_has_eth_tx ? 1 : 0);
+    if (account->output_nonce != expected_nonce) {
+      debug_print_int("tx_origin nonce not match, output:", account->output_nonce);
+      debug_print_int("tx_origin nonce not match, expected:", expected_nonce);
+      return -116;
+    }
   } else {
     if (!account->is_used) {
       debug_print_data("eoa account is unused", account->address.bytes, 20);
@@ -587,6 +671,11 @@ int verify_eoa_account(const eoa_account *account) {
       debug_print_int("eoa capacity not match, output:", account->output_capacity);
       return -109;
     }
+    if (account->input_nonce != account->output_nonce) {
+      debug_print_int("eoa nonce not match, input:", account->input_nonce);
+      debug_print_int("eoa nonce not match, output:", account->output_nonce);
+      return -116;
+    }
   }
   return 0;
 }
@@ -759,13 +848,19 @@ int contract_info_next_program(contract_info *info) {
           debug_print_data("info->address", info->address.bytes, 20);
           return -99;
         }
+        /* The capacity of the contract selfdestructed to itself is refunded to tx_origin */
+        evmc_address beneficiary = program->beneficiary;
+        if (memcmp(beneficiary.bytes, info->address.bytes, 20) == 0) {
+          debug_print("selfdestruct to itself, refund to tx_origin");
+          memcpy(beneficiary.bytes, global_tx_context.tx_origin.bytes, 20);
+        }
         contract_info *beneficiary_info = NULL;
-        find_contract_info(&beneficiary_info, global_info_list, global_info_count, &program->beneficiary);
+        find_contract_info(&beneficiary_info, global_info_list, global_info_count, &beneficiary);
         if (beneficiary_info == NULL) {
           eoa_account *beneficiary_account = NULL;
-          find_eoa_account(&beneficiary_account, global_eoa_list, global_eoa_count, &program->beneficiary);
+          find_eoa_account(&beneficiary_account, global_eoa_list, global_eoa_count, &beneficiary);
           if (beneficiary_account == NULL) {
-            debug_print_data("can not find selfdestruct target", program->beneficiary.bytes, 20);
+            debug_print_data("can not find selfdestruct target", beneficiary.bytes, 20);
             return -99;
           }
           debug_print("add beneficiary to eoa");
@@ -1530,6 +1625,12 @@ int load_account_infos(blake2b_state *blake2b_ctx) {
                          balance,
                          capacity,
                          input_index);
+#ifdef ETH_ADDRESS_MODE
+        ret = parse_eoa_nonce(cell_data, cell_data_size, &account->input_nonce);
+        if (ret != CKB_SUCCESS) {
+          return ret;
+        }
+#endif
         global_eoa_count += 1;
       }
     }
@@ -1665,10 +1766,10 @@ int load_account_infos(blake2b_state *blake2b_ctx) {
                              lock_script_size,
                              &is_eoa,
                              &eoa_address,
-                             &type_args_bytes_seg,
-                             &lock_args_bytes_seg);
+                             &lock_args_bytes_seg,
+                             &type_args_bytes_seg);
       if (ret != CKB_SUCCESS) {
-        debug_print_int("check eoa script failed, input index:", input_index);
+        debug_print_int("check eoa script failed, output index:", output_index);
         return ret;
       }
       if (is_eoa) {
@@ -1676,12 +1777,32 @@ int load_account_infos(blake2b_state *blake2b_ctx) {
         eoa_account *account = NULL;
         find_eoa_account(&account, global_eoa_list, global_eoa_count, &eoa_address);
         if (account == NULL) {
-          debug_print_data("eoa account not in inputs", eoa_address.bytes, 20);
-          return -100;
+          /* New EoA cell of an unknown transfer recipient, the occupied
+           * capacity is paid by tx_origin and the balance must be received
+           * by transfers (checked in verify_eoa_account). */
+          debug_print_data("new eoa account", eoa_address.bytes, 20);
+          if (global_eoa_count >= MAX_EOA_COUNT) {
+            return -100;
+          }
+          account = global_eoa_list + global_eoa_count;
+          eoa_account_init(account,
+                           &eoa_address,
+                           &type_args_bytes_seg,
+                           &lock_args_bytes_seg,
+                           0,
+                           capacity - balance,
+                           -1);
+          global_eoa_count += 1;
         }
         account->output_balance = balance;
         account->output_capacity = capacity;
         account->output_index = output_index;
+#ifdef ETH_ADDRESS_MODE
+        ret = parse_eoa_nonce(cell_data, cell_data_size, &account->output_nonce);
+        if (ret != CKB_SUCCESS) {
+          return ret;
+        }
+#endif
       }
     }
     output_index += 1;
@@ -1898,8 +2019,7 @@ int load_tx_context(blake2b_state *blake2b_ctx) {
   /* TODO block_coinbase */
   /* convert from compact_target */
   global_tx_context.block_difficulty = compact_to_difficulty(compact_target);
-  /* chain_id = 1 (mainnet) */
-  intx::uint256 chain_id = 1;
+  intx::uint256 chain_id = CHAIN_ID;
   intx::be::store(global_tx_context.chain_id.bytes, chain_id);
 
   debug_print_data("[block difficulty]", global_tx_context.block_difficulty.bytes, 32);
@@ -2054,6 +2174,300 @@ int load_tx_context(blake2b_state *blake2b_ctx) {
   return 0;
 }
 
+#ifdef ETH_ADDRESS_MODE
+#define ETH_TX_FIELD_COUNT 9
+#define WEI_PER_SHANNON ((uint64_t)10000000000)
+
+typedef struct {
+  /* payload of the item */
+  const uint8_t *ptr;
+  size_t size;
+  /* header + payload */
+  size_t encoded_size;
+  bool is_list;
+} rlp_item;
+
+/* Decode the header of a rlp item, the non-canonical encodings are rejected */
+int rlp_decode_item(const uint8_t *data, const size_t size, rlp_item *item) {
+  if (size == 0) {
+    return -115;
+  }
+  uint8_t prefix = data[0];
+  size_t header_size = 1;
+  size_t len;
+  if (prefix < 0x80) {
+    item->ptr = data;
+    item->size = 1;
+    item->encoded_size = 1;
+    item->is_list = false;
+    return 0;
+  } else if (prefix <= 0xb7 || (prefix >= 0xc0 && prefix <= 0xf7)) {
+    item->is_list = prefix >= 0xc0;
+    len = prefix - (item->is_list ? 0xc0 : 0x80);
+    if (!item->is_list && len == 1 && (size < 2 || data[1] < 0x80)) {
+      debug_print("non-canonical rlp single byte");
+      return -115;
+    }
+  } else {
+    item->is_list = prefix >= 0xf8;
+    size_t len_of_len = prefix - (item->is_list ? 0xf7 : 0xb7);
+    if (len_of_len > 4 || size < 1 + len_of_len || data[1] == 0) {
+      debug_print("invalid rlp length");
+      return -115;
+    }
+    len = 0;
+    for (size_t i = 0; i < len_of_len; i++) {
+      len = (len << 8) | data[1 + i];
+    }
+    if (len < 56) {
+      debug_print("non-canonical rlp length");
+      return -115;
+    }
+    header_size += len_of_len;
+  }
+  if (len > size - header_size) {
+    debug_print("rlp data too short");
+    return -115;
+  }
+  item->ptr = data + header_size;
+  item->size = len;
+  item->encoded_size = header_size + len;
+  return 0;
+}
+
+/* Load a big endian integer of at most 8 bytes */
+int rlp_item_to_u64(const rlp_item *item, uint64_t *value) {
+  if (item->size > 8) {
+    return -115;
+  }
+  *value = 0;
+  for (size_t i = 0; i < item->size; i++) {
+    *value = (*value << 8) | item->ptr[i];
+  }
+  return 0;
+}
+
+/* Load a big endian integer of at most 32 bytes */
+int rlp_item_to_u256(const rlp_item *item, intx::uint256 *value) {
+  if (item->size > 32) {
+    return -115;
+  }
+  uint8_t buf[32];
+  memset(buf, 0, 32);
+  memcpy(buf + (32 - item->size), item->ptr, item->size);
+  *value = intx::be::load<intx::uint256>(buf);
+  return 0;
+}
+
+size_t rlp_put_header(uint8_t *buf, const uint8_t base, const size_t len) {
+  if (len < 56) {
+    buf[0] = base + (uint8_t)len;
+    return 1;
+  }
+  size_t len_of_len = 0;
+  for (size_t rest = len; rest > 0; rest >>= 8) {
+    len_of_len += 1;
+  }
+  buf[0] = base + 55 + (uint8_t)len_of_len;
+  for (size_t i = 0; i < len_of_len; i++) {
+    buf[len_of_len - i] = (uint8_t)(len >> (8 * i));
+  }
+  return 1 + len_of_len;
+}
+
+/* The signed EIP-155 Ethereum transaction in the witness lock of tx_origin
+ * input: [nonce, gas_price, gas_limit, to, value, data, v, r, s] */
+static uint8_t global_eth_tx_buf[WITNESS_SIZE];
+/* rlp([nonce, gas_price, gas_limit, to, value, data, chain_id, 0, 0]) */
+static uint8_t global_eth_signing_buf[WITNESS_SIZE + 32];
+
+/* Load the signed Ethereum transaction from the witness of tx_origin input,
+ * returns the size of the transaction, 0 if there is no transaction. */
+int load_eth_transaction(const eoa_account *tx_origin_account, size_t *eth_tx_size) {
+  *eth_tx_size = 0;
+  uint64_t len = WITNESS_SIZE;
+  int ret = ckb_load_witness(global_eth_tx_buf, &len, 0, tx_origin_account->input_index, CKB_SOURCE_INPUT);
+  if (ret == CKB_INDEX_OUT_OF_BOUND) {
+    return 0;
+  } else if (ret != CKB_SUCCESS) {
+    return ret;
+  }
+  if (len > WITNESS_SIZE) {
+    debug_print_int("tx_origin witness too large", len);
+    return -115;
+  }
+  mol_seg_t witness_seg;
+  witness_seg.ptr = global_eth_tx_buf;
+  witness_seg.size = len;
+  if (MolReader_WitnessArgs_verify(&witness_seg, false) != MOL_OK) {
+    return 0;
+  }
+  mol_seg_t lock_seg = MolReader_WitnessArgs_get_lock(&witness_seg);
+  if (MolReader_BytesOpt_is_none(&lock_seg)) {
+    return 0;
+  }
+  mol_seg_t lock_bytes_seg = MolReader_Bytes_raw_bytes(&lock_seg);
+  memmove(global_eth_tx_buf, lock_bytes_seg.ptr, lock_bytes_seg.size);
+  *eth_tx_size = lock_bytes_seg.size;
+  return 0;
+}
+
+/* Verify the signed Ethereum transaction authorizes the entrance program:
+ *   - the chain id is CHAIN_ID (EIP-155)
+ *   - the nonce is the nonce of tx_origin cell
+ *   - `to`, `value` and `data` match the program
+ *   - the signature is the program signature
+ * The EIP-155 signing hash is written to `sign_message`, the signer is
+ * checked against the lock args of tx_origin by the caller. */
+int verify_eth_transaction(const size_t eth_tx_size,
+                           const uint8_t *signature_data,
+                           const uint8_t call_kind,
+                           const uint32_t flags,
+                           const evmc_address *destination,
+                           const evmc_uint256be *value,
+                           const uint32_t code_size,
+                           const uint8_t *code_data,
+                           const uint32_t input_size,
+                           const uint8_t *input_data,
+                           const eoa_account *tx_origin_account,
+                           uint8_t *sign_message) {
+  int ret;
+  rlp_item list;
+  ret = rlp_decode_item(global_eth_tx_buf, eth_tx_size, &list);
+  if (ret != CKB_SUCCESS) {
+    return ret;
+  }
+  if (!list.is_list || list.encoded_size != eth_tx_size) {
+    debug_print("invalid eth transaction rlp");
+    return -115;
+  }
+  rlp_item fields[ETH_TX_FIELD_COUNT];
+  size_t offset = 0;
+  for (size_t i = 0; i < ETH_TX_FIELD_COUNT; i++) {
+    ret = rlp_decode_item(list.ptr + offset, list.size - offset, &fields[i]);
+    if (ret != CKB_SUCCESS) {
+      return ret;
+    }
+    if (fields[i].is_list) {
+      debug_print_int("unexpected rlp list in eth transaction field", i);
+      return -115;
+    }
+    offset += fields[i].encoded_size;
+  }
+  if (offset != list.size) {
+    debug_print("invalid eth transaction field count");
+    return -115;
+  }
+
+  /* chain id and recovery id */
+  uint64_t v;
+  ret = rlp_item_to_u64(&fields[6], &v);
+  if (ret != CKB_SUCCESS) {
+    return ret;
+  }
+  if (v < 35 || (v - 35) / 2 != (uint64_t)CHAIN_ID) {
+    debug_print_int("invalid eth transaction v", v);
+    return -115;
+  }
+  uint8_t signature[65];
+  memset(signature, 0, 65);
+  for (size_t i = 0; i < 2; i++) {
+    const rlp_item *part = &fields[7 + i];
+    if (part->size > 32) {
+      return -115;
+    }
+    memcpy(signature + (i + 1) * 32 - part->size, part->ptr, part->size);
+  }
+  signature[64] = (uint8_t)((v - 35) % 2);
+  if (memcmp(signature, signature_data, 65) != 0) {
+    debug_print("program signature not match eth transaction");
+    return -115;
+  }
+
+  /* nonce */
+  uint64_t nonce;
+  ret = rlp_item_to_u64(&fields[0], &nonce);
+  if (ret != CKB_SUCCESS) {
+    return ret;
+  }
+  if (nonce != tx_origin_account->input_nonce) {
+    debug_print_int("eth transaction nonce not match, nonce:", nonce);
+    debug_print_int("eth transaction nonce not match, expected:", tx_origin_account->input_nonce);
+    return -116;
+  }
+
+  /* to and data */
+  const rlp_item *to = &fields[3];
+  const rlp_item *data = &fields[5];
+  if (to->size == 0) {
+    if (call_kind != EVMC_CREATE
+        || data->size != code_size
+        || (code_size > 0 && memcmp(data->ptr, code_data, code_size) != 0)) {
+      debug_print("eth transaction not match the create program");
+      return -115;
+    }
+  } else {
+    if (to->size != 20
+        || call_kind != EVMC_CALL
+        || flags != 0
+        || memcmp(to->ptr, destination->bytes, 20) != 0
+        || data->size != input_size
+        || (input_size > 0 && memcmp(data->ptr, input_data, input_size) != 0)) {
+      debug_print("eth transaction not match the call program");
+      return -115;
+    }
+  }
+
+  /* value in wei */
+  intx::uint256 wei;
+  ret = rlp_item_to_u256(&fields[4], &wei);
+  if (ret != CKB_SUCCESS) {
+    return ret;
+  }
+  intx::uint256 shannons = intx::be::load<intx::uint256>(value->bytes);
+  if (shannons > intx::uint256(UINT64_MAX) || wei != shannons * intx::uint256(WEI_PER_SHANNON)) {
+    debug_print("eth transaction value not match");
+    return -115;
+  }
+
+  /* EIP-155 signing hash */
+  uint8_t chain_id_be[8];
+  for (size_t i = 0; i < 8; i++) {
+    chain_id_be[i] = (uint8_t)((uint64_t)CHAIN_ID >> (8 * (7 - i)));
+  }
+  size_t chain_id_start = 0;
+  while (chain_id_start < 8 && chain_id_be[chain_id_start] == 0) {
+    chain_id_start += 1;
+  }
+  uint8_t chain_id_rlp[9];
+  size_t chain_id_size = 8 - chain_id_start;
+  if (chain_id_size == 1 && chain_id_be[7] < 0x80) {
+    chain_id_rlp[0] = chain_id_be[7];
+  } else {
+    chain_id_rlp[0] = (uint8_t)(0x80 + chain_id_size);
+    memcpy(chain_id_rlp + 1, chain_id_be + chain_id_start, chain_id_size);
+    chain_id_size += 1;
+  }
+  /* The encoded nonce, gas_price, gas_limit, to, value and data */
+  size_t unsigned_size = 0;
+  for (size_t i = 0; i < 6; i++) {
+    unsigned_size += fields[i].encoded_size;
+  }
+  size_t payload_size = unsigned_size + chain_id_size + 2;
+  size_t header_size = rlp_put_header(global_eth_signing_buf, 0xc0, payload_size);
+  uint8_t *ptr = global_eth_signing_buf + header_size;
+  memcpy(ptr, list.ptr, unsigned_size);
+  ptr += unsigned_size;
+  memcpy(ptr, chain_id_rlp, chain_id_size);
+  ptr += chain_id_size;
+  ptr[0] = 0x80;
+  ptr[1] = 0x80;
+  union ethash_hash256 signing_hash = ethash_keccak256(global_eth_signing_buf, header_size + payload_size);
+  memcpy(sign_message, signing_hash.bytes, 32);
+  return 0;
+}
+#endif
+
 inline int verify_params(const uint8_t *signature_data,
                          const uint8_t call_kind,
                          const uint32_t flags,
@@ -2096,6 +2510,11 @@ inline int verify_params(const uint8_t *signature_data,
       return ret;
     }
 
+    ret = merge_tx_origin_eoa_accounts();
+    if (ret != CKB_SUCCESS) {
+      return ret;
+    }
+
     ret = verify_contract_code(&blake2b_ctx, call_kind, destination, code_size, code_data);
     if (ret != CKB_SUCCESS) {
       return ret;
@@ -2114,7 +2533,7 @@ inline int verify_params(const uint8_t *signature_data,
 
   eoa_account *tx_origin_account = NULL;
   find_eoa_account(&tx_origin_account, global_eoa_list, global_eoa_count, tx_origin);
-  if (tx_origin_account == NULL) {
+  if (tx_origin_account == NULL || tx_origin_account->input_index < 0) {
     debug_print("can not find tx_origin account");
     return -99;
   }
@@ -2177,6 +2596,25 @@ inline int verify_params(const uint8_t *signature_data,
       // Verify EoA account call contract
       uint8_t sign_message[32];
       blake2b_final(&blake2b_ctx, sign_message, 32);
+#ifdef ETH_ADDRESS_MODE
+      /* The signed Ethereum transaction replaces the program signature */
+      size_t eth_tx_size;
+      ret = load_eth_transaction(tx_origin_account, &eth_tx_size);
+      if (ret != CKB_SUCCESS) {
+        return ret;
+      }
+      if (eth_tx_size > 0) {
+        debug_print("Verify Ethereum transaction");
+        ret = verify_eth_transaction(eth_tx_size, signature_data, call_kind, flags,
+                                     destination, value, code_size, code_data,
+                                     input_size, input_data, tx_origin_account,
+                                     sign_message);
+        if (ret != CKB_SUCCESS) {
+          return ret;
+        }
+        global_has_eth_tx = true;
+      }
+#endif
 
       /* Load signature */
       secp256k1_context context;
@@ -2201,6 +2639,17 @@ inline int verify_params(const uint8_t *signature_data,
 
       /* Check pubkey hash */
       uint8_t temp[65];
+#ifdef ETH_ADDRESS_MODE
+      size_t pubkey_size = 65;
+      if (secp256k1_ec_pubkey_serialize(&context, temp,
+                                        &pubkey_size, &pubkey,
+                                        SECP256K1_EC_UNCOMPRESSED) != 1) {
+        return -94;
+      }
+      /* Ethereum address: keccak256(uncompressed_pubkey[1..])[12..] */
+      union ethash_hash256 pubkey_hash = ethash_keccak256(temp + 1, pubkey_size - 1);
+      memcpy(temp, pubkey_hash.bytes + 12, 20);
+#else
       size_t pubkey_size = 33;
       if (secp256k1_ec_pubkey_serialize(&context, temp,
                                         &pubkey_size, &pubkey,
@@ -2211,6 +2660,7 @@ inline int verify_params(const uint8_t *signature_data,
       blake2b_init(&blake2b_ctx, 32);
       blake2b_update(&blake2b_ctx, temp, pubkey_size);
       blake2b_final(&blake2b_ctx, temp, 32);
+#endif
 
       eoa_account *account = NULL;
       find_eoa_account(&account, global_eoa_list, global_eoa_count, tx_origin);
@@ -2225,10 +2675,15 @@ inline int verify_params(const uint8_t *signature_data,
       }
 
       uint8_t hash[32];
+#ifdef ETH_ADDRESS_MODE
+      /* The EoA address is the lock args */
+      memcpy(hash, temp, 20);
+#else
       blake2b_init(&blake2b_ctx, 32);
       blake2b_update(&blake2b_ctx, account->type_args, 32);
       blake2b_update(&blake2b_ctx, temp, 20);
       blake2b_final(&blake2b_ctx, hash, 32);
+#endif
 
       /* Verify entrance program sender */
       if (memcmp(sender->bytes, hash, 20) != 0) {
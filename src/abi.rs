@@ -0,0 +1,728 @@
+use ckb_types::{bytes::Bytes, H256};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tiny_keccak::keccak256;
+
+/// A parameter of function/event in the contract ABI JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbiParam {
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub indexed: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<AbiParam>,
+}
+
+/// A function/constructor/event/fallback in the contract ABI JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbiEntry {
+    #[serde(rename = "type", default = "default_entry_kind")]
+    pub kind: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub inputs: Vec<AbiParam>,
+    #[serde(default)]
+    pub outputs: Vec<AbiParam>,
+    #[serde(default)]
+    pub anonymous: bool,
+}
+
+/// The standard ERC20/ERC721 events (and the non-indexed ERC721 draft events
+/// used by CryptoKitties), for decoding logs of contracts without ABI uploaded
+const STANDARD_EVENTS_JSON: &str = r#"[
+  {"type": "event", "name": "Transfer", "inputs": [
+    {"type": "address", "name": "from", "indexed": true},
+    {"type": "address", "name": "to", "indexed": true},
+    {"type": "uint256", "name": "value"}]},
+  {"type": "event", "name": "Approval", "inputs": [
+    {"type": "address", "name": "owner", "indexed": true},
+    {"type": "address", "name": "spender", "indexed": true},
+    {"type": "uint256", "name": "value"}]},
+  {"type": "event", "name": "Transfer", "inputs": [
+    {"type": "address", "name": "from", "indexed": true},
+    {"type": "address", "name": "to", "indexed": true},
+    {"type": "uint256", "name": "tokenId", "indexed": true}]},
+  {"type": "event", "name": "Approval", "inputs": [
+    {"type": "address", "name": "owner", "indexed": true},
+    {"type": "address", "name": "approved", "indexed": true},
+    {"type": "uint256", "name": "tokenId", "indexed": true}]},
+  {"type": "event", "name": "ApprovalForAll", "inputs": [
+    {"type": "address", "name": "owner", "indexed": true},
+    {"type": "address", "name": "operator", "indexed": true},
+    {"type": "bool", "name": "approved"}]},
+  {"type": "event", "name": "Transfer", "inputs": [
+    {"type": "address", "name": "from"},
+    {"type": "address", "name": "to"},
+    {"type": "uint256", "name": "tokenId"}]},
+  {"type": "event", "name": "Approval", "inputs": [
+    {"type": "address", "name": "owner"},
+    {"type": "address", "name": "approved"},
+    {"type": "uint256", "name": "tokenId"}]}
+]"#;
+
+lazy_static::lazy_static! {
+    pub static ref STANDARD_EVENTS: Abi =
+        Abi::from_json(STANDARD_EVENTS_JSON).expect("standard events abi");
+}
+
+fn default_entry_kind() -> String {
+    String::from("function")
+}
+
+/// A decoded function argument/return value or event parameter
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DecodedParam {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub value: Value,
+}
+
+/// A log decoded by the ABI of the emitting contract
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DecodedEvent {
+    pub name: String,
+    /// The event signature, topic0 = keccak256(signature)
+    pub signature: String,
+    pub params: Vec<DecodedParam>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum ParamType {
+    Address,
+    Bool,
+    Uint(usize),
+    Int(usize),
+    FixedBytes(usize),
+    Bytes,
+    String,
+    Array(Box<ParamType>),
+    FixedArray(Box<ParamType>, usize),
+    Tuple(Vec<ParamType>),
+}
+
+impl ParamType {
+    fn parse(param: &AbiParam) -> Result<ParamType, String> {
+        ParamType::parse_str(param.kind.as_str(), &param.components)
+    }
+
+    fn parse_str(kind: &str, components: &[AbiParam]) -> Result<ParamType, String> {
+        if kind.ends_with(']') {
+            let open = kind
+                .rfind('[')
+                .ok_or_else(|| format!("Invalid abi type: {}", kind))?;
+            let inner = Box::new(ParamType::parse_str(&kind[..open], components)?);
+            let size = &kind[open + 1..kind.len() - 1];
+            if size.is_empty() {
+                return Ok(ParamType::Array(inner));
+            }
+            let size = size
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid abi type: {}", kind))?;
+            return Ok(ParamType::FixedArray(inner, size));
+        }
+        let bits = |prefix: &str| -> Result<usize, String> {
+            let bits = match &kind[prefix.len()..] {
+                "" => 256,
+                bits => bits
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid abi type: {}", kind))?,
+            };
+            if bits == 0 || bits > 256 || bits % 8 != 0 {
+                return Err(format!("Invalid abi type: {}", kind));
+            }
+            Ok(bits)
+        };
+        match kind {
+            "address" => Ok(ParamType::Address),
+            "bool" => Ok(ParamType::Bool),
+            "string" => Ok(ParamType::String),
+            "bytes" => Ok(ParamType::Bytes),
+            // address ++ selector
+            "function" => Ok(ParamType::FixedBytes(24)),
+            "tuple" => components
+                .iter()
+                .map(ParamType::parse)
+                .collect::<Result<Vec<_>, _>>()
+                .map(ParamType::Tuple),
+            _ if kind.starts_with("uint") => bits("uint").map(ParamType::Uint),
+            _ if kind.starts_with("int") => bits("int").map(ParamType::Int),
+            _ if kind.starts_with("bytes") => match kind[5..].parse::<usize>() {
+                Ok(size) if size > 0 && size <= 32 => Ok(ParamType::FixedBytes(size)),
+                _ => Err(format!("Invalid abi type: {}", kind)),
+            },
+            _ => Err(format!("Unsupported abi type: {}", kind)),
+        }
+    }
+
+    /// The type name used in signatures
+    fn canonical(&self) -> String {
+        match self {
+            ParamType::Address => String::from("address"),
+            ParamType::Bool => String::from("bool"),
+            ParamType::Uint(bits) => format!("uint{}", bits),
+            ParamType::Int(bits) => format!("int{}", bits),
+            ParamType::FixedBytes(size) => format!("bytes{}", size),
+            ParamType::Bytes => String::from("bytes"),
+            ParamType::String => String::from("string"),
+            ParamType::Array(inner) => format!("{}[]", inner.canonical()),
+            ParamType::FixedArray(inner, size) => format!("{}[{}]", inner.canonical(), size),
+            ParamType::Tuple(types) => format!(
+                "({})",
+                types
+                    .iter()
+                    .map(ParamType::canonical)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        }
+    }
+
+    fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Bytes | ParamType::String | ParamType::Array(_) => true,
+            ParamType::FixedArray(inner, _) => inner.is_dynamic(),
+            ParamType::Tuple(types) => types.iter().any(ParamType::is_dynamic),
+            _ => false,
+        }
+    }
+
+    /// The size in the head part of the encoding
+    fn head_size(&self) -> usize {
+        if self.is_dynamic() {
+            return 32;
+        }
+        match self {
+            ParamType::FixedArray(inner, size) => inner.head_size() * size,
+            ParamType::Tuple(types) => types.iter().map(ParamType::head_size).sum(),
+            _ => 32,
+        }
+    }
+}
+
+fn param_types(params: &[AbiParam]) -> Result<Vec<ParamType>, String> {
+    params.iter().map(ParamType::parse).collect()
+}
+
+fn usize_word(value: usize) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..32].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+fn read_word(data: &[u8], offset: usize) -> Result<&[u8], String> {
+    offset
+        .checked_add(32)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| String::from("Abi data too short"))
+}
+
+fn read_usize(data: &[u8], offset: usize) -> Result<usize, String> {
+    let word = read_word(data, offset)?;
+    if word[0..24].iter().any(|byte| *byte != 0) {
+        return Err(String::from("Invalid abi offset or length"));
+    }
+    let mut be_bytes = [0u8; 8];
+    be_bytes.copy_from_slice(&word[24..32]);
+    Ok(u64::from_be_bytes(be_bytes) as usize)
+}
+
+fn parse_hex(value: &Value) -> Result<Vec<u8>, String> {
+    let input = value
+        .as_str()
+        .ok_or_else(|| format!("Expected hex string, got: {}", value))?;
+    hex::decode(input.trim_start_matches("0x")).map_err(|err| err.to_string())
+}
+
+/// Parse a decimal or "0x" prefixed hex unsigned integer as big endian word
+pub fn parse_uint(value: &Value) -> Result<[u8; 32], String> {
+    let mut word = [0u8; 32];
+    let input = match value {
+        Value::Number(number) => {
+            let number = number
+                .as_u64()
+                .ok_or_else(|| format!("Invalid unsigned integer: {}", number))?;
+            word[24..32].copy_from_slice(&number.to_be_bytes());
+            return Ok(word);
+        }
+        Value::String(input) => input.as_str(),
+        _ => return Err(format!("Invalid integer: {}", value)),
+    };
+    if input.starts_with("0x") {
+        let data = parse_hex(value)?;
+        if data.len() > 32 {
+            return Err(format!("Integer overflow: {}", input));
+        }
+        word[32 - data.len()..].copy_from_slice(&data);
+        return Ok(word);
+    }
+    if input.is_empty() {
+        return Err(String::from("Empty integer"));
+    }
+    for ch in input.chars() {
+        let mut carry = ch
+            .to_digit(10)
+            .ok_or_else(|| format!("Invalid integer: {}", input))?;
+        for byte in word.iter_mut().rev() {
+            let product = u32::from(*byte) * 10 + carry;
+            *byte = product as u8;
+            carry = product >> 8;
+        }
+        if carry != 0 {
+            return Err(format!("Integer overflow: {}", input));
+        }
+    }
+    Ok(word)
+}
+
+fn negate(word: &mut [u8; 32]) {
+    let mut carry = 1u16;
+    for byte in word.iter_mut().rev() {
+        let sum = u16::from(!*byte) + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+fn parse_int(value: &Value) -> Result<[u8; 32], String> {
+    let (negative, magnitude) = match value {
+        Value::Number(number) if number.is_i64() => {
+            let number = number.as_i64().expect("i64");
+            let magnitude = Value::from((i128::from(number)).abs() as u64);
+            (number < 0, magnitude)
+        }
+        Value::String(input) if input.starts_with('-') => {
+            (true, Value::String(input[1..].to_string()))
+        }
+        _ => (false, value.clone()),
+    };
+    let mut word = parse_uint(&magnitude)?;
+    if word[0] & 0x80 != 0 {
+        return Err(format!("Integer overflow: {}", value));
+    }
+    if negative {
+        negate(&mut word);
+    }
+    Ok(word)
+}
+
+/// Format a big endian unsigned integer as decimal string
+pub fn to_decimal(word: &[u8]) -> String {
+    let mut number = word.to_vec();
+    let mut digits = Vec::new();
+    while number.iter().any(|byte| *byte != 0) {
+        let mut remainder = 0u32;
+        for byte in number.iter_mut() {
+            let current = (remainder << 8) | u32::from(*byte);
+            *byte = (current / 10) as u8;
+            remainder = current % 10;
+        }
+        digits.push(std::char::from_digit(remainder, 10).expect("digit"));
+    }
+    if digits.is_empty() {
+        return String::from("0");
+    }
+    digits.iter().rev().collect()
+}
+
+fn encode_values(types: &[ParamType], values: &[Value]) -> Result<Vec<u8>, String> {
+    if types.len() != values.len() {
+        return Err(format!(
+            "Expected {} values, got {}",
+            types.len(),
+            values.len()
+        ));
+    }
+    let heads_size: usize = types.iter().map(ParamType::head_size).sum();
+    let mut head = Vec::new();
+    let mut tail = Vec::new();
+    for (param_type, value) in types.iter().zip(values) {
+        let data = encode_value(param_type, value)?;
+        if param_type.is_dynamic() {
+            head.extend_from_slice(&usize_word(heads_size + tail.len()));
+            tail.extend(data);
+        } else {
+            head.extend(data);
+        }
+    }
+    head.extend(tail);
+    Ok(head)
+}
+
+fn encode_packed_bytes(data: &[u8]) -> Vec<u8> {
+    let mut buf = usize_word(data.len()).to_vec();
+    buf.extend_from_slice(data);
+    let padding = (32 - data.len() % 32) % 32;
+    buf.extend(vec![0u8; padding]);
+    buf
+}
+
+fn as_array(value: &Value, size_opt: Option<usize>) -> Result<&Vec<Value>, String> {
+    let values = value
+        .as_array()
+        .ok_or_else(|| format!("Expected array, got: {}", value))?;
+    if let Some(size) = size_opt {
+        if values.len() != size {
+            return Err(format!("Expected {} items, got {}", size, values.len()));
+        }
+    }
+    Ok(values)
+}
+
+fn encode_value(param_type: &ParamType, value: &Value) -> Result<Vec<u8>, String> {
+    match param_type {
+        ParamType::Address => {
+            let data = parse_hex(value)?;
+            if data.len() != 20 {
+                return Err(format!("Invalid address: {}", value));
+            }
+            let mut word = [0u8; 32];
+            word[12..32].copy_from_slice(&data);
+            Ok(word.to_vec())
+        }
+        ParamType::Bool => {
+            let flag = match value {
+                Value::Bool(flag) => *flag,
+                Value::String(input) if input == "true" || input == "false" => input == "true",
+                _ => return Err(format!("Invalid bool: {}", value)),
+            };
+            Ok(usize_word(flag as usize).to_vec())
+        }
+        ParamType::Uint(bits) => {
+            let word = parse_uint(value)?;
+            if word[0..32 - bits / 8].iter().any(|byte| *byte != 0) {
+                return Err(format!("Integer overflow uint{}: {}", bits, value));
+            }
+            Ok(word.to_vec())
+        }
+        ParamType::Int(bits) => {
+            let word = parse_int(value)?;
+            let sign_byte = if word[0] & 0x80 != 0 { 0xff } else { 0x00 };
+            let start = 32 - bits / 8;
+            if word[0..start].iter().any(|byte| *byte != sign_byte)
+                || (word[start] & 0x80 != 0) != (sign_byte == 0xff)
+            {
+                return Err(format!("Integer overflow int{}: {}", bits, value));
+            }
+            Ok(word.to_vec())
+        }
+        ParamType::FixedBytes(size) => {
+            let data = parse_hex(value)?;
+            if data.len() != *size {
+                return Err(format!("Expected {} bytes, got: {}", size, value));
+            }
+            let mut word = vec![0u8; 32];
+            word[0..*size].copy_from_slice(&data);
+            Ok(word)
+        }
+        ParamType::Bytes => Ok(encode_packed_bytes(&parse_hex(value)?)),
+        ParamType::String => {
+            let input = value
+                .as_str()
+                .ok_or_else(|| format!("Expected string, got: {}", value))?;
+            Ok(encode_packed_bytes(input.as_bytes()))
+        }
+        ParamType::Array(inner) => {
+            let values = as_array(value, None)?;
+            let types = vec![inner.as_ref().clone(); values.len()];
+            let mut buf = usize_word(values.len()).to_vec();
+            buf.extend(encode_values(&types, values)?);
+            Ok(buf)
+        }
+        ParamType::FixedArray(inner, size) => {
+            let types = vec![inner.as_ref().clone(); *size];
+            encode_values(&types, as_array(value, Some(*size))?)
+        }
+        ParamType::Tuple(types) => encode_values(types, as_array(value, Some(types.len()))?),
+    }
+}
+
+fn decode_values(types: &[ParamType], data: &[u8]) -> Result<Vec<Value>, String> {
+    let mut values = Vec::with_capacity(types.len());
+    let mut offset = 0;
+    for param_type in types {
+        let value = if param_type.is_dynamic() {
+            let position = read_usize(data, offset)?;
+            let content = data
+                .get(position..)
+                .ok_or_else(|| String::from("Invalid abi offset"))?;
+            decode_value(param_type, content)?
+        } else {
+            let content = data
+                .get(offset..)
+                .ok_or_else(|| String::from("Abi data too short"))?;
+            decode_value(param_type, content)?
+        };
+        values.push(value);
+        offset += param_type.head_size();
+    }
+    Ok(values)
+}
+
+fn read_packed_bytes(data: &[u8]) -> Result<&[u8], String> {
+    let len = read_usize(data, 0)?;
+    len.checked_add(32)
+        .and_then(|end| data.get(32..end))
+        .ok_or_else(|| String::from("Abi data too short"))
+}
+
+fn decode_value(param_type: &ParamType, data: &[u8]) -> Result<Value, String> {
+    let value = match param_type {
+        ParamType::Address => {
+            Value::String(format!("0x{}", hex::encode(&read_word(data, 0)?[12..32])))
+        }
+        ParamType::Bool => Value::Bool(read_word(data, 0)?.iter().any(|byte| *byte != 0)),
+        ParamType::Uint(_) => Value::String(to_decimal(read_word(data, 0)?)),
+        ParamType::Int(_) => {
+            let mut word = [0u8; 32];
+            word.copy_from_slice(read_word(data, 0)?);
+            if word[0] & 0x80 != 0 {
+                negate(&mut word);
+                Value::String(format!("-{}", to_decimal(&word)))
+            } else {
+                Value::String(to_decimal(&word))
+            }
+        }
+        ParamType::FixedBytes(size) => {
+            Value::String(format!("0x{}", hex::encode(&read_word(data, 0)?[0..*size])))
+        }
+        ParamType::Bytes => Value::String(format!("0x{}", hex::encode(read_packed_bytes(data)?))),
+        ParamType::String => {
+            Value::String(String::from_utf8_lossy(read_packed_bytes(data)?).to_string())
+        }
+        ParamType::Array(inner) => {
+            let len = read_usize(data, 0)?;
+            if len > data.len() {
+                return Err(String::from("Invalid abi array length"));
+            }
+            let types = vec![inner.as_ref().clone(); len];
+            Value::Array(decode_values(&types, &data[32..])?)
+        }
+        ParamType::FixedArray(inner, size) => {
+            let types = vec![inner.as_ref().clone(); *size];
+            Value::Array(decode_values(&types, data)?)
+        }
+        ParamType::Tuple(types) => Value::Array(decode_values(types, data)?),
+    };
+    Ok(value)
+}
+
+fn named_params(params: &[AbiParam], values: Vec<Value>) -> Result<Vec<DecodedParam>, String> {
+    params
+        .iter()
+        .zip(values)
+        .map(|(param, value)| {
+            Ok(DecodedParam {
+                name: param.name.clone(),
+                kind: ParamType::parse(param)?.canonical(),
+                value,
+            })
+        })
+        .collect()
+}
+
+impl AbiEntry {
+    /// The signature of function/event: `name(type1,type2)`
+    pub fn signature(&self) -> Result<String, String> {
+        let types = param_types(&self.inputs)?
+            .iter()
+            .map(ParamType::canonical)
+            .collect::<Vec<_>>();
+        Ok(format!("{}({})", self.name, types.join(",")))
+    }
+
+    /// The call input: selector ++ encoded arguments
+    pub fn encode_input(&self, args: &[Value]) -> Result<Bytes, String> {
+        let selector = keccak256(self.signature()?.as_bytes());
+        let mut input = selector[0..4].to_vec();
+        input.extend(encode_values(&param_types(&self.inputs)?, args)?);
+        Ok(Bytes::from(input))
+    }
+
+    pub fn decode_output(&self, data: &[u8]) -> Result<Vec<DecodedParam>, String> {
+        let values = decode_values(&param_types(&self.outputs)?, data)?;
+        named_params(&self.outputs, values)
+    }
+
+    fn decode_event(&self, topics: &[H256], data: &[u8]) -> Result<DecodedEvent, String> {
+        let signature = self.signature()?;
+        let mut topics = topics.iter();
+        if !self.anonymous {
+            let topic0 = topics
+                .next()
+                .ok_or_else(|| String::from("Missing event topic"))?;
+            if topic0.as_bytes() != &keccak256(signature.as_bytes())[..] {
+                return Err(String::from("Event signature not match"));
+            }
+        }
+        let indexed_count = self.inputs.iter().filter(|param| param.indexed).count();
+        if topics.len() != indexed_count {
+            return Err(String::from("Indexed parameters not match"));
+        }
+        let (indexed, not_indexed): (Vec<&AbiParam>, Vec<&AbiParam>) =
+            self.inputs.iter().partition(|param| param.indexed);
+        let not_indexed_types = not_indexed
+            .iter()
+            .map(|param| ParamType::parse(param))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut data_values = decode_values(&not_indexed_types, data)?.into_iter();
+        let mut indexed_values = Vec::new();
+        for (param, topic) in indexed.iter().zip(topics) {
+            let param_type = ParamType::parse(param)?;
+            // Only the hash of dynamic values is in the topic
+            let value = if param_type.is_dynamic() {
+                Value::String(format!("{:#x}", topic))
+            } else {
+                decode_value(&param_type, topic.as_bytes())?
+            };
+            indexed_values.push(value);
+        }
+        let mut indexed_values = indexed_values.into_iter();
+        let values = self
+            .inputs
+            .iter()
+            .map(|param| {
+                let value = if param.indexed {
+                    indexed_values.next()
+                } else {
+                    data_values.next()
+                };
+                value.unwrap_or(Value::Null)
+            })
+            .collect::<Vec<_>>();
+        Ok(DecodedEvent {
+            name: self.name.clone(),
+            signature,
+            params: named_params(&self.inputs, values)?,
+        })
+    }
+}
+
+/// The ABI of a contract
+#[derive(Debug, Clone)]
+pub struct Abi {
+    pub entries: Vec<AbiEntry>,
+}
+
+impl Abi {
+    pub fn from_json(json: &str) -> Result<Abi, String> {
+        let entries: Vec<AbiEntry> = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        for entry in &entries {
+            param_types(&entry.inputs)?;
+            param_types(&entry.outputs)?;
+        }
+        Ok(Abi { entries })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.entries).expect("serialize abi")
+    }
+
+    /// Find the function by name or signature (for overloaded functions)
+    pub fn function(&self, name: &str) -> Result<&AbiEntry, String> {
+        let functions = self
+            .entries
+            .iter()
+            .filter(|entry| entry.kind == "function")
+            .filter(|entry| {
+                entry.name == name || entry.signature().map(|sig| sig == name).unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+        match functions.len() {
+            0 => Err(format!("Function not found in abi: {}", name)),
+            1 => Ok(functions[0]),
+            _ => Err(format!(
+                "Overloaded function {}, use the signature instead",
+                name
+            )),
+        }
+    }
+
+    /// Decode the log by the events in the ABI
+    pub fn decode_log(&self, topics: &[H256], data: &[u8]) -> Option<DecodedEvent> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == "event")
+            .find_map(|entry| entry.decode_event(topics, data).ok())
+    }
+}
+
+/// Decode the log by the ABI of the contract, fallback to the standard
+/// ERC20/ERC721 events
+pub fn decode_log(abi_opt: Option<&Abi>, topics: &[H256], data: &[u8]) -> Option<DecodedEvent> {
+    abi_opt
+        .and_then(|abi| abi.decode_log(topics, data))
+        .or_else(|| STANDARD_EVENTS.decode_log(topics, data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::{h160, h256};
+    use serde_json::json;
+
+    #[test]
+    fn test_erc20_abi() {
+        let abi = Abi::from_json(include_str!("../tests/evm-contracts/ERC20.abi")).unwrap();
+        let transfer = abi.function("transfer").unwrap();
+        assert_eq!(transfer.signature().unwrap(), "transfer(address,uint256)");
+        let input = transfer
+            .encode_input(&[
+                json!("0x3535353535353535353535353535353535353535"),
+                json!("1000"),
+            ])
+            .unwrap();
+        assert_eq!(
+            hex::encode(&input),
+            "a9059cbb\
+             0000000000000000000000003535353535353535353535353535353535353535\
+             00000000000000000000000000000000000000000000000000000000000003e8"
+        );
+        assert!(transfer.encode_input(&[json!("0x35")]).is_err());
+
+        let name = abi.function("name").unwrap();
+        let output = hex::decode(
+            "0000000000000000000000000000000000000000000000000000000000000020\
+             0000000000000000000000000000000000000000000000000000000000000003\
+             4142430000000000000000000000000000000000000000000000000000000000",
+        )
+        .unwrap();
+        assert_eq!(name.decode_output(&output).unwrap()[0].value, json!("ABC"));
+
+        let topics = vec![
+            h256!("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"),
+            h256!("0x3535353535353535353535353535353535353535"),
+            h256!("0x3636363636363636363636363636363636363636"),
+        ];
+        let data = usize_word(1000);
+        let event = abi.decode_log(&topics, &data).unwrap();
+        assert_eq!(event.name, "Transfer");
+        assert_eq!(
+            event.params[1].value,
+            json!("0x3636363636363636363636363636363636363636")
+        );
+        assert_eq!(event.params[2].value, json!("1000"));
+
+        // ERC721 events of KittyCore (not indexed) without ABI uploaded
+        let mut data = vec![0u8; 12];
+        data.extend(h160!("0x35").as_bytes());
+        data.extend(&[0u8; 12]);
+        data.extend(h160!("0x36").as_bytes());
+        data.extend(&usize_word(7));
+        let topics = vec![h256!(
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        )];
+        let event = decode_log(None, &topics, &data).unwrap();
+        assert_eq!(event.signature, "Transfer(address,address,uint256)");
+        assert_eq!(event.params[2].name, "tokenId");
+        assert_eq!(event.params[2].value, json!("7"));
+
+        let int_type = ParamType::Int(256);
+        let encoded = encode_value(&int_type, &json!(-2)).unwrap();
+        assert_eq!(decode_value(&int_type, &encoded).unwrap(), json!("-2"));
+    }
+}
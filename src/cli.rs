@@ -0,0 +1,1177 @@
+use ckb_hash::blake2b_256;
+use ckb_jsonrpc_types as json_types;
+use ckb_sdk::{build_signature, Address, AddressPayload, HumanCapacity, NetworkType};
+use ckb_types::{
+    bytes::{BufMut, Bytes, BytesMut},
+    h256, packed,
+    prelude::*,
+    H160, H256,
+};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use jsonrpc_core::MetaIoHandler;
+use jsonrpc_http_server::ServerBuilder;
+use jsonrpc_server_utils::cors::AccessControlAllowOrigin;
+use jsonrpc_server_utils::hosts::DomainsValidation;
+use rocksdb::DB;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use tempfile::NamedTempFile;
+
+use crate::abi::Abi;
+use crate::client::{ChainClient, HttpRpcClient, RpcPolicy};
+use crate::dev::{DevAccount, DevChain};
+use crate::server::{Rpc, RpcImpl, TransactionReceipt};
+use crate::storage::{
+    create_processor, export_snapshot, import_snapshot, init_or_migrate, processor_names,
+    rollback_to, value::StartBlock, BlockProcessor, Indexer, Loader,
+};
+use crate::types::{
+    AddressMode, CallKind, EoaAddress, Program, RunConfig, WitnessData, DEFAULT_CHAIN_ID, ONE_CKB,
+    SECP256K1,
+};
+
+const ANYONE_CAN_PAY_CODE_HASH: H256 =
+    h256!("0x8b10144daa110152e78dd002b44f429238cbbee5e62052205fdc6a1fc2c57a2a");
+
+/// Run the polyjuice command line. The block processors registered by
+/// `register_processor` before it can be enabled by `--processor`.
+pub fn main() -> Result<(), String> {
+    env_logger::init();
+
+    let arg_ckb_url = Arg::with_name("url")
+        .long("url")
+        .takes_value(true)
+        .required(true)
+        .default_value("http://127.0.0.1:8114")
+        .use_delimiter(true)
+        .help("The ckb rpc url, multiple urls separated by comma, the following urls are used as fallback");
+    let arg_rpc_timeout = Arg::with_name("rpc-timeout")
+        .long("rpc-timeout")
+        .takes_value(true)
+        .default_value("30")
+        .validator(|input| {
+            input
+                .parse::<u64>()
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .help("The timeout of ckb rpc request (unit: second)");
+    let arg_rpc_retries = Arg::with_name("rpc-retries")
+        .long("rpc-retries")
+        .takes_value(true)
+        .default_value("5")
+        .validator(|input| {
+            input
+                .parse::<u32>()
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .help("Max retries of ckb rpc request when connection failed or timeout, send_transaction is never retried");
+    let arg_privkey = Arg::with_name("privkey")
+        .long("privkey")
+        .short("k")
+        .takes_value(true)
+        .required(true)
+        .validator(|input| {
+            fs::File::open(input)
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .help("The private key file (hex)");
+    let arg_abi = Arg::with_name("abi")
+        .long("abi")
+        .takes_value(true)
+        .required(true)
+        .validator(|input| load_abi(input.as_str()).map(|_| ()))
+        .help("The contract ABI file (json)");
+    let arg_function = Arg::with_name("function")
+        .long("function")
+        .takes_value(true)
+        .required(true)
+        .help("The function name or signature (for overloaded functions)");
+    let arg_address_mode = Arg::with_name("address-mode")
+        .long("address-mode")
+        .takes_value(true)
+        .possible_values(&["ckb", "ethereum"])
+        .default_value("ckb")
+        .help("How the EoA address derived from the public key (must match the run config)");
+    let arg_generator = Arg::with_name("generator")
+        .long("generator")
+        .takes_value(true)
+        .required(true)
+        .validator(|input| {
+            fs::File::open(input)
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .help("The generator riscv binary");
+    let arg_config = Arg::with_name("config")
+        .long("config")
+        .takes_value(true)
+        .required(true)
+        .validator(|input| {
+            fs::File::open(input)
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .help("The config (json)");
+    let arg_db = Arg::with_name("db")
+        .long("db")
+        .takes_value(true)
+        .required(true)
+        .default_value("./data")
+        .help("Database directory");
+    let arg_rollback_window = Arg::with_name("rollback-window")
+        .long("rollback-window")
+        .takes_value(true)
+        .default_value("200")
+        .validator(|input| {
+            input
+                .parse::<u64>()
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .help("The number of recent blocks can be rolled back, the rollback data of older blocks will be compacted");
+    let arg_start_block = Arg::with_name("start-block")
+        .long("start-block")
+        .takes_value(true)
+        .requires("start-block-hash")
+        .validator(|input| {
+            input
+                .parse::<u64>()
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .help("Start indexing from this block instead of genesis (the block where the validator deployed)");
+    let arg_start_block_hash = Arg::with_name("start-block-hash")
+        .long("start-block-hash")
+        .takes_value(true)
+        .requires("start-block")
+        .validator(|input| parse_h256(input.as_str()).map(|_| ()))
+        .help("The hash of the start block");
+    let arg_prefetch_blocks = Arg::with_name("prefetch-blocks")
+        .long("prefetch-blocks")
+        .takes_value(true)
+        .default_value("8")
+        .validator(|input| {
+            input
+                .parse::<usize>()
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .help("The number of blocks the indexer downloads in one batch request");
+    let registered_processors = processor_names();
+    let processor_choices = registered_processors
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let arg_processor = Arg::with_name("processor")
+        .long("processor")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .possible_values(&processor_choices)
+        .help("Enable a registered block processor to build custom indexes (\"tokens\": ERC20/ERC721 token tracker)");
+    let arg_listen = Arg::with_name("listen")
+        .long("listen")
+        .takes_value(true)
+        .required(true)
+        .default_value("127.0.0.1:8214")
+        .help("Polyjuice rpc server listen address");
+    let arg_metrics_listen = Arg::with_name("metrics-listen")
+        .long("metrics-listen")
+        .takes_value(true)
+        .help("Serve Prometheus metrics on http://<ADDRESS>/metrics");
+    let arg_max_lag = Arg::with_name("max-lag")
+        .long("max-lag")
+        .takes_value(true)
+        .validator(|input| {
+            input
+                .parse::<u64>()
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .help("Refuse create/call/static_call when the indexer is more than <BLOCKS> behind the CKB tip");
+    let matches = App::new("polyjuice")
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Run the polyjuice server")
+                .arg(arg_generator.clone())
+                .arg(arg_config.clone())
+                .arg(arg_db.clone())
+                .arg(arg_listen.clone())
+                .arg(arg_metrics_listen)
+                .arg(arg_max_lag)
+                .arg(arg_rollback_window.clone())
+                .arg(arg_start_block.clone())
+                .arg(arg_start_block_hash.clone())
+                .arg(arg_prefetch_blocks.clone())
+                .arg(arg_processor.clone())
+                .arg(arg_ckb_url.clone())
+                .arg(arg_rpc_timeout.clone())
+                .arg(arg_rpc_retries.clone())
+        )
+        .subcommand(
+            SubCommand::with_name("dev")
+                .about("Run the polyjuice server on an in-process simulated chain with pre-funded EoA accounts")
+                .arg(arg_generator.clone())
+                .arg(
+                    Arg::with_name("validator")
+                        .long("validator")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| fs::File::open(input).map(|_| ()).map_err(|err| err.to_string()))
+                        .help("The validator riscv binary")
+                )
+                .arg(arg_listen.clone())
+                .arg(arg_rollback_window.clone())
+                .arg(
+                    Arg::with_name("accounts")
+                        .long("accounts")
+                        .takes_value(true)
+                        .default_value("10")
+                        .validator(|input| input.parse::<usize>().map(|_| ()).map_err(|err| err.to_string()))
+                        .help("The number of pre-funded EoA accounts")
+                )
+                .arg(
+                    Arg::with_name("balance")
+                        .long("balance")
+                        .takes_value(true)
+                        .default_value("1000000.0")
+                        .validator(|input| HumanCapacity::from_str(input.as_str()).map(|_| ()))
+                        .help("The balance of every pre-funded EoA account (unit: CKB)")
+                )
+                .arg(arg_address_mode.clone())
+                .arg(arg_processor.clone())
+        )
+        .subcommand(
+            SubCommand::with_name("rollback-to")
+                .about("Rollback the indexed blocks to a block number")
+                .arg(arg_db.clone())
+                .arg(
+                    Arg::with_name("block")
+                        .long("block")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| input.parse::<u64>().map(|_| ()).map_err(|err| err.to_string()))
+                        .help("The block number to rollback to (the block is kept)")
+                )
+                .arg(arg_processor.clone())
+        )
+        .subcommand(
+            SubCommand::with_name("export-snapshot")
+                .about("Export the indexed state to a snapshot file")
+                .arg(arg_db.clone())
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The snapshot file path")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("import-snapshot")
+                .about("Import a snapshot file into an empty database")
+                .arg(arg_db.clone())
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .short("i")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| fs::File::open(input).map(|_| ()).map_err(|err| err.to_string()))
+                        .help("The snapshot file path")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("reindex")
+                .about("Rollback the indexed blocks and index again from a block number until reach the tip")
+                .arg(arg_generator.clone())
+                .arg(arg_config.clone())
+                .arg(arg_db.clone())
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| input.parse::<u64>().map(|_| ()).map_err(|err| err.to_string()))
+                        .help("The block number to index from")
+                )
+                .arg(arg_rollback_window.clone())
+                .arg(arg_start_block.clone())
+                .arg(arg_start_block_hash.clone())
+                .arg(arg_prefetch_blocks.clone())
+                .arg(arg_processor.clone())
+                .arg(arg_ckb_url.clone())
+                .arg(arg_rpc_timeout.clone())
+                .arg(arg_rpc_retries.clone())
+        )
+        .subcommand(
+            SubCommand::with_name("sign-tx")
+                .about("Sign transaction generated by polyjuice")
+                .arg(
+                    Arg::with_name("tx-receipt")
+                        .long("tx-receipt")
+                        .short("t")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| fs::File::open(input).map(|_| ()).map_err(|err| err.to_string()))
+                        .help("The transaction receipt file (json)")
+                )
+                .arg(arg_privkey.clone())
+                .arg(arg_address_mode.clone())
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .help("The output file path")
+                )
+                .arg(arg_ckb_url.clone())
+                .arg(arg_rpc_timeout.clone())
+                .arg(arg_rpc_retries.clone())
+        )
+        .subcommand(
+            SubCommand::with_name("new-eoa-account")
+                .about("Create an EoA account")
+                .arg(arg_privkey.clone())
+                .arg(arg_address_mode.clone())
+                .arg(
+                    Arg::with_name("balance")
+                        .long("balance")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| HumanCapacity::from_str(input.as_str()).map(|_| ()))
+                        .help("The balance to target EoA account (unit: CKB, format: 123.335, need extra capacity to create the cell)")
+                )
+                .arg(arg_ckb_url.clone())
+                .arg(arg_rpc_timeout.clone())
+                .arg(arg_rpc_retries.clone())
+        )
+        .subcommand(
+            SubCommand::with_name("build-tx")
+                .about("Build and serialize a eth transaction which will put into witness data")
+                .arg(
+                    Arg::with_name("call-kind")
+                        .long("call-kind")
+                        .takes_value(true)
+                        .default_value("call")
+                        .possible_values(&["create", "call"])
+                        .help("The kind of the call")
+                )
+                .arg(Arg::with_name("static").long("static").help("Is static call"))
+                .arg(
+                    Arg::with_name("signature")
+                        .long("signature")
+                        .takes_value(true)
+                        .validator(|input| parse_hex_binary(input.as_str()).map(|_| ()))
+                        .help("The signature (65 bytes)")
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .takes_value(true)
+                        .validator(|input| input.parse::<u32>().map(|_| ()).map_err(|err| err.to_string()))
+                        .default_value("0")
+                        .help("The call depth"),
+                )
+                .arg(
+                    Arg::with_name("sender")
+                        .long("sender")
+                        .takes_value(true)
+                        .validator(|input| parse_h160(input.as_str()).map(|_| ()))
+                        .default_value("0x1111111111111111111111111111111111111111")
+                        .help("The sender of the message")
+                )
+                .arg(
+                    Arg::with_name("destination")
+                        .long("destination")
+                        .takes_value(true)
+                        .validator(|input| parse_h160(input.as_str()).map(|_| ()))
+                        .default_value("0x2222222222222222222222222222222222222222")
+                        .help("The destination of the message")
+                )
+                .arg(
+                    Arg::with_name("code")
+                        .long("code")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| parse_hex_binary(input.as_str()).map(|_| ()))
+                        .help("The code to create/call the contract, hex file path or hex string")
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .takes_value(true)
+                        .validator(|input| parse_hex_binary(input.as_str()).map(|_| ()))
+                        .help("The input data to create/call the contract, hex file path or hex string")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("abi-encode")
+                .about("Encode the input of a contract function call by the contract ABI")
+                .arg(arg_abi.clone())
+                .arg(arg_function.clone())
+                .arg(
+                    Arg::with_name("args")
+                        .long("args")
+                        .takes_value(true)
+                        .default_value("[]")
+                        .validator(|input| serde_json::from_str::<Vec<serde_json::Value>>(input.as_str()).map(|_| ()).map_err(|err| err.to_string()))
+                        .help("The function arguments (json array)")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("abi-decode")
+                .about("Decode the return data of a contract function call by the contract ABI")
+                .arg(arg_abi.clone())
+                .arg(arg_function.clone())
+                .arg(
+                    Arg::with_name("data")
+                        .long("data")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(|input| parse_hex_binary(input.trim_start_matches("0x")).map(|_| ()))
+                        .help("The return data, hex file path or hex string")
+                )
+        ).get_matches();
+
+    match matches.subcommand() {
+        ("run", Some(m)) => {
+            let run_config = load_run_config(
+                m.value_of("generator").unwrap(),
+                m.value_of("config").unwrap(),
+            )?;
+            let mut client = build_rpc_client(m)?;
+            let db_dir = m.value_of("db").unwrap();
+            let listen_addr = m.value_of("listen").unwrap();
+            let rollback_window: u64 = m.value_of("rollback-window").unwrap().parse().unwrap();
+
+            log::info!("Open database: {:?}", db_dir);
+            let db = Arc::new(DB::open_default(db_dir).expect("rocksdb"));
+            init_or_migrate(&db, Some(&mut client))?;
+            if let Some(metrics_addr) = m.value_of("metrics-listen") {
+                metrics::serve_metrics(metrics_addr, Arc::clone(&db))?;
+            }
+            let mut indexer = Indexer::new(
+                Arc::clone(&db),
+                Box::new(client),
+                run_config.clone(),
+                rollback_window,
+            );
+            // Share the pending transactions with the indexer
+            let loader = Arc::new(indexer.loader.clone());
+            indexer.start_block = parse_start_block(m)?;
+            indexer.prefetch_blocks = m.value_of("prefetch-blocks").unwrap().parse().unwrap();
+            indexer.processors = parse_processors(m)?;
+            let _ = thread::spawn(move || indexer.index().expect("indexer faliure"));
+
+            let max_lag = m.value_of("max-lag").map(|value| value.parse().unwrap());
+            serve(loader, run_config, max_lag, listen_addr);
+        }
+        ("dev", Some(m)) => {
+            let generator = fs::read(m.value_of("generator").unwrap())
+                .map(Bytes::from)
+                .map_err(|err| err.to_string())?;
+            let validator = fs::read(m.value_of("validator").unwrap())
+                .map(Bytes::from)
+                .map_err(|err| err.to_string())?;
+            let listen_addr = m.value_of("listen").unwrap();
+            let rollback_window: u64 = m.value_of("rollback-window").unwrap().parse().unwrap();
+            let account_count: usize = m.value_of("accounts").unwrap().parse().unwrap();
+            let balance = HumanCapacity::from_str(m.value_of("balance").unwrap())
+                .unwrap()
+                .0;
+
+            let address_mode = parse_address_mode(m)?;
+
+            let accounts = (0..account_count)
+                .map(|index| DevAccount::new(index, address_mode))
+                .collect::<Vec<_>>();
+            let (chain, run_config) =
+                DevChain::new(generator, validator, &accounts, balance, address_mode);
+            println!("Dev accounts (balance: {} CKB):", HumanCapacity(balance));
+            for (index, account) in accounts.iter().enumerate() {
+                println!(
+                    "[{}] eoa-address: {:#x}, lock-arg: {:#x}, privkey: {}",
+                    index,
+                    account.eoa_address,
+                    account.lock_arg,
+                    hex::encode(&account.privkey[..])
+                );
+            }
+
+            // The chain is in memory, so is the indexed state
+            let db_dir = tempfile::tempdir().map_err(|err| err.to_string())?;
+            log::info!("Open database: {:?}", db_dir.path());
+            let db = Arc::new(DB::open_default(db_dir.path()).expect("rocksdb"));
+            init_or_migrate(&db, None)?;
+            let mut indexer = Indexer::new(
+                Arc::clone(&db),
+                Box::new(chain),
+                run_config.clone(),
+                rollback_window,
+            );
+            indexer.processors = parse_processors(m)?;
+            let loader = Arc::new(indexer.loader.clone());
+            let _ = thread::spawn(move || indexer.index().expect("indexer faliure"));
+            serve(loader, run_config, None, listen_addr);
+        }
+        ("rollback-to", Some(m)) => {
+            let db_dir = m.value_of("db").unwrap();
+            let block_number: u64 = m.value_of("block").unwrap().parse().unwrap();
+            log::info!("Open database: {:?}", db_dir);
+            let db = DB::open_default(db_dir).expect("rocksdb");
+            init_or_migrate(&db, None)?;
+            rollback_to(&db, Some(block_number), &parse_processors(m)?)?;
+            println!("Rollback to block {} finished", block_number);
+        }
+        ("export-snapshot", Some(m)) => {
+            let db_dir = m.value_of("db").unwrap();
+            let output = m.value_of("output").unwrap();
+            log::info!("Open database: {:?}", db_dir);
+            let db = DB::open_default(db_dir).expect("rocksdb");
+            init_or_migrate(&db, None)?;
+            let header = export_snapshot(&db, output)?;
+            println!(
+                "Exported snapshot: block={}, hash={:#x}, records={}, checksum={:#x}",
+                header.number, header.hash, header.record_count, header.checksum
+            );
+        }
+        ("import-snapshot", Some(m)) => {
+            let db_dir = m.value_of("db").unwrap();
+            let input = m.value_of("input").unwrap();
+            log::info!("Open database: {:?}", db_dir);
+            let db = DB::open_default(db_dir).expect("rocksdb");
+            init_or_migrate(&db, None)?;
+            let header = import_snapshot(&db, input)?;
+            println!(
+                "Imported snapshot: block={}, hash={:#x}, records={}, checksum={:#x}",
+                header.number, header.hash, header.record_count, header.checksum
+            );
+        }
+        ("reindex", Some(m)) => {
+            let run_config = load_run_config(
+                m.value_of("generator").unwrap(),
+                m.value_of("config").unwrap(),
+            )?;
+            let mut client = build_rpc_client(m)?;
+            let db_dir = m.value_of("db").unwrap();
+            let from_block: u64 = m.value_of("from").unwrap().parse().unwrap();
+            let rollback_window: u64 = m.value_of("rollback-window").unwrap().parse().unwrap();
+
+            log::info!("Open database: {:?}", db_dir);
+            let db = Arc::new(DB::open_default(db_dir).expect("rocksdb"));
+            init_or_migrate(&db, Some(&mut client))?;
+            let processors = parse_processors(m)?;
+            rollback_to(&db, from_block.checked_sub(1), &processors)?;
+            let mut indexer = Indexer::new(
+                Arc::clone(&db),
+                Box::new(client),
+                run_config,
+                rollback_window,
+            );
+            indexer.stop_at_tip = true;
+            indexer.start_block = parse_start_block(m)?;
+            indexer.prefetch_blocks = m.value_of("prefetch-blocks").unwrap().parse().unwrap();
+            indexer.processors = processors;
+            indexer.index()?;
+            println!("Reindex from block {} finished", from_block);
+        }
+        ("sign-tx", Some(m)) => {
+            let mut tx_receipt: TransactionReceipt =
+                fs::read_to_string(m.value_of("tx-receipt").unwrap())
+                    .map_err(|err| err.to_string())
+                    .and_then(|json_string| {
+                        serde_json::from_str(json_string.as_str()).map_err(|err| err.to_string())
+                    })?;
+            let address_mode = parse_address_mode(m)?;
+            let privkey_path = m.value_of("privkey").unwrap();
+            let privkey = fs::read_to_string(privkey_path)
+                .map_err(|err| err.to_string())
+                .and_then(|privkey| {
+                    hex::decode(&privkey.trim().as_bytes()[0..64]).map_err(|err| err.to_string())
+                })
+                .and_then(|data| {
+                    secp256k1::SecretKey::from_slice(data.as_slice()).map_err(|err| err.to_string())
+                })?;
+            let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+            let tx_origin_lock_arg = address_mode.lock_args(&pubkey);
+            // The EoA address is the lock args in Ethereum mode
+            if address_mode == AddressMode::Ethereum {
+                let tx_origin = entrance_witness_data(&tx_receipt.tx)
+                    .map(|witness_data| witness_data.program.tx_origin)
+                    .ok_or_else(|| String::from("No entrance witness found"))?;
+                if tx_origin.0 != tx_origin_lock_arg {
+                    return Err(format!(
+                        "The private key not match tx_origin: {:#x}",
+                        tx_origin.0
+                    ));
+                }
+            }
+            let ckb_uri = m.value_of("url").unwrap();
+
+            println!("Building signature");
+            sign_program_witness(&mut tx_receipt.tx, &privkey)?;
+            while tx_receipt.tx.witnesses.len() < tx_receipt.tx.inputs.len() {
+                tx_receipt.tx.witnesses.push(Default::default());
+            }
+
+            println!("Sign anyone can pay");
+            let tx_view = packed::Transaction::from(tx_receipt.tx.clone()).into_view();
+            let mut client = build_rpc_client(m)?;
+            // {lock_arg => {type_hash => (input_index, input_capacity, output_capacity)}}
+            let mut eoa_cells: HashMap<H160, HashMap<H256, (usize, u64, u64)>> = Default::default();
+            for (idx, input) in tx_view.inputs().into_iter().enumerate() {
+                let output: packed::CellOutput =
+                    get_live_cell(&mut client, input.previous_output().into(), false)?.into();
+                let lock_script = output.lock();
+                let code_hash: H256 = lock_script.code_hash().unpack();
+                if code_hash == ANYONE_CAN_PAY_CODE_HASH {
+                    let type_hash: H256 = output
+                        .type_()
+                        .to_opt()
+                        .expect("type id type script should exists")
+                        .calc_script_hash()
+                        .unpack();
+                    let input_capacity: u64 = output.capacity().unpack();
+                    let lock_arg =
+                        H160::from_slice(lock_script.args().raw_data().as_ref()).unwrap();
+                    let value = eoa_cells.entry(lock_arg).or_default();
+                    if value.contains_key(&type_hash) {
+                        return Err(format!("duplicated type script hash: {:x}", type_hash));
+                    }
+                    value.insert(type_hash, (idx, input_capacity, 0));
+                }
+            }
+            for output in tx_view.outputs().into_iter() {
+                let lock_script = output.lock();
+                let code_hash: H256 = lock_script.code_hash().unpack();
+                if code_hash == ANYONE_CAN_PAY_CODE_HASH {
+                    let type_hash: H256 = output
+                        .type_()
+                        .to_opt()
+                        .expect("type id type script should exists")
+                        .calc_script_hash()
+                        .unpack();
+                    let output_capacity: u64 = output.capacity().unpack();
+                    let lock_arg =
+                        H160::from_slice(lock_script.args().raw_data().as_ref()).unwrap();
+                    if let Some(value) = eoa_cells.get_mut(&lock_arg) {
+                        if let Some(inner_value) = value.get_mut(&type_hash) {
+                            inner_value.2 = output_capacity;
+                        } else {
+                            return Err(format!("type hash not found in output: {:x}", type_hash));
+                        }
+                    } else {
+                        return Err(format!("lock arg not found in output: {:x}", lock_arg));
+                    }
+                }
+            }
+            for (lock_arg, type_scripts) in eoa_cells {
+                let mut need_signature = false;
+                let mut idxs = type_scripts
+                    .values()
+                    .map(|(idx, _, _)| *idx)
+                    .collect::<Vec<_>>();
+                idxs.sort();
+                for (_, input_capacity, output_capacity) in type_scripts.values() {
+                    if input_capacity > output_capacity {
+                        need_signature = true;
+                        break;
+                    }
+                }
+
+                let lock_field = if need_signature {
+                    if lock_arg != tx_origin_lock_arg {
+                        return Err(format!("The only tx_origin need anyone can pay signature, current lock arg: {:x}", lock_arg));
+                    }
+                    let input_size = tx_view.inputs().len();
+                    let witnesses: Vec<packed::Bytes> = tx_view.witnesses().into_iter().collect();
+                    let signature = build_signature(
+                        &tx_view,
+                        input_size,
+                        &idxs,
+                        &witnesses,
+                        None,
+                        |message: &H256, _tx| {
+                            let message =
+                                secp256k1::Message::from_slice(message.as_bytes()).unwrap();
+                            Ok(serialize_signature(
+                                &SECP256K1.sign_recoverable(&message, &privkey),
+                            ))
+                        },
+                    )?;
+
+                    Some(signature)
+                } else {
+                    Some(Default::default())
+                };
+
+                let first_witness = &tx_receipt.tx.witnesses[idxs[0]];
+                let init_witness = if first_witness.is_empty() {
+                    packed::WitnessArgs::default()
+                } else {
+                    packed::WitnessArgs::from_slice(first_witness.as_bytes())
+                        .map_err(|err| err.to_string())?
+                };
+                tx_receipt.tx.witnesses[idxs[0]] = json_types::JsonBytes::from_bytes(
+                    init_witness
+                        .as_builder()
+                        .lock(lock_field.pack())
+                        .build()
+                        .as_bytes(),
+                );
+            }
+
+            let tx_file = NamedTempFile::new().map_err(|err| err.to_string())?;
+            let tx_path_str = tx_file.path().to_str().unwrap();
+            println!(
+                "[Command]: ckb-cli --url {} tx init --tx-file {}",
+                ckb_uri, tx_path_str
+            );
+            let output = Command::new("ckb-cli")
+                .args(&["--url", ckb_uri, "tx", "init", "--tx-file", tx_path_str])
+                .output()
+                .expect("Failed to execute command");
+            if output.status.success() {
+                println!("success!");
+            } else {
+                println!("[stdout]: {}", String::from_utf8_lossy(&output.stdout));
+                println!("[stderr]: {}", String::from_utf8_lossy(&output.stderr));
+            }
+
+            let tx_body: serde_json::Value = serde_json::to_value(&tx_receipt.tx).unwrap();
+            let cli_tx_content = fs::read_to_string(tx_path_str).unwrap();
+            let mut cli_tx: serde_json::Value = serde_json::from_str(&cli_tx_content).unwrap();
+            cli_tx["transaction"] = tx_body;
+            let cli_tx_content = serde_json::to_string_pretty(&cli_tx).unwrap();
+            if let Some(output) = m.value_of("output") {
+                fs::write(output, cli_tx_content.as_bytes()).map_err(|err| err.to_string())?;
+            } else {
+                println!("{}", cli_tx_content);
+            }
+        }
+        ("new-eoa-account", Some(m)) => {
+            let address_mode = parse_address_mode(m)?;
+            let balance_str = m.value_of("balance").unwrap();
+            let ckb_uri = m.value_of("url").unwrap();
+            let privkey_path = m.value_of("privkey").unwrap();
+            let privkey = fs::read_to_string(privkey_path)
+                .map_err(|err| err.to_string())
+                .and_then(|privkey| {
+                    hex::decode(&privkey.trim().as_bytes()[0..64]).map_err(|err| err.to_string())
+                })
+                .and_then(|data| {
+                    secp256k1::SecretKey::from_slice(data.as_slice()).map_err(|err| err.to_string())
+                })?;
+
+            let balance = HumanCapacity::from_str(balance_str).unwrap().0;
+            let capacity = balance + ONE_CKB * (8 + (32 + 1 + 32) + (32 + 1 + 20));
+            let capacity_string = HumanCapacity(capacity).to_string();
+            let mut client = build_rpc_client(m)?;
+            let chain_info = client.get_blockchain_info()?;
+            let network = NetworkType::from_raw_str(chain_info.chain.as_str())
+                .ok_or_else(|| format!("Unexpected network type: {}", chain_info.chain))?;
+            // FIXME: transfer to anyone-can-pay
+            let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+            let lock_arg = address_mode.lock_args(&pubkey);
+            println!("[lock-arg]: 0x{:x}", lock_arg);
+            let address_payload = AddressPayload::new_full_data(
+                ANYONE_CAN_PAY_CODE_HASH.pack(),
+                Bytes::from(lock_arg.as_bytes().to_vec()),
+            );
+            let address_string = Address::new(network, address_payload).to_string();
+            println!(
+                "[Command]: ckb-cli wallet transfer --privkey-path {} --to-address {} --capacity {} --tx-fee 0.001 --type-id --skip-check-to-address",
+                privkey_path,
+                address_string,
+                capacity_string,
+            );
+            let output = Command::new("ckb-cli")
+                .args(&["--url", ckb_uri])
+                .args(&["wallet", "transfer"])
+                .args(&["--privkey-path", privkey_path])
+                .args(&["--to-address", address_string.as_str()])
+                .args(&["--capacity", capacity_string.as_str()])
+                .args(&["--tx-fee", "0.001"])
+                .args(&["--type-id", "--skip-check-to-address"])
+                .output()
+                .expect("Failed to execute command");
+            if output.status.success() {
+                println!(
+                    "tx-hash: {}, output-index: 0",
+                    String::from_utf8_lossy(&output.stdout).trim()
+                );
+            } else {
+                println!("[stdout]: {}", String::from_utf8_lossy(&output.stdout));
+                println!("[stderr]: {}", String::from_utf8_lossy(&output.stderr));
+                return Err(String::from("failed"));
+            }
+            let tx_hash_string = String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .chars()
+                .skip(2)
+                .collect::<String>();
+            let tx_hash = H256::from_str(tx_hash_string.as_str()).unwrap();
+            let tx_with_status = client.get_transaction(tx_hash)?.unwrap();
+            let output = tx_with_status.transaction.inner.outputs[0].clone();
+            let type_args = output.type_.unwrap().args;
+            let lock_args = output.lock.args;
+            println!("[type_args]: {}", hex::encode(type_args.as_bytes()));
+            println!("[lock_args]: {}", hex::encode(lock_args.as_bytes()));
+            let eoa_address = address_mode.eoa_address(type_args.as_bytes(), lock_args.as_bytes());
+            println!("{:#x}", eoa_address);
+        }
+        ("build-tx", Some(m)) => {
+            let signature = m
+                .value_of("signature")
+                .map(|input| {
+                    let data = parse_hex_binary(input)?;
+                    if data.len() != 65 {
+                        return Err(format!("Invalid data length for signature: {}", data.len()));
+                    }
+                    let mut target = [0u8; 65];
+                    target.copy_from_slice(data.as_ref());
+                    Ok(Bytes::from(target.to_vec()))
+                })
+                .transpose()?
+                .unwrap_or_else(|| Bytes::from([0u8; 65].to_vec()));
+            let kind: CallKind = m
+                .value_of("call-kind")
+                .map(|input| serde_json::from_str(format!("\"{}\"", input).as_str()).unwrap())
+                .unwrap();
+            let flags: u32 = if m.is_present("static") { 1 } else { 0 };
+            let depth: u32 = m.value_of("depth").unwrap().parse::<u32>().unwrap();
+            let sender = parse_h160(m.value_of("sender").unwrap()).unwrap();
+            let destination = parse_h160(m.value_of("destination").unwrap()).unwrap();
+            let code = parse_hex_binary(m.value_of("code").unwrap())
+                .map(Bytes::from)
+                .unwrap();
+            let input = parse_hex_binary(m.value_of("input").unwrap_or(""))
+                .map(Bytes::from)
+                .unwrap();
+            let program = Program {
+                kind,
+                flags,
+                depth,
+                tx_origin: EoaAddress(sender.clone()),
+                sender,
+                destination,
+                value: 0,
+                code,
+                input,
+            };
+            let mut witness_data = WitnessData::new(program);
+            witness_data.signature = signature;
+            let program_data = witness_data.program_data();
+            println!(
+                "[length]: {}",
+                hex::encode(&(program_data.len() as u32).to_le_bytes()[..])
+            );
+            println!("[binary]: {}", hex::encode(program_data.as_ref()));
+        }
+        ("abi-encode", Some(m)) => {
+            let abi = load_abi(m.value_of("abi").unwrap())?;
+            let args: Vec<serde_json::Value> =
+                serde_json::from_str(m.value_of("args").unwrap()).map_err(|err| err.to_string())?;
+            let input = abi
+                .function(m.value_of("function").unwrap())?
+                .encode_input(&args)?;
+            println!("0x{}", hex::encode(input.as_ref()));
+        }
+        ("abi-decode", Some(m)) => {
+            let abi = load_abi(m.value_of("abi").unwrap())?;
+            let data = parse_hex_binary(m.value_of("data").unwrap().trim_start_matches("0x"))?;
+            let return_values = abi
+                .function(m.value_of("function").unwrap())?
+                .decode_output(&data)?;
+            println!("{}", serde_json::to_string_pretty(&return_values).unwrap());
+        }
+        _ => println!("{}", matches.usage()),
+    }
+    Ok(())
+}
+
+// Start the polyjuice rpc server and wait for Ctrl-C
+fn serve(loader: Arc<Loader>, run_config: RunConfig, max_lag: Option<u64>, listen_addr: &str) {
+    let mut io_handler = MetaIoHandler::with_middleware(metrics::RpcMetrics);
+    io_handler.extend_with(
+        RpcImpl {
+            loader,
+            run_config,
+            max_lag,
+        }
+        .to_delegate(),
+    );
+
+    let rpc_server = ServerBuilder::new(io_handler)
+        .cors(DomainsValidation::AllowOnly(vec![
+            AccessControlAllowOrigin::Null,
+            AccessControlAllowOrigin::Any,
+        ]))
+        .threads(4)
+        .max_request_body_size(10_485_760)
+        .start_http(&listen_addr.parse().expect("parse listen address"))
+        .expect("jsonrpc initialize");
+    log::info!("RPC server listen on: {}", listen_addr);
+
+    // Wait for exit
+    let exit = Arc::new((Mutex::new(()), Condvar::new()));
+    let e = Arc::clone(&exit);
+    ctrlc::set_handler(move || {
+        e.1.notify_all();
+    })
+    .expect("error setting Ctrl-C handler");
+    let _guard = exit
+        .1
+        .wait(exit.0.lock().expect("locking"))
+        .expect("waiting");
+    rpc_server.close();
+    log::info!("exiting...");
+}
+
+fn build_rpc_client(m: &ArgMatches) -> Result<HttpRpcClient, String> {
+    let urls = m
+        .values_of("url")
+        .unwrap()
+        .map(|url| url.to_string())
+        .collect::<Vec<_>>();
+    let policy = RpcPolicy {
+        timeout: Duration::from_secs(m.value_of("rpc-timeout").unwrap().parse().unwrap()),
+        max_retries: m.value_of("rpc-retries").unwrap().parse().unwrap(),
+        ..Default::default()
+    };
+    HttpRpcClient::new(urls, policy).map_err(|err| err.to_string())
+}
+
+/// The entrance program in the first witness of the transaction built by
+/// polyjuice.
+pub fn entrance_witness_data(tx: &json_types::Transaction) -> Option<WitnessData> {
+    let witness = tx.witnesses.get(0)?;
+    let witness_args = packed::WitnessArgs::from_slice(witness.as_bytes()).ok()?;
+    let raw_witness = witness_args
+        .input_type()
+        .to_opt()
+        .or_else(|| witness_args.output_type().to_opt())?
+        .raw_data();
+    WitnessData::load_from(&raw_witness)
+        .ok()?
+        .map(|(_, witness_data)| witness_data)
+}
+
+/// Sign the entrance program in the witness of the transaction built by
+/// polyjuice.
+pub fn sign_program_witness(
+    tx: &mut json_types::Transaction,
+    privkey: &secp256k1::SecretKey,
+) -> Result<(), String> {
+    let tx_hash: H256 = packed::Transaction::from(tx.clone())
+        .calc_tx_hash()
+        .unpack();
+    let (message, entrance_witness_args, entrance_raw_witness) = {
+        let mut entrance_witness = None;
+        let mut unsigned_data = BytesMut::default();
+        unsigned_data.put(tx_hash.as_bytes());
+        let mut output_witnesses = Vec::new();
+        for (idx, witness) in tx.witnesses.iter().enumerate() {
+            let witness_args = packed::WitnessArgs::from_slice(witness.as_bytes())
+                .map_err(|err| err.to_string())?;
+            if let Some(raw_witness) = witness_args
+                .input_type()
+                .to_opt()
+                .map(|witness_data| witness_data.raw_data())
+            {
+                if idx == 0 {
+                    entrance_witness = Some((witness_args, raw_witness.clone()));
+                    let mut raw_witness = raw_witness.as_ref().to_vec();
+                    // 4 bytes is for program length (u32)
+                    raw_witness[4..4 + 65].copy_from_slice(&[0u8; 65][..]);
+                    unsigned_data.put(&raw_witness[..]);
+                } else {
+                    unsigned_data.put(raw_witness.as_ref());
+                }
+            } else if let Some(raw_witness) = witness_args
+                .output_type()
+                .to_opt()
+                .map(|witness_data| witness_data.raw_data())
+            {
+                output_witnesses.push((idx, witness_args, raw_witness));
+            }
+        }
+        for (idx, witness_args, raw_witness) in output_witnesses {
+            if idx == 0 {
+                entrance_witness = Some((witness_args, raw_witness.clone()));
+                let mut raw_witness = raw_witness.as_ref().to_vec();
+                // 4 bytes is for program length (u32)
+                raw_witness[4..4 + 65].copy_from_slice(&[0u8; 65][..]);
+                unsigned_data.put(&raw_witness[..]);
+            } else {
+                unsigned_data.put(raw_witness.as_ref());
+            }
+        }
+
+        let (entrance_witness_args, entrance_raw_witness) =
+            entrance_witness.ok_or_else(|| String::from("No entrance witness found"))?;
+        let message = secp256k1::Message::from_slice(&blake2b_256(&unsigned_data)[..])
+            .map_err(|err| err.to_string())?;
+        (message, entrance_witness_args, entrance_raw_witness)
+    };
+    let signature = SECP256K1.sign_recoverable(&message, privkey);
+    let (recov_id, data) = signature.serialize_compact();
+    let mut signature_bytes = [0u8; 65];
+    signature_bytes[0..64].copy_from_slice(&data[0..64]);
+    signature_bytes[64] = recov_id.to_i32() as u8;
+
+    let mut raw_witness = entrance_raw_witness.as_ref().to_vec();
+    raw_witness[4..4 + 65].copy_from_slice(&signature_bytes[..]);
+    let data = packed::BytesOpt::new_builder()
+        .set(Some(Bytes::from(raw_witness).pack()))
+        .build();
+    let witness = if entrance_witness_args.output_type().to_opt().is_some() {
+        entrance_witness_args.as_builder().output_type(data).build()
+    } else {
+        entrance_witness_args.as_builder().input_type(data).build()
+    };
+
+    tx.witnesses[0] = json_types::JsonBytes::from_bytes(witness.as_bytes());
+    Ok(())
+}
+
+pub fn get_live_cell(
+    client: &mut HttpRpcClient,
+    out_point: json_types::OutPoint,
+    with_data: bool,
+) -> Result<json_types::CellOutput, String> {
+    let cell = client.get_live_cell(out_point.clone(), with_data)?;
+    if cell.status != "live" {
+        return Err(format!(
+            "Invalid cell status: {}, out_point: {:?}",
+            cell.status, out_point
+        ));
+    }
+    let cell_status = cell.status.clone();
+    cell.cell.map(|cell| cell.output).ok_or_else(|| {
+        format!(
+            "Invalid input cell, status: {}, out_point: {:?}",
+            cell_status, out_point
+        )
+    })
+}
+
+pub fn serialize_signature(signature: &secp256k1::recovery::RecoverableSignature) -> [u8; 65] {
+    let (recov_id, data) = signature.serialize_compact();
+    let mut signature_bytes = [0u8; 65];
+    signature_bytes[0..64].copy_from_slice(&data[0..64]);
+    signature_bytes[64] = recov_id.to_i32() as u8;
+    signature_bytes
+}
+
+fn load_run_config(generator_path: &str, config_path: &str) -> Result<RunConfig, String> {
+    let generator = fs::read(generator_path)
+        .map(Bytes::from)
+        .map_err(|err| err.to_string())?;
+    let config_json: RunConfigJson = fs::read_to_string(config_path)
+        .map_err(|err| err.to_string())
+        .and_then(|json_string| {
+            serde_json::from_str(json_string.as_str()).map_err(|err| err.to_string())
+        })?;
+    if config_json.create_eoa_recipient && config_json.address_mode != AddressMode::Ethereum {
+        return Err(String::from(
+            "create_eoa_recipient requires the ethereum address mode",
+        ));
+    }
+    Ok(RunConfig {
+        generator,
+        type_dep: config_json.type_dep.into(),
+        type_script: config_json.type_script.into(),
+        lock_dep: config_json.lock_dep.into(),
+        lock_script: config_json.lock_script.into(),
+        eoa_lock_dep: config_json.eoa_lock_dep.into(),
+        eoa_lock_script: config_json.eoa_lock_script.into(),
+        address_mode: config_json.address_mode,
+        create_eoa_recipient: config_json.create_eoa_recipient,
+        chain_id: config_json.chain_id,
+    })
+}
+
+fn parse_address_mode(m: &ArgMatches) -> Result<AddressMode, String> {
+    let input = m.value_of("address-mode").unwrap();
+    serde_json::from_str(format!("\"{}\"", input).as_str()).map_err(|err| err.to_string())
+}
+
+fn parse_h160(input: &str) -> Result<H160, String> {
+    serde_json::from_str(format!("\"{}\"", input).as_str()).map_err(|err| err.to_string())
+}
+
+fn parse_h256(input: &str) -> Result<H256, String> {
+    serde_json::from_str(format!("\"{}\"", input).as_str()).map_err(|err| err.to_string())
+}
+
+fn parse_start_block(m: &ArgMatches) -> Result<Option<StartBlock>, String> {
+    if let Some(number) = m.value_of("start-block") {
+        let number = number.parse::<u64>().map_err(|err| err.to_string())?;
+        let hash = parse_h256(m.value_of("start-block-hash").unwrap())?;
+        Ok(Some(StartBlock { number, hash }))
+    } else {
+        Ok(None)
+    }
+}
+
+fn parse_processors(m: &ArgMatches) -> Result<Vec<Box<dyn BlockProcessor>>, String> {
+    m.values_of("processor")
+        .map(|names| names.map(create_processor).collect())
+        .unwrap_or_else(|| Ok(Vec::new()))
+}
+
+fn load_abi(path: &str) -> Result<Abi, String> {
+    fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|json_string| Abi::from_json(json_string.as_str()))
+}
+
+fn parse_hex_binary(input: &str) -> Result<Vec<u8>, String> {
+    hex::decode(input)
+        .map_err(|err| err.to_string())
+        .or_else(|_err| {
+            let content = fs::read_to_string(input).map_err(|err| err.to_string())?;
+            hex::decode(&content).map_err(|err| err.to_string())
+        })
+}
+
+// Can deploy those scripts by:
+//     ckb-cli wallet transfer --data-path xxx
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunConfigJson {
+    // Type script (Validator)
+    pub type_dep: json_types::CellDep,
+    pub type_script: json_types::Script,
+    // Lock script for contract (default always success)
+    pub lock_dep: json_types::CellDep,
+    pub lock_script: json_types::Script,
+    // Lock script for EoA account
+    pub eoa_lock_dep: json_types::CellDep,
+    pub eoa_lock_script: json_types::Script,
+    // The EoA address derivation: "ckb" (default) or "ethereum"
+    #[serde(default)]
+    pub address_mode: AddressMode,
+    // Create an EoA cell for the unknown recipient of a value transfer
+    // (Ethereum address mode only), otherwise the transfer is rejected
+    #[serde(default)]
+    pub create_eoa_recipient: bool,
+    // The chain id of the signed Ethereum transactions and `CHAINID` opcode
+    #[serde(default = "default_chain_id")]
+    pub chain_id: u64,
+}
+
+fn default_chain_id() -> u64 {
+    DEFAULT_CHAIN_ID
+}
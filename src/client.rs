@@ -1,8 +1,115 @@
 use ckb_jsonrpc_types::{
     BlockNumber, BlockView, CellWithStatus, ChainInfo, EpochNumber, EpochView, HeaderView,
-    OutPoint, TransactionWithStatus,
+    OutPoint, Transaction, TransactionWithStatus,
 };
 use ckb_types::H256;
+use serde::de::DeserializeOwned;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::metrics;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RpcError {
+    /// The CKB node url is invalid
+    InvalidUrl(String),
+    /// Connection failed or timeout, the request can be retried
+    Transport(String),
+    /// The CKB node returned an error response
+    Rpc(String),
+    /// The response can not be decoded
+    Decode(String),
+}
+
+impl RpcError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, RpcError::Transport(_))
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RpcError::InvalidUrl(err) => write!(f, "Invalid CKB rpc url: {}", err),
+            RpcError::Transport(err) => write!(f, "CKB rpc transport error: {}", err),
+            RpcError::Rpc(err) => write!(f, "CKB rpc error: {}", err),
+            RpcError::Decode(err) => write!(f, "CKB rpc decode error: {}", err),
+        }
+    }
+}
+
+impl StdError for RpcError {}
+
+impl From<RpcError> for String {
+    fn from(err: RpcError) -> String {
+        err.to_string()
+    }
+}
+
+impl From<reqwest::Error> for RpcError {
+    fn from(err: reqwest::Error) -> RpcError {
+        if err.is_decode() {
+            RpcError::Decode(err.to_string())
+        } else {
+            RpcError::Transport(err.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(err: serde_json::Error) -> RpcError {
+        RpcError::Decode(err.to_string())
+    }
+}
+
+/// The timeout, retry and failover policy of the CKB rpc client
+#[derive(Debug, Clone)]
+pub struct RpcPolicy {
+    /// Timeout of one http request
+    pub timeout: Duration,
+    /// Max retries of a request when the transport failed
+    pub max_retries: u32,
+    /// The backoff before first retry, doubled for each retry
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Consecutive failures before a node is marked unhealthy
+    pub max_failures: u32,
+    /// How long an unhealthy node is skipped
+    pub unhealthy_duration: Duration,
+}
+
+impl Default for RpcPolicy {
+    fn default() -> RpcPolicy {
+        RpcPolicy {
+            timeout: Duration::from_secs(30),
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            max_failures: 3,
+            unhealthy_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RpcPolicy {
+    /// Exponential backoff with jitter, the result is in range [backoff/2, backoff]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .checked_mul(1 << std::cmp::min(attempt, 16))
+            .map(|backoff| std::cmp::min(backoff, self.max_backoff))
+            .unwrap_or(self.max_backoff);
+        let half_millis = backoff.as_millis() as u64 / 2;
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.subsec_nanos() as u64)
+            .unwrap_or_default();
+        Duration::from_millis(half_millis + nanos % (half_millis + 1))
+    }
+}
 
 macro_rules! jsonrpc {
     (
@@ -21,14 +128,19 @@ macro_rules! jsonrpc {
         }
 
         impl $struct_name {
-            pub fn new(uri: &str) -> Self {
-                let url = reqwest::Url::parse(uri).expect("ckb uri, e.g. \"http://127.0.0.1:8114\"");
-                $struct_name { url, id: 0, client: reqwest::blocking::Client::new(), }
+            pub fn new(uri: &str, timeout: Duration) -> Result<Self, RpcError> {
+                let url = reqwest::Url::parse(uri)
+                    .map_err(|err| RpcError::InvalidUrl(format!("{}, e.g. \"http://127.0.0.1:8114\": {}", uri, err)))?;
+                let client = reqwest::blocking::Client::builder()
+                    .timeout(timeout)
+                    .build()
+                    .map_err(|err| RpcError::InvalidUrl(err.to_string()))?;
+                Ok($struct_name { url, id: 0, client })
             }
 
             $(
                 $(#[$attr])*
-                pub fn $method(&mut $selff $(, $arg_name: $arg_ty)*) -> Result<$return_ty, failure::Error> {
+                pub fn $method(&mut $selff $(, $arg_name: $arg_ty)*) -> Result<$return_ty, RpcError> {
                     let method = String::from(stringify!($method));
                     let params = serialize_parameters!($($arg_name,)*);
                     $selff.id += 1;
@@ -46,7 +158,7 @@ macro_rules! jsonrpc {
                             serde_json::from_value(success.result).map_err(Into::into)
                         },
                         ckb_jsonrpc_types::response::Output::Failure(failure) => {
-                            Err(failure.error.into())
+                            Err(RpcError::Rpc(failure.error.to_string()))
                         }
                     }
                 }
@@ -60,9 +172,11 @@ macro_rules! serialize_parameters {
     ($($arg_name:ident,)+) => ( serde_json::to_value(($($arg_name,)+))?)
 }
 
-jsonrpc!(pub struct RawHttpRpcClient {
+jsonrpc!(#[derive(Clone)]
+pub struct RawHttpRpcClient {
     // Chain
     pub fn get_block(&mut self, hash: H256) -> Option<BlockView>;
+    pub fn get_block_by_number(&mut self, number: BlockNumber) -> Option<BlockView>;
     pub fn get_epoch_by_number(&mut self, number: EpochNumber) -> Option<EpochView>;
     pub fn get_header(&mut self, hash: H256) -> Option<HeaderView>;
     pub fn get_header_by_number(&mut self, number: BlockNumber) -> Option<HeaderView>;
@@ -73,70 +187,533 @@ jsonrpc!(pub struct RawHttpRpcClient {
     pub fn get_blockchain_info(&mut self) -> ChainInfo;
 
     // Pool
+    pub fn send_transaction(&mut self, tx: Transaction) -> H256;
 });
 
-pub struct HttpRpcClient {
-    url: String,
-    client: RawHttpRpcClient,
+impl RawHttpRpcClient {
+    /// Send the requests in one JSON-RPC batch, the responses are correlated
+    /// by id and returned in the order of requests.
+    pub fn batch_request(
+        &mut self,
+        requests: Vec<(&str, serde_json::Value)>,
+    ) -> Result<Vec<Result<serde_json::Value, RpcError>>, RpcError> {
+        use ckb_jsonrpc_types::response::{Id, Output};
+
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+        let first_id = self.id + 1;
+        let count = requests.len() as u64;
+        let mut req_json = Vec::with_capacity(requests.len());
+        for (method, params) in requests {
+            self.id += 1;
+            req_json.push(serde_json::json!({
+                "id": self.id,
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": params,
+            }));
+        }
+
+        let resp = self.client.post(self.url.clone()).json(&req_json).send()?;
+        let outputs = resp.json::<Vec<Output>>()?;
+        let mut results: Vec<Option<Result<serde_json::Value, RpcError>>> =
+            (0..count).map(|_| None).collect();
+        for output in outputs {
+            let (id, result) = match output {
+                Output::Success(success) => (success.id, Ok(success.result)),
+                Output::Failure(failure) => {
+                    (failure.id, Err(RpcError::Rpc(failure.error.to_string())))
+                }
+            };
+            match id {
+                Id::Num(id) if id >= first_id && id < first_id + count => {
+                    results[(id - first_id) as usize] = Some(result);
+                }
+                _ => {
+                    return Err(RpcError::Decode(format!(
+                        "Unexpected response id: {:?}",
+                        id
+                    )));
+                }
+            }
+        }
+        results
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| {
+                result.ok_or_else(|| RpcError::Decode(format!("No response for request {}", index)))
+            })
+            .collect()
+    }
 }
 
-impl Clone for HttpRpcClient {
-    fn clone(&self) -> HttpRpcClient {
-        HttpRpcClient::new(self.url.clone())
+/// The access to a CKB chain. The indexer and loader only talk to CKB through
+/// it, so they can also run on the mock chain in tests.
+pub trait ChainClient: Send + Sync {
+    // Chain
+    fn get_block(&mut self, hash: H256) -> Result<Option<BlockView>, RpcError>;
+    fn get_block_by_number(&mut self, number: u64) -> Result<Option<BlockView>, RpcError>;
+    fn get_epoch_by_number(&mut self, number: u64) -> Result<Option<EpochView>, RpcError>;
+    fn get_header(&mut self, hash: H256) -> Result<Option<HeaderView>, RpcError>;
+    fn get_header_by_number(&mut self, number: u64) -> Result<Option<HeaderView>, RpcError>;
+    fn get_live_cell(
+        &mut self,
+        out_point: OutPoint,
+        with_data: bool,
+    ) -> Result<CellWithStatus, RpcError>;
+    fn get_tip_block_number(&mut self) -> Result<u64, RpcError>;
+    fn get_tip_header(&mut self) -> Result<HeaderView, RpcError>;
+    fn get_transaction(&mut self, hash: H256) -> Result<Option<TransactionWithStatus>, RpcError>;
+
+    // Pool
+    fn send_transaction(&mut self, tx: Transaction) -> Result<H256, RpcError>;
+
+    // Batch
+    fn get_blocks_by_number(
+        &mut self,
+        numbers: Vec<u64>,
+    ) -> Result<Vec<Option<BlockView>>, RpcError> {
+        numbers
+            .into_iter()
+            .map(|number| self.get_block_by_number(number))
+            .collect()
+    }
+    fn get_live_cells(
+        &mut self,
+        out_points: Vec<OutPoint>,
+        with_data: bool,
+    ) -> Result<Vec<CellWithStatus>, RpcError> {
+        out_points
+            .into_iter()
+            .map(|out_point| self.get_live_cell(out_point, with_data))
+            .collect()
     }
+    fn get_transactions(
+        &mut self,
+        hashes: Vec<H256>,
+    ) -> Result<Vec<Option<TransactionWithStatus>>, RpcError> {
+        hashes
+            .into_iter()
+            .map(|hash| self.get_transaction(hash))
+            .collect()
+    }
+
+    /// How long to wait before requesting again after a request failed
+    fn error_backoff(&self) -> Duration;
+
+    fn clone_box(&self) -> Box<dyn ChainClient>;
 }
 
-impl HttpRpcClient {
-    pub fn new(url: String) -> HttpRpcClient {
-        let client = RawHttpRpcClient::new(url.as_str());
-        HttpRpcClient { url, client }
+impl Clone for Box<dyn ChainClient> {
+    fn clone(&self) -> Box<dyn ChainClient> {
+        self.clone_box()
     }
 }
 
+#[derive(Debug, Clone, Default)]
+struct NodeHealth {
+    failures: u32,
+    unhealthy_until: Option<Instant>,
+}
+
+/// The CKB rpc client with retry and failover, the clones share the health
+/// state of the nodes.
+#[derive(Clone)]
+pub struct HttpRpcClient {
+    /// The clients of the node urls, in order of preference
+    clients: Vec<RawHttpRpcClient>,
+    health: Arc<Mutex<Vec<NodeHealth>>>,
+    policy: RpcPolicy,
+}
+
 impl HttpRpcClient {
+    pub fn new(urls: Vec<String>, policy: RpcPolicy) -> Result<HttpRpcClient, RpcError> {
+        if urls.is_empty() {
+            return Err(RpcError::InvalidUrl(String::from("No CKB rpc url")));
+        }
+        let clients = urls
+            .iter()
+            .map(|url| RawHttpRpcClient::new(url.as_str(), policy.timeout))
+            .collect::<Result<Vec<_>, _>>()?;
+        let health = Arc::new(Mutex::new(vec![NodeHealth::default(); clients.len()]));
+        Ok(HttpRpcClient {
+            clients,
+            health,
+            policy,
+        })
+    }
+
+    // The first healthy node, or the node will recover first
+    fn select_node(&self) -> usize {
+        let now = Instant::now();
+        let health = self.health.lock().expect("lock health");
+        health
+            .iter()
+            .position(|node| node.unhealthy_until.map(|until| until <= now) != Some(false))
+            .unwrap_or_else(|| {
+                health
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, node)| node.unhealthy_until)
+                    .map(|(index, _)| index)
+                    .unwrap_or(0)
+            })
+    }
+
+    fn update_health(&self, index: usize, success: bool) {
+        let mut health = self.health.lock().expect("lock health");
+        let node = &mut health[index];
+        if success {
+            node.failures = 0;
+            node.unhealthy_until = None;
+        } else {
+            node.failures += 1;
+            if node.failures >= self.policy.max_failures {
+                log::warn!(
+                    "CKB node {} is unhealthy after {} failures",
+                    self.clients[index].url,
+                    node.failures
+                );
+                node.unhealthy_until = Some(Instant::now() + self.policy.unhealthy_duration);
+            }
+        }
+    }
+
+    // Send the request to the selected node, retry (maybe on other node) when
+    // the transport failed.
+    fn request<T, F>(&mut self, f: F) -> Result<T, RpcError>
+    where
+        F: FnMut(&mut RawHttpRpcClient) -> Result<T, RpcError>,
+    {
+        let max_retries = self.policy.max_retries;
+        self.request_with_retries(max_retries, f)
+    }
+
+    fn request_with_retries<T, F>(&mut self, max_retries: u32, mut f: F) -> Result<T, RpcError>
+    where
+        F: FnMut(&mut RawHttpRpcClient) -> Result<T, RpcError>,
+    {
+        let mut attempt = 0;
+        loop {
+            let index = self.select_node();
+            match f(&mut self.clients[index]) {
+                Err(err) if err.is_retryable() => {
+                    self.update_health(index, false);
+                    if attempt >= max_retries {
+                        metrics::CKB_RPC_ERRORS.inc();
+                        return Err(err);
+                    }
+                    let backoff = self.policy.backoff(attempt);
+                    log::debug!(
+                        "Retry CKB rpc request in {:?}, attempt={}, error: {}",
+                        backoff,
+                        attempt + 1,
+                        err
+                    );
+                    sleep(backoff);
+                    metrics::CKB_RPC_RETRIES.inc();
+                    attempt += 1;
+                }
+                result => {
+                    // The node is responsive even it returns an error response
+                    self.update_health(index, true);
+                    if result.is_err() {
+                        metrics::CKB_RPC_ERRORS.inc();
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+impl ChainClient for HttpRpcClient {
     // Chain
-    pub fn get_block(&mut self, hash: H256) -> Result<Option<BlockView>, String> {
-        self.client.get_block(hash).map_err(|err| err.to_string())
+    fn get_block(&mut self, hash: H256) -> Result<Option<BlockView>, RpcError> {
+        self.request(|client| client.get_block(hash.clone()))
     }
-    pub fn get_epoch_by_number(&mut self, number: u64) -> Result<Option<EpochView>, String> {
-        self.client
-            .get_epoch_by_number(EpochNumber::from(number))
-            .map_err(|err| err.to_string())
+    fn get_block_by_number(&mut self, number: u64) -> Result<Option<BlockView>, RpcError> {
+        self.request(|client| client.get_block_by_number(BlockNumber::from(number)))
     }
-    pub fn get_header(&mut self, hash: H256) -> Result<Option<HeaderView>, String> {
-        self.client.get_header(hash).map_err(|err| err.to_string())
+    fn get_epoch_by_number(&mut self, number: u64) -> Result<Option<EpochView>, RpcError> {
+        self.request(|client| client.get_epoch_by_number(EpochNumber::from(number)))
     }
-    pub fn get_header_by_number(&mut self, number: u64) -> Result<Option<HeaderView>, String> {
-        self.client
-            .get_header_by_number(BlockNumber::from(number))
-            .map_err(|err| err.to_string())
+    fn get_header(&mut self, hash: H256) -> Result<Option<HeaderView>, RpcError> {
+        self.request(|client| client.get_header(hash.clone()))
     }
-    pub fn get_live_cell(
+    fn get_header_by_number(&mut self, number: u64) -> Result<Option<HeaderView>, RpcError> {
+        self.request(|client| client.get_header_by_number(BlockNumber::from(number)))
+    }
+    fn get_live_cell(
         &mut self,
         out_point: OutPoint,
         with_data: bool,
-    ) -> Result<CellWithStatus, String> {
-        self.client
-            .get_live_cell(out_point, with_data)
-            .map_err(|err| err.to_string())
-    }
-    pub fn get_tip_block_number(&mut self) -> Result<u64, String> {
-        self.client
-            .get_tip_block_number()
+    ) -> Result<CellWithStatus, RpcError> {
+        self.request(|client| client.get_live_cell(out_point.clone(), with_data))
+    }
+    fn get_tip_block_number(&mut self) -> Result<u64, RpcError> {
+        self.request(|client| client.get_tip_block_number())
             .map(|number| number.value())
-            .map_err(|err| err.to_string())
     }
-    pub fn get_tip_header(&mut self) -> Result<HeaderView, String> {
-        self.client.get_tip_header().map_err(|err| err.to_string())
+    fn get_tip_header(&mut self) -> Result<HeaderView, RpcError> {
+        self.request(|client| client.get_tip_header())
+    }
+    fn get_transaction(&mut self, hash: H256) -> Result<Option<TransactionWithStatus>, RpcError> {
+        self.request(|client| client.get_transaction(hash.clone()))
+    }
+
+    // Pool
+    fn send_transaction(&mut self, tx: Transaction) -> Result<H256, RpcError> {
+        // Never retried, the transaction may already be accepted by the node
+        // when the transport failed.
+        self.request_with_retries(0, |client| client.send_transaction(tx.clone()))
+    }
+
+    // Batch
+    fn get_blocks_by_number(
+        &mut self,
+        numbers: Vec<u64>,
+    ) -> Result<Vec<Option<BlockView>>, RpcError> {
+        let params = numbers
+            .into_iter()
+            .map(|number| serde_json::json!([BlockNumber::from(number)]))
+            .collect();
+        self.batch("get_block_by_number", params)
+    }
+    fn get_live_cells(
+        &mut self,
+        out_points: Vec<OutPoint>,
+        with_data: bool,
+    ) -> Result<Vec<CellWithStatus>, RpcError> {
+        let params = out_points
+            .into_iter()
+            .map(|out_point| serde_json::json!([out_point, with_data]))
+            .collect();
+        self.batch("get_live_cell", params)
+    }
+    fn get_transactions(
+        &mut self,
+        hashes: Vec<H256>,
+    ) -> Result<Vec<Option<TransactionWithStatus>>, RpcError> {
+        let params = hashes
+            .into_iter()
+            .map(|hash| serde_json::json!([hash]))
+            .collect();
+        self.batch("get_transaction", params)
+    }
+
+    fn error_backoff(&self) -> Duration {
+        self.policy.max_backoff
+    }
+
+    fn clone_box(&self) -> Box<dyn ChainClient> {
+        Box::new(self.clone())
+    }
+}
+
+impl HttpRpcClient {
+    pub fn get_blockchain_info(&mut self) -> Result<ChainInfo, RpcError> {
+        self.request(|client| client.get_blockchain_info())
+    }
+
+    fn batch<T: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: Vec<serde_json::Value>,
+    ) -> Result<Vec<T>, RpcError> {
+        let requests = params
+            .into_iter()
+            .map(|params| (method, params))
+            .collect::<Vec<_>>();
+        self.request(|client| client.batch_request(requests.clone()))?
+            .into_iter()
+            .map(|result| {
+                result.and_then(|value| serde_json::from_value(value).map_err(Into::into))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::packed;
+    use serde_json::{json, Value};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    /// Serve the JSON-RPC requests by the handler, the connection is closed
+    /// without response when the handler returns None.
+    fn fake_node<F>(handler: F) -> String
+    where
+        F: Fn(Value) -> Option<Value> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => break,
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    if line.to_lowercase().starts_with("content-length:") {
+                        content_length = line["content-length:".len()..].trim().parse().unwrap();
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0u8; content_length];
+                reader.read_exact(&mut body).unwrap();
+                if let Some(response) = handler(serde_json::from_slice(&body).unwrap()) {
+                    let response = response.to_string();
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        response.len(),
+                        response
+                    )
+                    .unwrap();
+                }
+            }
+        });
+        url
+    }
+
+    fn success(id: &Value, result: Value) -> Value {
+        json!({"jsonrpc": "2.0", "id": id, "result": result})
+    }
+
+    #[test]
+    fn test_batch_request() {
+        // Respond in reversed order, the parameter "0x2" is rejected
+        let url = fake_node(|request| {
+            let outputs = request
+                .as_array()
+                .unwrap()
+                .iter()
+                .rev()
+                .map(|req| {
+                    if req["params"][0] == "0x2" {
+                        json!({
+                            "jsonrpc": "2.0",
+                            "id": req["id"],
+                            "error": {"code": -1, "message": "bad param"},
+                        })
+                    } else if req["method"] == "echo" {
+                        success(&req["id"], req["params"][0].clone())
+                    } else {
+                        success(&req["id"], Value::Null)
+                    }
+                })
+                .collect();
+            Some(Value::Array(outputs))
+        });
+        let mut client = RawHttpRpcClient::new(&url, Duration::from_secs(5)).unwrap();
+        assert_eq!(client.batch_request(Vec::new()), Ok(Vec::new()));
+        for _ in 0..2 {
+            let results = client
+                .batch_request(vec![
+                    ("echo", json!(["0x1"])),
+                    ("echo", json!(["0x2"])),
+                    ("echo", json!(["0x3"])),
+                ])
+                .unwrap();
+            assert_eq!(results[0], Ok(json!("0x1")));
+            assert!(matches!(results[1], Err(RpcError::Rpc(_))));
+            assert_eq!(results[2], Ok(json!("0x3")));
+        }
+
+        let mut client = HttpRpcClient::new(vec![url], RpcPolicy::default()).unwrap();
+        let blocks = client.get_blocks_by_number(vec![1, 3]).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks.iter().all(Option::is_none));
+        assert!(matches!(
+            client.get_blocks_by_number(vec![2]),
+            Err(RpcError::Rpc(_))
+        ));
+
+        // The responses not match the requests
+        let url = fake_node(|_| Some(json!([success(&json!(100), Value::Null)])));
+        let mut client = RawHttpRpcClient::new(&url, Duration::from_secs(5)).unwrap();
+        assert!(matches!(
+            client.batch_request(vec![("echo", json!(["0x1"]))]),
+            Err(RpcError::Decode(_))
+        ));
+        let url = fake_node(|_| Some(json!([])));
+        let mut client = RawHttpRpcClient::new(&url, Duration::from_secs(5)).unwrap();
+        assert!(matches!(
+            client.batch_request(vec![("echo", json!(["0x1"]))]),
+            Err(RpcError::Decode(_))
+        ));
     }
-    pub fn get_transaction(&mut self, hash: H256) -> Result<Option<TransactionWithStatus>, String> {
-        self.client
-            .get_transaction(hash)
-            .map_err(|err| err.to_string())
+
+    #[test]
+    fn test_rpc_policy_backoff() {
+        let policy = RpcPolicy::default();
+        for attempt in 0..20 {
+            let backoff = policy.backoff(attempt);
+            assert!(backoff <= policy.max_backoff);
+            assert!(backoff >= policy.initial_backoff / 2);
+        }
+        // Doubled for each retry until reach the max backoff
+        let backoff = policy.backoff(3);
+        assert!(backoff >= policy.initial_backoff * 4);
+        assert!(backoff <= policy.initial_backoff * 8);
+        assert!(policy.backoff(20) >= policy.max_backoff / 2);
+        assert!(HttpRpcClient::new(Vec::new(), policy.clone()).is_err());
+        assert!(matches!(
+            HttpRpcClient::new(vec![String::from("not a url")], policy).err(),
+            Some(RpcError::InvalidUrl(_))
+        ));
     }
-    pub fn get_blockchain_info(&mut self) -> Result<ChainInfo, String> {
-        self.client
-            .get_blockchain_info()
-            .map_err(|err| err.to_string())
+
+    #[test]
+    fn test_rpc_failover() {
+        let policy = RpcPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            max_failures: 1,
+            ..Default::default()
+        };
+        let dead_hits = Arc::new(AtomicUsize::new(0));
+        let dead_node = {
+            let dead_hits = Arc::clone(&dead_hits);
+            fake_node(move |_| {
+                dead_hits.fetch_add(1, Ordering::SeqCst);
+                None
+            })
+        };
+        let live_node = fake_node(|request| Some(success(&request["id"], json!("0x5"))));
+
+        // Retry on the next node when the transport failed
+        let mut client =
+            HttpRpcClient::new(vec![dead_node.clone(), live_node], policy.clone()).unwrap();
+        assert_eq!(client.get_tip_block_number(), Ok(5));
+        assert_eq!(dead_hits.load(Ordering::SeqCst), 1);
+        // The unhealthy node is skipped, also by the clones
+        assert_eq!(client.clone().get_tip_block_number(), Ok(5));
+        assert_eq!(dead_hits.load(Ordering::SeqCst), 1);
+
+        // Give up after max retries
+        let mut client = HttpRpcClient::new(vec![dead_node], policy).unwrap();
+        assert!(matches!(
+            client.get_tip_block_number(),
+            Err(RpcError::Transport(_))
+        ));
+        assert_eq!(dead_hits.load(Ordering::SeqCst), 4);
+        // The transaction is never sent twice
+        let tx = Transaction::from(packed::Transaction::default());
+        assert!(matches!(
+            client.send_transaction(tx),
+            Err(RpcError::Transport(_))
+        ));
+        assert_eq!(dead_hits.load(Ordering::SeqCst), 5);
     }
 }
@@ -0,0 +1,472 @@
+use ckb_chain_spec::consensus::TYPE_ID_CODE_HASH;
+use ckb_hash::blake2b_256;
+use ckb_jsonrpc_types::{
+    BlockView, CellWithStatus, EpochView, HeaderView, OutPoint, Transaction, TransactionWithStatus,
+};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, DepType, ScriptHashType, TransactionBuilder},
+    packed,
+    prelude::*,
+    H160, H256,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cli::{entrance_witness_data, sign_program_witness};
+use crate::client::{ChainClient, RpcError};
+use crate::mock_chain::MockChain;
+use crate::types::{
+    AddressMode, RunConfig, ALWAYS_SUCCESS_SCRIPT, DEFAULT_CHAIN_ID, ONE_CKB, SECP256K1,
+};
+
+/// The always success lock of contract cells (the same binary in README)
+const ALWAYS_SUCCESS_BINARY: &str = "7f454c460201010000000000000000000200f3000100000078000100000000004000000000000000980000000000000005000000400038000100400003000200010000000500000000000000000000000000010000000000000001000000000082000000000000008200000000000000001000000000000001459308d00573000000002e7368737472746162002e74657874000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000b000000010000000600000000000000780001000000000078000000000000000a0000000000000000000000000000000200000000000000000000000000000001000000030000000000000000000000000000000000000082000000000000001100000000000000000000000000000001000000000000000000000000000000";
+/// The lock of EoA cells
+const ANYONE_CAN_PAY_BINARY: &[u8] = include_bytes!("../tests/anyone_can_pay");
+
+/// A pre-funded EoA account of the dev chain
+pub struct DevAccount {
+    pub privkey: secp256k1::SecretKey,
+    pub lock_arg: H160,
+    pub eoa_address: H160,
+    type_args: Bytes,
+}
+
+impl DevAccount {
+    /// The private key is derived from the index, so the accounts are the same
+    /// in every run.
+    pub fn new(index: usize, address_mode: AddressMode) -> DevAccount {
+        let seed = format!("polyjuice-dev-account-{}", index);
+        let privkey =
+            secp256k1::SecretKey::from_slice(&blake2b_256(seed.as_bytes())).expect("dev privkey");
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        let lock_arg = address_mode.lock_args(&pubkey);
+        let type_args = Bytes::from(blake2b_256(lock_arg.as_bytes()).to_vec());
+        let eoa_address = address_mode.eoa_address(&type_args, lock_arg.as_bytes());
+        DevAccount {
+            privkey,
+            lock_arg,
+            eoa_address,
+            type_args,
+        }
+    }
+}
+
+/// The simulated chain of `polyjuice dev`. The transactions of the dev
+/// accounts are signed when submitted, and committed in a new block instantly.
+#[derive(Clone)]
+pub struct DevChain {
+    chain: MockChain,
+    /// EoA address => private key
+    privkeys: Arc<HashMap<H160, secp256k1::SecretKey>>,
+}
+
+impl DevChain {
+    /// Deploy the scripts and fund the accounts in genesis block, return the
+    /// chain and the config to run polyjuice on it.
+    pub fn new(
+        generator: Bytes,
+        validator: Bytes,
+        accounts: &[DevAccount],
+        balance: u64,
+        address_mode: AddressMode,
+    ) -> (DevChain, RunConfig) {
+        let always_success = Bytes::from(hex::decode(ALWAYS_SUCCESS_BINARY).unwrap());
+        let anyone_can_pay = Bytes::from(ANYONE_CAN_PAY_BINARY);
+        let type_script = packed::Script::new_builder()
+            .code_hash(packed::CellOutput::calc_data_hash(&validator))
+            .hash_type(ScriptHashType::Data.into())
+            .build();
+        let eoa_lock_script = packed::Script::new_builder()
+            .code_hash(packed::CellOutput::calc_data_hash(&anyone_can_pay))
+            .hash_type(ScriptHashType::Data.into())
+            .build();
+
+        let mut genesis_tx = TransactionBuilder::default();
+        for data in vec![validator, always_success, anyone_can_pay] {
+            let output = packed::CellOutput::default();
+            let capacity = output
+                .occupied_capacity(Capacity::bytes(data.len()).expect("capacity"))
+                .expect("capacity");
+            genesis_tx = genesis_tx
+                .output(output.as_builder().capacity(capacity.pack()).build())
+                .output_data(data.pack());
+        }
+        for account in accounts {
+            let type_script = packed::Script::new_builder()
+                .code_hash(TYPE_ID_CODE_HASH.pack())
+                .hash_type(ScriptHashType::Type.into())
+                .args(account.type_args.pack())
+                .build();
+            let lock_script = eoa_lock_script
+                .clone()
+                .as_builder()
+                .args(Bytes::from(account.lock_arg.as_bytes().to_vec()).pack())
+                .build();
+            // Same as `new-eoa-account`
+            let capacity = balance + ONE_CKB * (8 + (32 + 1 + 32) + (32 + 1 + 20));
+            let output = packed::CellOutput::new_builder()
+                .capacity(capacity.pack())
+                .lock(lock_script)
+                .type_(
+                    packed::ScriptOpt::new_builder()
+                        .set(Some(type_script))
+                        .build(),
+                )
+                .build();
+            genesis_tx = genesis_tx.output(output).output_data(Bytes::new().pack());
+        }
+        let genesis_tx = genesis_tx.build();
+        let cell_dep = |index: u32| {
+            packed::CellDep::new_builder()
+                .out_point(packed::OutPoint::new(genesis_tx.hash(), index))
+                .dep_type(DepType::Code.into())
+                .build()
+        };
+        let run_config = RunConfig {
+            generator,
+            type_dep: cell_dep(0),
+            type_script,
+            lock_dep: cell_dep(1),
+            lock_script: ALWAYS_SUCCESS_SCRIPT.clone(),
+            eoa_lock_dep: cell_dep(2),
+            eoa_lock_script,
+            address_mode,
+            create_eoa_recipient: address_mode == AddressMode::Ethereum,
+            chain_id: DEFAULT_CHAIN_ID,
+        };
+        let privkeys = accounts
+            .iter()
+            .map(|account| (account.eoa_address.clone(), account.privkey))
+            .collect();
+        let chain = DevChain {
+            chain: MockChain::new(vec![genesis_tx]),
+            privkeys: Arc::new(privkeys),
+        };
+        (chain, run_config)
+    }
+
+    // The private key of tx_origin if the entrance program is not signed yet
+    fn unsigned_origin(&self, tx: &Transaction) -> Option<secp256k1::SecretKey> {
+        let witness_data = entrance_witness_data(tx)?;
+        if witness_data.signature.iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        self.privkeys
+            .get(&witness_data.program.tx_origin.0)
+            .cloned()
+    }
+}
+
+impl ChainClient for DevChain {
+    fn get_block(&mut self, hash: H256) -> Result<Option<BlockView>, RpcError> {
+        self.chain.get_block(hash)
+    }
+    fn get_block_by_number(&mut self, number: u64) -> Result<Option<BlockView>, RpcError> {
+        self.chain.get_block_by_number(number)
+    }
+    fn get_epoch_by_number(&mut self, number: u64) -> Result<Option<EpochView>, RpcError> {
+        self.chain.get_epoch_by_number(number)
+    }
+    fn get_header(&mut self, hash: H256) -> Result<Option<HeaderView>, RpcError> {
+        self.chain.get_header(hash)
+    }
+    fn get_header_by_number(&mut self, number: u64) -> Result<Option<HeaderView>, RpcError> {
+        self.chain.get_header_by_number(number)
+    }
+    fn get_live_cell(
+        &mut self,
+        out_point: OutPoint,
+        with_data: bool,
+    ) -> Result<CellWithStatus, RpcError> {
+        self.chain.get_live_cell(out_point, with_data)
+    }
+    fn get_tip_block_number(&mut self) -> Result<u64, RpcError> {
+        self.chain.get_tip_block_number()
+    }
+    fn get_tip_header(&mut self) -> Result<HeaderView, RpcError> {
+        self.chain.get_tip_header()
+    }
+    fn get_transaction(&mut self, hash: H256) -> Result<Option<TransactionWithStatus>, RpcError> {
+        self.chain.get_transaction(hash)
+    }
+
+    fn send_transaction(&mut self, mut tx: Transaction) -> Result<H256, RpcError> {
+        if let Some(privkey) = self.unsigned_origin(&tx) {
+            sign_program_witness(&mut tx, &privkey).map_err(RpcError::Rpc)?;
+        }
+        let tx_hash = self.chain.send_transaction(tx)?;
+        let block = self.chain.mine_block();
+        log::info!(
+            "Dev chain produced block {}, tx_hash={:#x}",
+            block.number(),
+            tx_hash
+        );
+        Ok(tx_hash)
+    }
+
+    fn error_backoff(&self) -> Duration {
+        self.chain.error_backoff()
+    }
+
+    fn clone_box(&self) -> Box<dyn ChainClient> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::Abi;
+    use crate::eth_tx::{eoa_cell_data, EthTransaction};
+    use crate::server::{Rpc, RpcImpl, TransactionReceipt};
+    use crate::storage::{Indexer, Loader, Runner};
+    use crate::types::{ContractAddress, ALWAYS_SUCCESS_CODE_HASH};
+    use ckb_jsonrpc_types::JsonBytes;
+    use rocksdb::DB;
+    use std::fs;
+
+    #[test]
+    fn test_dev_chain_genesis() {
+        let always_success = hex::decode(ALWAYS_SUCCESS_BINARY).unwrap();
+        assert_eq!(
+            packed::CellOutput::calc_data_hash(&always_success),
+            ALWAYS_SUCCESS_CODE_HASH.pack()
+        );
+
+        for address_mode in vec![AddressMode::Ckb, AddressMode::Ethereum] {
+            let accounts = (0..2)
+                .map(|index| DevAccount::new(index, address_mode))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                DevAccount::new(1, address_mode).eoa_address,
+                accounts[1].eoa_address
+            );
+            if address_mode == AddressMode::Ethereum {
+                assert_eq!(accounts[0].eoa_address, accounts[0].lock_arg);
+            }
+            let (chain, run_config) = DevChain::new(
+                Bytes::from(vec![0x01]),
+                Bytes::from(vec![0x02]),
+                &accounts,
+                100 * ONE_CKB,
+                address_mode,
+            );
+            let dir = tempfile::tempdir().unwrap();
+            let db = Arc::new(DB::open_default(dir.path()).unwrap());
+            let mut indexer = Indexer::new(Arc::clone(&db), Box::new(chain), run_config, 10);
+            indexer.stop_at_tip = true;
+            indexer.index().unwrap();
+            for account in &accounts {
+                let cell = indexer
+                    .loader
+                    .load_eoa_live_cell_value(account.eoa_address.clone())
+                    .unwrap();
+                assert_eq!(cell.lock_args, account.lock_arg);
+                assert_eq!(cell.balance(), 100 * ONE_CKB);
+            }
+        }
+    }
+
+    fn read_binary(path: &str) -> Bytes {
+        fs::read(path)
+            .map(Bytes::from)
+            .unwrap_or_else(|err| panic!("{}: {}, run `make -C c all-via-docker` first", path, err))
+    }
+
+    fn load_contract(name: &str) -> Bytes {
+        let path = format!("tests/evm-contracts/{}.bin", name);
+        let code = fs::read_to_string(path).unwrap();
+        Bytes::from(hex::decode(code.trim()).unwrap())
+    }
+
+    fn commit(rpc: &RpcImpl, indexer: &mut Indexer, receipt: TransactionReceipt) {
+        rpc.send_transaction(receipt.tx).unwrap();
+        indexer.index().unwrap();
+    }
+
+    fn static_call(rpc: &RpcImpl, sender: &H160, address: &ContractAddress, input: &str) -> Bytes {
+        let loader = Loader::clone(&rpc.loader);
+        Runner::new(loader, rpc.run_config.clone())
+            .static_call(
+                sender.clone(),
+                address.clone(),
+                Bytes::from(hex::decode(input).unwrap()),
+            )
+            .unwrap()
+            .entrance_info()
+            .return_data()
+    }
+
+    fn uint256(value: u8) -> Bytes {
+        let mut data = vec![0u8; 32];
+        data[31] = value;
+        Bytes::from(data)
+    }
+
+    /// The dev chain with one account running the generator and validator
+    /// binaries, returns (chain, indexer, rpc, account).
+    fn setup_dev_chain(
+        dir: &tempfile::TempDir,
+        address_mode: AddressMode,
+    ) -> (DevChain, Indexer, RpcImpl, DevAccount) {
+        let account = DevAccount::new(0, address_mode);
+        let validator = match address_mode {
+            AddressMode::Ckb => "c/build/validator",
+            AddressMode::Ethereum => "c/build/validator_eth",
+        };
+        let (chain, run_config) = DevChain::new(
+            read_binary("c/build/generator"),
+            read_binary(validator),
+            &[DevAccount::new(0, address_mode)],
+            1000 * ONE_CKB,
+            address_mode,
+        );
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let mut indexer = Indexer::new(db, Box::new(chain.clone()), run_config.clone(), 10);
+        indexer.stop_at_tip = true;
+        indexer.index().unwrap();
+        let rpc = RpcImpl {
+            loader: Arc::new(indexer.loader.clone()),
+            run_config,
+            max_lag: None,
+        };
+        (chain, indexer, rpc, account)
+    }
+
+    /// Deploy and call the contract in tests/evm-contracts on the dev chain, the
+    /// generator and validator binaries are required.
+    #[test]
+    #[ignore]
+    fn test_evm_contract_on_dev_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let (chain, mut indexer, rpc, account) = setup_dev_chain(&dir, AddressMode::Ckb);
+        let sender = account.eoa_address;
+        let get_input = "6d4ce63c";
+
+        let code = JsonBytes::from_bytes(load_contract("SimpleStorage"));
+        let receipt = rpc.create(sender.clone(), code, 0).unwrap();
+        let address = receipt.created_addresses[0].clone();
+        commit(&rpc, &mut indexer, receipt);
+        assert_eq!(
+            static_call(&rpc, &sender, &address, get_input),
+            uint256(123)
+        );
+        let created_number = chain.chain.tip().number();
+
+        // set(13)
+        let mut set_input = hex::decode("60fe47b1").unwrap();
+        set_input.extend_from_slice(&uint256(13));
+        let set_input = JsonBytes::from_vec(set_input);
+        let receipt = rpc
+            .call(sender.clone(), address.clone(), set_input, 0)
+            .unwrap();
+        commit(&rpc, &mut indexer, receipt);
+        assert_eq!(static_call(&rpc, &sender, &address, get_input), uint256(13));
+
+        // The call is rolled back by the reorg, then committed again
+        chain
+            .chain
+            .reorg(created_number, vec![Vec::new(), Vec::new()]);
+        indexer.index().unwrap();
+        assert_eq!(
+            static_call(&rpc, &sender, &address, get_input),
+            uint256(123)
+        );
+        chain.chain.mine_block();
+        indexer.index().unwrap();
+        assert_eq!(static_call(&rpc, &sender, &address, get_input), uint256(13));
+    }
+
+    /// The capacity of the contract selfdestructed to itself is refunded to
+    /// tx_origin, and selfdestruct to an unknown beneficiary is rejected.
+    #[test]
+    #[ignore]
+    fn test_selfdestruct_on_dev_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_chain, mut indexer, rpc, account) = setup_dev_chain(&dir, AddressMode::Ckb);
+        let sender = account.eoa_address;
+        let abi = fs::read_to_string("tests/evm-contracts/SelfDestruct.abi").unwrap();
+        let done_input = Abi::from_json(&abi)
+            .unwrap()
+            .function("done")
+            .unwrap()
+            .encode_input(&[])
+            .unwrap();
+        let done_input = JsonBytes::from_bytes(done_input);
+        // constructor(address owner)
+        let deploy = |owner: &H160| {
+            let mut code = load_contract("SelfDestruct").to_vec();
+            code.extend_from_slice(&[0u8; 12]);
+            code.extend_from_slice(owner.as_bytes());
+            rpc.create(sender.clone(), JsonBytes::from_vec(code), 0)
+                .unwrap()
+        };
+
+        // The contract address only depends on the inputs of the transaction
+        let address = deploy(&H160::default()).created_addresses[0].clone();
+        let receipt = deploy(&address.0);
+        assert_eq!(receipt.created_addresses[0], address);
+        commit(&rpc, &mut indexer, receipt);
+        let balance = rpc.get_balance(sender.clone()).unwrap();
+        let capacity = rpc
+            .loader
+            .load_latest_contract_state(address.clone())
+            .unwrap()
+            .capacity;
+        let receipt = rpc
+            .call(sender.clone(), address.clone(), done_input.clone(), 0)
+            .unwrap();
+        assert_eq!(receipt.destructed_addresses, vec![address]);
+        commit(&rpc, &mut indexer, receipt);
+        assert_eq!(
+            rpc.get_balance(sender.clone()).unwrap(),
+            balance - ONE_CKB + capacity
+        );
+
+        let receipt = deploy(&H160::from_slice(&[0xde; 20]).unwrap());
+        let address = receipt.created_addresses[0].clone();
+        commit(&rpc, &mut indexer, receipt);
+        assert!(rpc.call(sender, address, done_input, 0).is_err());
+    }
+
+    /// The signed Ethereum transaction is committed without the auto-signing of
+    /// the dev chain: the entrance program carries the signature of the eth
+    /// transaction, and the next nonce is saved in the tx_origin cell.
+    #[test]
+    #[ignore]
+    fn test_raw_transaction_on_dev_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let (chain, mut indexer, rpc, account) = setup_dev_chain(&dir, AddressMode::Ethereum);
+        let code = load_contract("SimpleStorage");
+        let sign = |nonce: u64, chain_id: u64| {
+            EthTransaction::sign(nonce, None, 0, code.clone(), chain_id, &account.privkey)
+        };
+        let mut mock_chain = chain.chain.clone();
+
+        for nonce in 0..2 {
+            let eth_tx = sign(nonce, DEFAULT_CHAIN_ID);
+            let raw_tx = JsonBytes::from_bytes(eth_tx.raw.clone());
+            let receipt = rpc.raw_transaction(raw_tx.clone()).unwrap();
+            let witness_data = entrance_witness_data(&receipt.tx).unwrap();
+            assert_eq!(witness_data.signature, eth_tx.signature);
+            let pubkey = eth_tx.recover_pubkey().unwrap();
+            assert_eq!(AddressMode::Ethereum.lock_args(&pubkey), account.lock_arg);
+            mock_chain.send_transaction(receipt.tx).unwrap();
+            mock_chain.mine_block();
+            indexer.index().unwrap();
+
+            let (_, _, data) = indexer
+                .loader
+                .load_eoa_live_cell(account.eoa_address.clone())
+                .unwrap();
+            assert_eq!(data, eoa_cell_data(nonce + 1));
+            // The replayed transaction is rejected
+            assert!(rpc.raw_transaction(raw_tx).is_err());
+        }
+        let raw_tx = JsonBytes::from_bytes(sign(2, DEFAULT_CHAIN_ID + 1).raw);
+        assert!(rpc.raw_transaction(raw_tx).is_err());
+    }
+}
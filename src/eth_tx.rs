@@ -0,0 +1,390 @@
+use ckb_types::{
+    bytes::{BufMut, Bytes, BytesMut},
+    H160,
+};
+use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+use tiny_keccak::keccak256;
+
+use crate::types::SECP256K1;
+
+/// The value of Ethereum transactions is in wei, 1 CKB (1e8 shannons) is
+/// mapped to 1 ether (1e18 wei), so 1 shannon is 1e10 wei.
+pub const WEI_PER_SHANNON: u128 = 10_000_000_000;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Rlp {
+    Bytes(Bytes),
+    List(Vec<Rlp>),
+}
+
+impl Rlp {
+    fn into_bytes(self) -> Result<Bytes, String> {
+        match self {
+            Rlp::Bytes(data) => Ok(data),
+            Rlp::List(_) => Err(String::from("Unexpected rlp list")),
+        }
+    }
+}
+
+fn rlp_length(data: &[u8], offset: usize, len_of_len: usize) -> Result<(usize, usize), String> {
+    let len_bytes = data
+        .get(offset..offset + len_of_len)
+        .ok_or_else(|| String::from("Invalid rlp length"))?;
+    if len_of_len > 8 || len_bytes.first() == Some(&0) {
+        return Err(String::from("Invalid rlp length"));
+    }
+    let len = len_bytes
+        .iter()
+        .fold(0u64, |len, byte| (len << 8) | u64::from(*byte));
+    Ok((offset + len_of_len, len as usize))
+}
+
+/// Decode a rlp item, return the item and the length consumed. The
+/// non-canonical encodings are rejected (the same as the validator).
+fn rlp_decode(data: &[u8]) -> Result<(Rlp, usize), String> {
+    let prefix = *data.first().ok_or_else(|| String::from("Empty rlp data"))?;
+    let (start, len, is_list) = match prefix {
+        0x00..=0x7f => return Ok((Rlp::Bytes(Bytes::from(vec![prefix])), 1)),
+        0x80..=0xb7 => (1, (prefix - 0x80) as usize, false),
+        0xb8..=0xbf => {
+            let (start, len) = rlp_length(data, 1, (prefix - 0xb7) as usize)?;
+            (start, len, false)
+        }
+        0xc0..=0xf7 => (1, (prefix - 0xc0) as usize, true),
+        0xf8..=0xff => {
+            let (start, len) = rlp_length(data, 1, (prefix - 0xf7) as usize)?;
+            (start, len, true)
+        }
+    };
+    if (prefix == 0x81 && data.get(1).map_or(false, |byte| *byte < 0x80)) || (start > 1 && len < 56)
+    {
+        return Err(String::from("Non-canonical rlp"));
+    }
+    let end = start
+        .checked_add(len)
+        .filter(|end| *end <= data.len())
+        .ok_or_else(|| String::from("Rlp data too short"))?;
+    let payload = &data[start..end];
+    if !is_list {
+        return Ok((Rlp::Bytes(Bytes::from(payload.to_vec())), end));
+    }
+    let mut items = Vec::new();
+    let mut offset = 0;
+    while offset < payload.len() {
+        let (item, consumed) = rlp_decode(&payload[offset..])?;
+        items.push(item);
+        offset += consumed;
+    }
+    Ok((Rlp::List(items), end))
+}
+
+fn rlp_put_length(buf: &mut BytesMut, base: u8, len: usize) {
+    if len < 56 {
+        buf.put(&[base + len as u8][..]);
+    } else {
+        let be_bytes = (len as u64).to_be_bytes();
+        let len_bytes = trim_zeros(&be_bytes[..]);
+        buf.put(&[base + 55 + len_bytes.len() as u8][..]);
+        buf.put(len_bytes);
+    }
+}
+
+fn rlp_encode_list(items: &[Bytes]) -> Bytes {
+    let mut payload = BytesMut::default();
+    for item in items {
+        if item.len() == 1 && item[0] < 0x80 {
+            payload.put(item.as_ref());
+        } else {
+            rlp_put_length(&mut payload, 0x80, item.len());
+            payload.put(item.as_ref());
+        }
+    }
+    let mut buf = BytesMut::default();
+    rlp_put_length(&mut buf, 0xc0, payload.len());
+    buf.put(payload.as_ref());
+    buf.freeze()
+}
+
+fn trim_zeros(data: &[u8]) -> &[u8] {
+    let start = data
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(data.len());
+    &data[start..]
+}
+
+fn to_u64(name: &str, data: &[u8]) -> Result<u64, String> {
+    if data.len() > 8 {
+        return Err(format!(
+            "The {} is too large: 0x{}",
+            name,
+            hex::encode(data)
+        ));
+    }
+    Ok(data
+        .iter()
+        .fold(0u64, |value, byte| (value << 8) | u64::from(*byte)))
+}
+
+/// The data of an EoA cell in Ethereum address mode is empty or the nonce of
+/// the next Ethereum transaction signed by the owner (u64 little endian), the
+/// validator increases it for every Ethereum transaction.
+pub fn eoa_cell_nonce(data: &[u8]) -> Result<u64, String> {
+    match data.len() {
+        0 => Ok(0),
+        8 => {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(data);
+            Ok(u64::from_le_bytes(buf))
+        }
+        len => Err(format!("Invalid EoA cell data length: {}", len)),
+    }
+}
+
+/// The EoA cell data of the nonce, see `eoa_cell_nonce`
+pub fn eoa_cell_data(nonce: u64) -> Bytes {
+    Bytes::from(nonce.to_le_bytes().to_vec())
+}
+
+/// Convert the value in wei to shannons, the value must be a multiple of
+/// `WEI_PER_SHANNON`.
+fn wei_to_shannons(data: &[u8]) -> Result<u64, String> {
+    let data = trim_zeros(data);
+    if data.len() > 16 {
+        return Err(format!("The value is too large: 0x{}", hex::encode(data)));
+    }
+    let wei = data
+        .iter()
+        .fold(0u128, |value, byte| (value << 8) | u128::from(*byte));
+    if wei % WEI_PER_SHANNON != 0 {
+        return Err(format!(
+            "The value {} wei is not a multiple of 1 shannon ({} wei)",
+            wei, WEI_PER_SHANNON
+        ));
+    }
+    let shannons = wei / WEI_PER_SHANNON;
+    if shannons > u128::from(std::u64::MAX) {
+        return Err(format!("The value is too large: {} wei", wei));
+    }
+    Ok(shannons as u64)
+}
+
+/// A signed EIP-155 Ethereum transaction, the gas fields are ignored by
+/// polyjuice.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EthTransaction {
+    pub nonce: u64,
+    /// None for contract creation
+    pub to: Option<H160>,
+    /// The value in shannons (converted from wei)
+    pub value: u64,
+    pub data: Bytes,
+    pub chain_id: u64,
+    /// r ++ s ++ recovery_id
+    pub signature: Bytes,
+    /// The original signed payload
+    pub raw: Bytes,
+    /// nonce, gas_price, gas_limit, to, value, data
+    unsigned_fields: Vec<Bytes>,
+}
+
+impl EthTransaction {
+    pub fn decode(raw: &[u8]) -> Result<EthTransaction, String> {
+        let (item, consumed) = rlp_decode(raw)?;
+        if consumed != raw.len() {
+            return Err(String::from("Extra data after the transaction"));
+        }
+        let fields = match item {
+            Rlp::List(items) if items.len() == 9 => items
+                .into_iter()
+                .map(Rlp::into_bytes)
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(String::from("Invalid transaction rlp")),
+        };
+        let to = match fields[3].len() {
+            0 => None,
+            20 => Some(H160::from_slice(&fields[3]).expect("convert to h160")),
+            len => return Err(format!("Invalid to address length: {}", len)),
+        };
+        let v = to_u64("v", &fields[6])?;
+        // The legacy transactions (v = 27/28) can be replayed on any chain
+        let (chain_id, recovery_id) = match v {
+            27 | 28 => {
+                return Err(String::from(
+                    "The transaction without chain id (EIP-155) is not supported",
+                ))
+            }
+            v if v >= 35 => ((v - 35) / 2, (v - 35) % 2),
+            v => return Err(format!("Invalid v: {}", v)),
+        };
+        let mut signature = [0u8; 65];
+        for (index, field) in [&fields[7], &fields[8]].iter().enumerate() {
+            let part = trim_zeros(field);
+            if part.len() > 32 {
+                return Err(String::from("Invalid signature"));
+            }
+            let end = (index + 1) * 32;
+            signature[end - part.len()..end].copy_from_slice(part);
+        }
+        signature[64] = recovery_id as u8;
+        Ok(EthTransaction {
+            nonce: to_u64("nonce", &fields[0])?,
+            to,
+            value: wei_to_shannons(&fields[4])?,
+            data: fields[5].clone(),
+            chain_id,
+            signature: Bytes::from(signature.to_vec()),
+            raw: Bytes::from(raw.to_vec()),
+            unsigned_fields: fields[0..6].to_vec(),
+        })
+    }
+
+    /// The message signed by the sender
+    pub fn signing_hash(&self) -> [u8; 32] {
+        let mut fields = self.unsigned_fields.clone();
+        let be_bytes = self.chain_id.to_be_bytes();
+        fields.push(Bytes::from(trim_zeros(&be_bytes[..]).to_vec()));
+        fields.push(Bytes::new());
+        fields.push(Bytes::new());
+        keccak256(&rlp_encode_list(&fields))
+    }
+
+    /// Sign a transaction by the private key (EIP-155)
+    #[cfg(test)]
+    pub fn sign(
+        nonce: u64,
+        to: Option<H160>,
+        value: u64,
+        data: Bytes,
+        chain_id: u64,
+        privkey: &secp256k1::SecretKey,
+    ) -> EthTransaction {
+        let encode_u128 = |value: u128| Bytes::from(trim_zeros(&value.to_be_bytes()[..]).to_vec());
+        let wei = u128::from(value) * WEI_PER_SHANNON;
+        let mut fields = vec![
+            encode_u128(u128::from(nonce)),
+            Bytes::new(),
+            Bytes::new(),
+            to.map(|to| Bytes::from(to.as_bytes().to_vec()))
+                .unwrap_or_default(),
+            encode_u128(wei),
+            data,
+            encode_u128(u128::from(chain_id)),
+            Bytes::new(),
+            Bytes::new(),
+        ];
+        let message = secp256k1::Message::from_slice(&keccak256(&rlp_encode_list(&fields))[..])
+            .expect("message");
+        let (recovery_id, signature) = SECP256K1
+            .sign_recoverable(&message, privkey)
+            .serialize_compact();
+        let v = chain_id * 2 + 35 + recovery_id.to_i32() as u64;
+        fields[6] = encode_u128(u128::from(v));
+        fields[7] = Bytes::from(trim_zeros(&signature[0..32]).to_vec());
+        fields[8] = Bytes::from(trim_zeros(&signature[32..64]).to_vec());
+        EthTransaction::decode(&rlp_encode_list(&fields)).expect("decode signed transaction")
+    }
+
+    pub fn recover_pubkey(&self) -> Result<secp256k1::PublicKey, String> {
+        let message = secp256k1::Message::from_slice(&self.signing_hash()[..])
+            .map_err(|err| err.to_string())?;
+        let recovery_id =
+            RecoveryId::from_i32(i32::from(self.signature[64])).map_err(|err| err.to_string())?;
+        let signature = RecoverableSignature::from_compact(&self.signature[0..64], recovery_id)
+            .map_err(|err| err.to_string())?;
+        SECP256K1
+            .recover(&message, &signature)
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::h160;
+
+    #[test]
+    fn test_decode_eip155_transaction() {
+        // The example in EIP-155
+        let raw = hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+        let tx = EthTransaction::decode(&raw).unwrap();
+        assert_eq!(tx.nonce, 9);
+        assert_eq!(
+            tx.to,
+            Some(h160!("0x3535353535353535353535353535353535353535"))
+        );
+        // 1 ether => 1 CKB
+        assert_eq!(tx.value, 100_000_000);
+        assert!(tx.data.is_empty());
+        assert_eq!(tx.chain_id, 1);
+        assert_eq!(
+            hex::encode(tx.signing_hash()),
+            "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+        let privkey = secp256k1::SecretKey::from_slice(&[0x46u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        assert_eq!(tx.recover_pubkey().unwrap(), pubkey);
+
+        assert!(EthTransaction::decode(&raw[..raw.len() - 1]).is_err());
+        // The legacy transaction (v = 27)
+        let mut legacy_raw = raw.clone();
+        let v_index = legacy_raw.len() - 67;
+        assert_eq!(legacy_raw[v_index], 0x25);
+        legacy_raw[v_index] = 0x1b;
+        let err = EthTransaction::decode(&legacy_raw).unwrap_err();
+        assert!(err.contains("chain id"), "{}", err);
+    }
+
+    #[test]
+    fn test_sign_transaction() {
+        let privkey = secp256k1::SecretKey::from_slice(&[0x46u8; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        let to = h160!("0x3535353535353535353535353535353535353535");
+        let data = Bytes::from(vec![0x01; 60]);
+        let tx = EthTransaction::sign(3, Some(to.clone()), 100, data.clone(), 0x706a, &privkey);
+        assert_eq!(tx.nonce, 3);
+        assert_eq!(tx.to, Some(to));
+        assert_eq!(tx.value, 100);
+        assert_eq!(tx.data, data);
+        assert_eq!(tx.chain_id, 0x706a);
+        assert_eq!(tx.recover_pubkey().unwrap(), pubkey);
+
+        // Non-canonical single byte and length
+        let raw = tx.raw.to_vec();
+        assert_eq!(raw[0], 0xf8);
+        assert_eq!(&raw[2..6], &[0x03, 0x80, 0x80, 0x94]);
+        let mut non_canonical = vec![0xf8, raw[1] + 1, 0x81, 0x03];
+        non_canonical.extend_from_slice(&raw[3..]);
+        assert!(EthTransaction::decode(&non_canonical).is_err());
+        let mut non_canonical = vec![0xf8, raw[1] + 1, 0x03, 0x80, 0x80, 0xb8, 0x14];
+        non_canonical.extend_from_slice(&raw[6..]);
+        let err = EthTransaction::decode(&non_canonical).unwrap_err();
+        assert!(err.contains("Non-canonical"), "{}", err);
+    }
+
+    #[test]
+    fn test_eoa_cell_nonce() {
+        assert_eq!(eoa_cell_nonce(&[]).unwrap(), 0);
+        assert_eq!(eoa_cell_nonce(&eoa_cell_data(258)).unwrap(), 258);
+        assert_eq!(eoa_cell_data(1).as_ref(), &[1, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(eoa_cell_nonce(&[1]).is_err());
+    }
+
+    #[test]
+    fn test_wei_to_shannons() {
+        assert_eq!(wei_to_shannons(&[]).unwrap(), 0);
+        assert_eq!(
+            wei_to_shannons(&WEI_PER_SHANNON.to_be_bytes()[..]).unwrap(),
+            1
+        );
+        // Not a multiple of 1 shannon
+        assert!(wei_to_shannons(&(WEI_PER_SHANNON + 1).to_be_bytes()[..]).is_err());
+        let max_wei = u128::from(std::u64::MAX) * WEI_PER_SHANNON;
+        assert_eq!(
+            wei_to_shannons(&max_wei.to_be_bytes()[..]).unwrap(),
+            std::u64::MAX
+        );
+        assert!(wei_to_shannons(&(max_wei + WEI_PER_SHANNON).to_be_bytes()[..]).is_err());
+        assert!(wei_to_shannons(&[1u8; 17]).is_err());
+    }
+}
@@ -0,0 +1,21 @@
+mod abi;
+pub mod cli;
+mod client;
+mod dev;
+mod eth_tx;
+mod metrics;
+mod mock_chain;
+mod server;
+pub mod storage;
+pub mod types;
+
+/// A downstream binary registers its block processors then runs the command
+/// line (`--processor <NAME>` enables them):
+///
+/// ```ignore
+/// fn main() -> Result<(), String> {
+///     polyjuice::register_processor("receipts", || Box::new(ReceiptIndexer))?;
+///     polyjuice::cli::main()
+/// }
+/// ```
+pub use storage::{register_processor, BlockProcessor, ProcessedBlock, ProcessorFactory};
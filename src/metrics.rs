@@ -0,0 +1,216 @@
+use jsonrpc_core::futures::{future::Either, Future};
+use jsonrpc_core::{middleware, Call, FutureResponse, Metadata, Middleware, Output};
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter,
+    IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use rocksdb::DB;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+lazy_static::lazy_static! {
+    pub static ref REGISTRY: Registry = Registry::new();
+
+    pub static ref INDEXER_TIP: IntGauge = register(IntGauge::new(
+        "polyjuice_indexer_tip_block_number",
+        "The last indexed block number",
+    ));
+    pub static ref CKB_TIP: IntGauge = register(IntGauge::new(
+        "polyjuice_ckb_tip_block_number",
+        "The tip block number of CKB",
+    ));
+    static ref INDEXER_LAG: IntGauge = register(IntGauge::new(
+        "polyjuice_indexer_lag_blocks",
+        "The number of blocks the indexer is behind the CKB tip",
+    ));
+    pub static ref BLOCKS_PROCESSED: IntCounter = register(IntCounter::new(
+        "polyjuice_indexer_blocks_processed_total",
+        "The number of blocks processed by the indexer",
+    ));
+    pub static ref BLOCKS_ROLLED_BACK: IntCounter = register(IntCounter::new(
+        "polyjuice_indexer_blocks_rolled_back_total",
+        "The number of blocks rolled back by the indexer",
+    ));
+    pub static ref BLOCK_PROCESS_SECONDS: Histogram = register(Histogram::with_opts(
+        HistogramOpts::new(
+            "polyjuice_indexer_block_process_seconds",
+            "The time to process a block (download excluded)",
+        )
+        .buckets(exponential_buckets(0.001, 2.0, 16).unwrap()),
+    ));
+    pub static ref RPC_SECONDS: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new(
+            "polyjuice_rpc_request_duration_seconds",
+            "The latency of the RPC methods",
+        ),
+        &["method"],
+    ));
+    pub static ref RPC_ERRORS: IntCounterVec = register(IntCounterVec::new(
+        Opts::new("polyjuice_rpc_errors_total", "The error responses of the RPC methods"),
+        &["method"],
+    ));
+    pub static ref VM_CYCLES: HistogramVec = register(HistogramVec::new(
+        HistogramOpts::new(
+            "polyjuice_vm_cycles",
+            "The VM cycles consumed by create/call/static_call",
+        )
+        .buckets(exponential_buckets(10_000.0, 4.0, 12).unwrap()),
+        &["kind"],
+    ));
+    static ref DB_SIZE: IntGauge = register(IntGauge::new(
+        "polyjuice_rocksdb_sst_files_bytes",
+        "The total size of the RocksDB SST files",
+    ));
+    pub static ref CKB_RPC_ERRORS: IntCounter = register(IntCounter::new(
+        "polyjuice_ckb_rpc_errors_total",
+        "The failed CKB RPC requests (after retries)",
+    ));
+    pub static ref CKB_RPC_RETRIES: IntCounter = register(IntCounter::new(
+        "polyjuice_ckb_rpc_retries_total",
+        "The retried CKB RPC requests",
+    ));
+}
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(result: prometheus::Result<T>) -> T {
+    let collector = result.expect("create metric");
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("register metric");
+    collector
+}
+
+/// Encode the metrics in Prometheus text format
+pub fn gather(db: &DB) -> Vec<u8> {
+    INDEXER_LAG.set((CKB_TIP.get() - INDEXER_TIP.get()).max(0));
+    match db.property_int_value("rocksdb.total-sst-files-size") {
+        Ok(Some(size)) => DB_SIZE.set(size as i64),
+        Ok(None) => {}
+        Err(err) => log::warn!("Get RocksDB size failed: {}", err),
+    }
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .expect("encode metrics");
+    buffer
+}
+
+/// Serve `GET /metrics` in a background thread
+pub fn serve_metrics(listen_addr: &str, db: Arc<DB>) -> Result<(), String> {
+    // Register all the metrics, so they are reported before the first update
+    lazy_static::initialize(&BLOCKS_PROCESSED);
+    lazy_static::initialize(&BLOCKS_ROLLED_BACK);
+    lazy_static::initialize(&BLOCK_PROCESS_SECONDS);
+    lazy_static::initialize(&RPC_SECONDS);
+    lazy_static::initialize(&RPC_ERRORS);
+    lazy_static::initialize(&VM_CYCLES);
+    lazy_static::initialize(&CKB_RPC_ERRORS);
+    lazy_static::initialize(&CKB_RPC_RETRIES);
+    let listener = TcpListener::bind(listen_addr).map_err(|err| err.to_string())?;
+    log::info!("Metrics server listen on: {}", listen_addr);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = handle_metrics_request(stream, &db) {
+                        log::debug!("Metrics request error: {}", err);
+                    }
+                }
+                Err(err) => log::warn!("Metrics connection failed: {}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_metrics_request(stream: TcpStream, db: &DB) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    let mut stream = reader.into_inner();
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    if request_line.starts_with("GET ") && path == "/metrics" {
+        let body = gather(db);
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            TextEncoder::new().format_type(),
+            body.len()
+        )?;
+        stream.write_all(&body)
+    } else {
+        stream
+            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+    }
+}
+
+/// Record the latency and errors of the RPC methods
+#[derive(Default)]
+pub struct RpcMetrics;
+
+impl<M: Metadata> Middleware<M> for RpcMetrics {
+    type Future = FutureResponse;
+    type CallFuture = middleware::NoopCallFuture;
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, M) -> X + Send + Sync,
+        X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+    {
+        let method = match &call {
+            Call::MethodCall(method_call) => method_call.method.clone(),
+            _ => return Either::B(next(call, meta)),
+        };
+        let start = Instant::now();
+        Either::A(Box::new(next(call, meta).map(move |output| {
+            RPC_SECONDS
+                .with_label_values(&[method.as_str()])
+                .observe(start.elapsed().as_secs_f64());
+            if let Some(Output::Failure(_)) = output {
+                RPC_ERRORS.with_label_values(&[method.as_str()]).inc();
+            }
+            output
+        })))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_serve_metrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        serve_metrics(&addr, db).unwrap();
+
+        let mut stream = TcpStream::connect(&addr).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("polyjuice_indexer_lag_blocks"));
+        assert!(response.contains("polyjuice_indexer_blocks_rolled_back_total"));
+        assert!(response.contains("polyjuice_rocksdb_sst_files_bytes"));
+
+        let mut stream = TcpStream::connect(&addr).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+}
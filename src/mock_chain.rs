@@ -0,0 +1,320 @@
+use ckb_jsonrpc_types::{
+    BlockView, CellData, CellInfo, CellWithStatus, EpochView, HeaderView, JsonBytes, OutPoint,
+    Transaction, TransactionWithStatus, TxStatus,
+};
+use ckb_types::{bytes::Bytes, core, packed, prelude::*, H256};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::client::{ChainClient, RpcError};
+
+/// The blocks in one epoch
+const EPOCH_LENGTH: u64 = 1000;
+
+#[derive(Default)]
+struct ChainState {
+    /// The blocks of the main chain, indexed by block number
+    blocks: Vec<core::BlockView>,
+    /// The sent transactions not committed yet
+    pool: Vec<core::TransactionView>,
+    /// Make the blocks of different forks have different hashes
+    nonce: u128,
+}
+
+impl ChainState {
+    fn find_transaction(&self, hash: &H256) -> Option<(core::TransactionView, Option<H256>)> {
+        let hash: packed::Byte32 = hash.pack();
+        self.blocks
+            .iter()
+            .find_map(|block| {
+                block
+                    .transactions()
+                    .into_iter()
+                    .find(|tx| tx.hash() == hash)
+                    .map(|tx| (tx, Some(block.hash().unpack())))
+            })
+            .or_else(|| {
+                self.pool
+                    .iter()
+                    .find(|tx| tx.hash() == hash)
+                    .map(|tx| (tx.clone(), None))
+            })
+    }
+
+    fn is_consumed(&self, out_point: &packed::OutPoint, include_pool: bool) -> bool {
+        let pool = if include_pool {
+            &self.pool[..]
+        } else {
+            &self.pool[..0]
+        };
+        self.blocks
+            .iter()
+            .flat_map(|block| block.transactions())
+            .chain(pool.iter().cloned())
+            .any(|tx| tx.input_pts_iter().any(|input| &input == out_point))
+    }
+}
+
+/// An in-memory CKB chain for tests and the dev mode. The scripts are not
+/// verified, the blocks are appended by the caller and the chain can fork at
+/// any height. The clones share the same chain.
+#[derive(Clone)]
+pub struct MockChain {
+    state: Arc<Mutex<ChainState>>,
+}
+
+impl MockChain {
+    /// Create the chain with a genesis block contains the transactions
+    pub fn new(genesis_txs: Vec<core::TransactionView>) -> MockChain {
+        let chain = MockChain {
+            state: Arc::new(Mutex::new(ChainState::default())),
+        };
+        chain.append_block(genesis_txs);
+        chain
+    }
+
+    fn state(&self) -> MutexGuard<ChainState> {
+        self.state.lock().expect("lock mock chain")
+    }
+
+    pub fn tip(&self) -> core::BlockView {
+        self.state().blocks.last().cloned().expect("genesis block")
+    }
+
+    /// Append a block contains a cellbase and the transactions to the tip
+    pub fn append_block(&self, txs: Vec<core::TransactionView>) -> core::BlockView {
+        let mut state = self.state();
+        let (number, parent_hash, parent_timestamp) = state
+            .blocks
+            .last()
+            .map(|parent| (parent.number() + 1, parent.hash(), parent.timestamp()))
+            .unwrap_or_default();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time")
+            .as_millis() as u64;
+        state.nonce += 1;
+        // The generator reads the coinbase from the cellbase of tip block
+        let cellbase = core::TransactionBuilder::default()
+            .input(packed::CellInput::new_cellbase_input(number))
+            .output(packed::CellOutput::default())
+            .output_data(Bytes::new().pack())
+            .build();
+        let epoch = core::EpochNumberWithFraction::new(
+            number / EPOCH_LENGTH,
+            number % EPOCH_LENGTH,
+            EPOCH_LENGTH,
+        );
+        let block = core::BlockBuilder::default()
+            .parent_hash(parent_hash)
+            .number(number.pack())
+            .epoch(epoch.full_value().pack())
+            .timestamp(std::cmp::max(now, parent_timestamp + 1).pack())
+            .nonce(state.nonce.pack())
+            .transaction(cellbase)
+            .transactions(txs)
+            .build();
+        let committed = block.tx_hashes();
+        state.pool.retain(|tx| !committed.contains(&tx.hash()));
+        state.blocks.push(block.clone());
+        block
+    }
+
+    /// Commit all the sent transactions in a new block
+    pub fn mine_block(&self) -> core::BlockView {
+        let txs = self.state().pool.clone();
+        self.append_block(txs)
+    }
+
+    /// Discard the blocks after `number`, the following blocks are appended on
+    /// the fork. The transactions (except the cellbase) of the discarded blocks
+    /// are put back into the pool.
+    #[cfg(test)]
+    pub fn fork(&self, number: u64) {
+        let mut state = self.state();
+        let detached = state.blocks.split_off(number as usize + 1);
+        for block in detached {
+            state.pool.extend(block.transactions().into_iter().skip(1));
+        }
+    }
+
+    /// Replace the blocks after `number` by the new blocks
+    #[cfg(test)]
+    pub fn reorg(&self, number: u64, blocks: Vec<Vec<core::TransactionView>>) {
+        self.fork(number);
+        for txs in blocks {
+            self.append_block(txs);
+        }
+    }
+}
+
+impl ChainClient for MockChain {
+    fn get_block(&mut self, hash: H256) -> Result<Option<BlockView>, RpcError> {
+        let hash: packed::Byte32 = hash.pack();
+        Ok(self
+            .state()
+            .blocks
+            .iter()
+            .find(|block| block.hash() == hash)
+            .cloned()
+            .map(Into::into))
+    }
+    fn get_block_by_number(&mut self, number: u64) -> Result<Option<BlockView>, RpcError> {
+        Ok(self
+            .state()
+            .blocks
+            .get(number as usize)
+            .cloned()
+            .map(Into::into))
+    }
+    fn get_epoch_by_number(&mut self, number: u64) -> Result<Option<EpochView>, RpcError> {
+        let tip_number = self.tip().number();
+        if number > tip_number / EPOCH_LENGTH {
+            return Ok(None);
+        }
+        Ok(Some(EpochView {
+            number: number.into(),
+            start_number: (number * EPOCH_LENGTH).into(),
+            length: EPOCH_LENGTH.into(),
+            compact_target: 0u32.into(),
+        }))
+    }
+    fn get_header(&mut self, hash: H256) -> Result<Option<HeaderView>, RpcError> {
+        self.get_block(hash)
+            .map(|block_opt| block_opt.map(|block| block.header))
+    }
+    fn get_header_by_number(&mut self, number: u64) -> Result<Option<HeaderView>, RpcError> {
+        self.get_block_by_number(number)
+            .map(|block_opt| block_opt.map(|block| block.header))
+    }
+    fn get_live_cell(
+        &mut self,
+        out_point: OutPoint,
+        with_data: bool,
+    ) -> Result<CellWithStatus, RpcError> {
+        let state = self.state();
+        let out_point = packed::OutPoint::from(out_point);
+        let index: u32 = out_point.index().unpack();
+        let cell_opt = state
+            .find_transaction(&out_point.tx_hash().unpack())
+            .filter(|(_, block_hash)| block_hash.is_some())
+            .and_then(|(tx, _)| tx.output_with_data(index as usize));
+        let (cell, status) = match cell_opt {
+            Some(_) if state.is_consumed(&out_point, false) => (None, "dead"),
+            Some((output, data)) => {
+                let data = if with_data {
+                    Some(CellData {
+                        hash: packed::CellOutput::calc_data_hash(&data).unpack(),
+                        content: JsonBytes::from_bytes(data),
+                    })
+                } else {
+                    None
+                };
+                let cell = CellInfo {
+                    output: output.into(),
+                    data,
+                };
+                (Some(cell), "live")
+            }
+            None => (None, "unknown"),
+        };
+        Ok(CellWithStatus {
+            cell,
+            status: status.to_string(),
+        })
+    }
+    fn get_tip_block_number(&mut self) -> Result<u64, RpcError> {
+        Ok(self.tip().number())
+    }
+    fn get_tip_header(&mut self) -> Result<HeaderView, RpcError> {
+        Ok(self.tip().header().into())
+    }
+    fn get_transaction(&mut self, hash: H256) -> Result<Option<TransactionWithStatus>, RpcError> {
+        Ok(self
+            .state()
+            .find_transaction(&hash)
+            .map(|(tx, block_hash_opt)| TransactionWithStatus {
+                transaction: tx.into(),
+                tx_status: block_hash_opt
+                    .map(TxStatus::committed)
+                    .unwrap_or_else(TxStatus::pending),
+            }))
+    }
+
+    fn send_transaction(&mut self, tx: Transaction) -> Result<H256, RpcError> {
+        let tx = packed::Transaction::from(tx).into_view();
+        let mut state = self.state();
+        for out_point in tx.input_pts_iter() {
+            let tx_hash: H256 = out_point.tx_hash().unpack();
+            let index: u32 = out_point.index().unpack();
+            let is_unknown = state
+                .find_transaction(&tx_hash)
+                .and_then(|(input_tx, _)| input_tx.output(index as usize))
+                .is_none();
+            if is_unknown || state.is_consumed(&out_point, true) {
+                return Err(RpcError::Rpc(format!(
+                    "Dead or unknown input: {:#x}[{}]",
+                    tx_hash, index
+                )));
+            }
+        }
+        state.pool.push(tx.clone());
+        Ok(tx.hash().unpack())
+    }
+
+    fn error_backoff(&self) -> Duration {
+        Duration::from_millis(10)
+    }
+
+    fn clone_box(&self) -> Box<dyn ChainClient> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::core::TransactionBuilder;
+
+    #[test]
+    fn test_mock_chain_fork() {
+        let output = packed::CellOutput::new_builder()
+            .capacity(core::Capacity::bytes(100).unwrap().pack())
+            .build();
+        let genesis_tx = TransactionBuilder::default()
+            .output(output.clone())
+            .output_data(Bytes::new().pack())
+            .build();
+        let mut chain = MockChain::new(vec![genesis_tx.clone()]);
+        let genesis_out_point = packed::OutPoint::new(genesis_tx.hash(), 0);
+        let spend_tx = TransactionBuilder::default()
+            .input(packed::CellInput::new(genesis_out_point.clone(), 0))
+            .output(output)
+            .output_data(Bytes::new().pack())
+            .build();
+
+        let tx_hash = chain.send_transaction(spend_tx.data().into()).unwrap();
+        // Double spend
+        assert!(chain.send_transaction(spend_tx.data().into()).is_err());
+        let block1 = chain.mine_block();
+        assert_eq!(&block1.tx_hashes()[1..], &[spend_tx.hash()]);
+        assert_eq!(chain.get_tip_block_number().unwrap(), 1);
+        let cell = chain
+            .get_live_cell(genesis_out_point.into(), false)
+            .unwrap();
+        assert_eq!(cell.status, "dead");
+        let committed = chain.get_transaction(tx_hash.clone()).unwrap().unwrap();
+        assert_eq!(committed.tx_status.block_hash, Some(block1.hash().unpack()));
+
+        // The transaction is back to the pool after the reorg
+        chain.reorg(0, vec![Vec::new(), Vec::new()]);
+        assert_eq!(chain.get_tip_block_number().unwrap(), 2);
+        let block1_fork = chain.get_block_by_number(1).unwrap().unwrap();
+        assert_ne!(block1_fork.header.hash, block1.hash().unpack());
+        assert!(chain.get_block(block1.hash().unpack()).unwrap().is_none());
+        let pending = chain.get_transaction(tx_hash).unwrap().unwrap();
+        assert_eq!(pending.tx_status.block_hash, None);
+        // The cellbase of the discarded block is not pooled
+        assert_eq!(&chain.mine_block().tx_hashes()[1..], &[spend_tx.hash()]);
+    }
+}
@@ -1,15 +1,27 @@
-use crate::storage::{CsalRunContext, Loader, Runner};
-use crate::types::{ContractAddress, ContractChange, ContractMeta, EoaAddress, RunConfig};
+use crate::abi::{decode_log, parse_uint, Abi, AbiEntry, DecodedEvent, DecodedParam};
+use crate::eth_tx::EthTransaction;
+use crate::storage::{value, CsalRunContext, Loader, Runner};
+use crate::types::{
+    smth256_to_h256, ContractAddress, ContractChange, ContractMeta, EoaAddress, RunConfig,
+    SyncStatus, TokenBalanceInfo, TokenTransferInfo, TransferCursor, TransferInfo,
+};
 use ckb_hash::blake2b_256;
-use ckb_jsonrpc_types::{JsonBytes, Transaction};
-use ckb_types::{bytes::Bytes, prelude::*, H160, H256};
+use ckb_jsonrpc_types::{JsonBytes, OutPoint, Transaction};
+use ckb_types::{bytes::Bytes, packed, prelude::*, H160, H256};
 use jsonrpc_core::{Error, ErrorCode, Result as RpcResult};
 use jsonrpc_derive::rpc;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::error::Error as StdError;
 use std::sync::Arc;
 
+/// Max transfers returned by one `get_transfers` call
+pub const TRANSFERS_PAGE_SIZE: usize = 100;
+/// Max holders returned by one `get_token_holders` call
+pub const TOKEN_HOLDERS_PAGE_SIZE: usize = 100;
+
 #[rpc(server)]
 pub trait Rpc {
     #[rpc(name = "create")]
@@ -24,6 +36,9 @@ pub trait Rpc {
         value: u64,
     ) -> RpcResult<TransactionReceipt>;
 
+    #[rpc(name = "raw_transaction")]
+    fn raw_transaction(&self, raw_tx: JsonBytes) -> RpcResult<TransactionReceipt>;
+
     #[rpc(name = "static_call")]
     fn static_call(
         &self,
@@ -32,6 +47,37 @@ pub trait Rpc {
         input: JsonBytes,
     ) -> RpcResult<StaticCallResponse>;
 
+    #[rpc(name = "call_function")]
+    fn call_function(
+        &self,
+        sender: H160,
+        contract_address: ContractAddress,
+        function: String,
+        args: Vec<Value>,
+        value: u64,
+    ) -> RpcResult<FunctionCallReceipt>;
+
+    #[rpc(name = "static_call_function")]
+    fn static_call_function(
+        &self,
+        sender: H160,
+        contract_address: ContractAddress,
+        function: String,
+        args: Vec<Value>,
+    ) -> RpcResult<StaticCallFunctionResponse>;
+
+    #[rpc(name = "upload_abi")]
+    fn upload_abi(&self, contract_address: ContractAddress, abi: Value) -> RpcResult<()>;
+
+    #[rpc(name = "get_abi")]
+    fn get_abi(&self, contract_address: ContractAddress) -> RpcResult<Option<Vec<AbiEntry>>>;
+
+    #[rpc(name = "send_transaction")]
+    fn send_transaction(&self, tx: Transaction) -> RpcResult<H256>;
+
+    #[rpc(name = "merge_eoa_cells")]
+    fn merge_eoa_cells(&self, lock_args: H160) -> RpcResult<Transaction>;
+
     #[rpc(name = "get_code")]
     fn get_code(&self, contract_address: ContractAddress) -> RpcResult<ContractCodeJson>;
 
@@ -61,19 +107,104 @@ pub trait Rpc {
 
     #[rpc(name = "get_balance")]
     fn get_balance(&self, address: H160) -> RpcResult<u64>;
+
+    #[rpc(name = "get_account")]
+    fn get_account(&self, address: H160, block_number: Option<u64>) -> RpcResult<AccountJson>;
+
+    #[rpc(name = "get_transfers")]
+    fn get_transfers(
+        &self,
+        address: H160,
+        from_block: u64,
+        to_block: Option<u64>,
+        cursor: Option<TransferCursor>,
+    ) -> RpcResult<TransfersJson>;
+
+    #[rpc(name = "get_token_balances")]
+    fn get_token_balances(&self, address: H160) -> RpcResult<Vec<TokenBalanceInfo>>;
+
+    #[rpc(name = "get_token_holders")]
+    fn get_token_holders(
+        &self,
+        token: ContractAddress,
+        cursor: Option<H160>,
+    ) -> RpcResult<TokenHoldersJson>;
+
+    #[rpc(name = "get_token_owner")]
+    fn get_token_owner(&self, token: ContractAddress, token_id: String) -> RpcResult<Option<H160>>;
+
+    #[rpc(name = "get_token_transfers")]
+    fn get_token_transfers(
+        &self,
+        address: H160,
+        token: Option<ContractAddress>,
+        from_block: u64,
+        to_block: Option<u64>,
+        cursor: Option<TransferCursor>,
+    ) -> RpcResult<TokenTransfersJson>;
+
+    #[rpc(name = "get_sync_status")]
+    fn get_sync_status(&self) -> RpcResult<SyncStatus>;
 }
 
 pub struct RpcImpl {
     pub loader: Arc<Loader>,
     pub run_config: RunConfig,
+    /// Refuse to run programs when the indexer lags behind the CKB tip by
+    /// more than this number of blocks
+    pub max_lag: Option<u64>,
+}
+
+impl RpcImpl {
+    fn check_lag(&self) -> RpcResult<()> {
+        if let Some(max_lag) = self.max_lag {
+            let status = self.loader.load_sync_status().map_err(convert_err)?;
+            if status.lag > max_lag {
+                return Err(convert_err(format!(
+                    "Indexer is syncing, {} blocks behind the CKB tip (max lag: {})",
+                    status.lag, max_lag
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn load_abi(&self, address: &ContractAddress) -> RpcResult<Abi> {
+        self.loader
+            .load_contract_abi(address)
+            .map_err(convert_err)?
+            .ok_or_else(|| convert_err(format!("ABI not uploaded for contract: {:x}", address.0)))
+    }
+
+    fn load_abi_opt(&self, address: &ContractAddress) -> Option<Abi> {
+        self.loader
+            .load_contract_abi(address)
+            .unwrap_or_else(|err| {
+                log::warn!("Load abi of {:x} failed: {}", address.0, err);
+                None
+            })
+    }
+
+    /// Decode the logs by the uploaded ABIs of the emitting contracts
+    fn decode_logs<'a>(&self, logs: impl IntoIterator<Item = &'a mut LogEntry>) {
+        let mut abis: HashMap<ContractAddress, Option<Abi>> = HashMap::default();
+        for entry in logs {
+            let abi_opt = abis
+                .entry(entry.address.clone())
+                .or_insert_with(|| self.load_abi_opt(&entry.address));
+            entry.event = decode_log(abi_opt.as_ref(), &entry.topics, entry.data.as_bytes());
+        }
+    }
 }
 
 impl Rpc for RpcImpl {
     fn create(&self, sender: H160, code: JsonBytes, value: u64) -> RpcResult<TransactionReceipt> {
         log::debug!("create(sender: {:x}, value: {})", sender, value);
+        self.check_lag()?;
         let loader = Loader::clone(&self.loader);
         let run_config = self.run_config.clone();
         let context = Runner::new(loader, run_config)
+            .with_pending()
             .create(sender, code.into_bytes(), value)
             .map_err(convert_err_box)?;
         let resp = TransactionReceipt::try_from(context).map_err(convert_err);
@@ -94,9 +225,11 @@ impl Rpc for RpcImpl {
             contract_address.0,
             hex::encode(input.as_bytes())
         );
+        self.check_lag()?;
         let loader = Loader::clone(&self.loader);
         let run_config = self.run_config.clone();
         let context = Runner::new(loader, run_config)
+            .with_pending()
             .call(sender, contract_address, input.into_bytes(), value)
             .map_err(convert_err_box)?;
         let resp = TransactionReceipt::try_from(context).map_err(convert_err);
@@ -104,6 +237,24 @@ impl Rpc for RpcImpl {
         resp
     }
 
+    fn raw_transaction(&self, raw_tx: JsonBytes) -> RpcResult<TransactionReceipt> {
+        log::debug!(
+            "raw_transaction(raw_tx: {})",
+            hex::encode(raw_tx.as_bytes())
+        );
+        self.check_lag()?;
+        let eth_tx = EthTransaction::decode(raw_tx.as_bytes()).map_err(convert_err)?;
+        let loader = Loader::clone(&self.loader);
+        let run_config = self.run_config.clone();
+        let context = Runner::new(loader, run_config)
+            .with_pending()
+            .eth_transaction(eth_tx)
+            .map_err(convert_err_box)?;
+        let resp = TransactionReceipt::try_from(context).map_err(convert_err);
+        log::debug!("raw_transaction finished");
+        resp
+    }
+
     fn static_call(
         &self,
         sender: H160,
@@ -116,6 +267,7 @@ impl Rpc for RpcImpl {
             contract_address.0,
             hex::encode(input.as_bytes())
         );
+        self.check_lag()?;
         let loader = Loader::clone(&self.loader);
         let run_config = self.run_config.clone();
         let context = Runner::new(loader, run_config)
@@ -125,6 +277,107 @@ impl Rpc for RpcImpl {
         StaticCallResponse::try_from(context).map_err(convert_err)
     }
 
+    fn call_function(
+        &self,
+        sender: H160,
+        contract_address: ContractAddress,
+        function: String,
+        args: Vec<Value>,
+        value: u64,
+    ) -> RpcResult<FunctionCallReceipt> {
+        log::debug!(
+            "call_function(sender: {:x}, contract_address: {:x}, function: {}, args: {:?})",
+            sender,
+            contract_address.0,
+            function,
+            args
+        );
+        self.check_lag()?;
+        let abi = self.load_abi(&contract_address)?;
+        let entry = abi.function(&function).map_err(convert_err)?;
+        let input = entry.encode_input(&args).map_err(convert_err)?;
+        let loader = Loader::clone(&self.loader);
+        let run_config = self.run_config.clone();
+        let context = Runner::new(loader, run_config)
+            .with_pending()
+            .call(sender, contract_address, input, value)
+            .map_err(convert_err_box)?;
+        let return_data = context.entrance_info().return_data();
+        let mut receipt = TransactionReceipt::try_from(context).map_err(convert_err)?;
+        let return_values = entry.decode_output(&return_data).map_err(convert_err)?;
+        self.decode_logs(&mut receipt.logs);
+        Ok(FunctionCallReceipt {
+            receipt,
+            return_values,
+        })
+    }
+
+    fn static_call_function(
+        &self,
+        sender: H160,
+        contract_address: ContractAddress,
+        function: String,
+        args: Vec<Value>,
+    ) -> RpcResult<StaticCallFunctionResponse> {
+        log::debug!(
+            "static_call_function(sender: {:x}, contract_address: {:x}, function: {}, args: {:?})",
+            sender,
+            contract_address.0,
+            function,
+            args
+        );
+        self.check_lag()?;
+        let abi = self.load_abi(&contract_address)?;
+        let entry = abi.function(&function).map_err(convert_err)?;
+        let input = entry.encode_input(&args).map_err(convert_err)?;
+        let loader = Loader::clone(&self.loader);
+        let run_config = self.run_config.clone();
+        let context = Runner::new(loader, run_config)
+            .static_call(sender, contract_address, input)
+            .map_err(convert_err_box)?;
+        let mut response = StaticCallResponse::try_from(context).map_err(convert_err)?;
+        let return_values = entry
+            .decode_output(response.return_data.as_bytes())
+            .map_err(convert_err)?;
+        self.decode_logs(&mut response.logs);
+        Ok(StaticCallFunctionResponse {
+            response,
+            return_values,
+        })
+    }
+
+    fn upload_abi(&self, contract_address: ContractAddress, abi: Value) -> RpcResult<()> {
+        log::debug!("upload_abi(contract_address: {:x})", contract_address.0);
+        let abi = Abi::from_json(abi.to_string().as_str()).map_err(convert_err)?;
+        self.loader
+            .save_contract_abi(contract_address, &abi)
+            .map_err(convert_err)
+    }
+
+    fn get_abi(&self, contract_address: ContractAddress) -> RpcResult<Option<Vec<AbiEntry>>> {
+        self.loader
+            .load_contract_abi(&contract_address)
+            .map(|abi_opt| abi_opt.map(|abi| abi.entries))
+            .map_err(convert_err)
+    }
+
+    fn send_transaction(&self, tx: Transaction) -> RpcResult<H256> {
+        log::debug!("send_transaction(tx: {:?})", tx);
+        let mut loader = Loader::clone(&self.loader);
+        loader.send_transaction(tx).map_err(convert_err)
+    }
+
+    fn merge_eoa_cells(&self, lock_args: H160) -> RpcResult<Transaction> {
+        log::debug!("merge_eoa_cells(lock_args: {:x})", lock_args);
+        let loader = Loader::clone(&self.loader);
+        let run_config = self.run_config.clone();
+        Runner::new(loader, run_config)
+            .with_pending()
+            .merge_eoa_cells(lock_args)
+            .map(Transaction::from)
+            .map_err(convert_err_box)
+    }
+
     fn get_code(&self, contract_address: ContractAddress) -> RpcResult<ContractCodeJson> {
         log::debug!("get_code(contract_address: {:x})", contract_address.0);
         self.loader
@@ -160,10 +413,18 @@ impl Rpc for RpcImpl {
         contract_address: ContractAddress,
         block_number: Option<u64>,
     ) -> RpcResult<ContractChangeJson> {
-        self.loader
-            .load_latest_contract_change(contract_address, block_number, true, true)
+        let mut change = self
+            .loader
+            .load_latest_contract_change(contract_address.clone(), block_number, true, true)
             .map(ContractChangeJson::from)
-            .map_err(convert_err)
+            .map_err(convert_err)?;
+        let abi_opt = self.load_abi_opt(&contract_address);
+        change.events = change
+            .logs
+            .iter()
+            .map(|(topics, data)| decode_log(abi_opt.as_ref(), topics, data.as_bytes()))
+            .collect();
+        Ok(change)
     }
 
     fn get_logs(
@@ -175,7 +436,7 @@ impl Rpc for RpcImpl {
         limit: Option<u32>,
     ) -> RpcResult<Vec<LogInfo>> {
         let mut loader = Loader::clone(&self.loader);
-        loader
+        let mut logs = loader
             .load_logs(from_block, to_block, address, filter_topics, limit)
             .map(|logs| {
                 logs.into_iter()
@@ -189,14 +450,15 @@ impl Rpc for RpcImpl {
                     })
                     .collect::<Vec<_>>()
             })
-            .map_err(convert_err)
+            .map_err(convert_err)?;
+        self.decode_logs(logs.iter_mut().map(|info| &mut info.log));
+        Ok(logs)
     }
 
     fn get_balance(&self, address: H160) -> RpcResult<u64> {
-        let mut loader = Loader::clone(&self.loader);
+        let loader = Loader::clone(&self.loader);
         loader
-            .load_eoa_live_cell(address.clone())
-            .map(|(cell, _, _)| cell.balance())
+            .load_eoa_balance(address.clone())
             .or_else(|_err| {
                 loader
                     .load_contract_meta(ContractAddress(address.clone()))
@@ -204,6 +466,130 @@ impl Rpc for RpcImpl {
             })
             .map_err(convert_err)
     }
+
+    fn get_account(&self, address: H160, block_number: Option<u64>) -> RpcResult<AccountJson> {
+        log::debug!(
+            "get_account(address: {:x}, block_number: {:?})",
+            address,
+            block_number
+        );
+        let mut loader = Loader::clone(&self.loader);
+        if let Ok(meta) = loader.load_contract_meta(ContractAddress(address.clone())) {
+            let change = loader
+                .load_latest_contract_change(meta.address.clone(), block_number, false, false)
+                .map_err(convert_err)?;
+            return Ok(AccountJson::from_contract(meta, change));
+        }
+        // NOTE: EoA account only have latest state indexed, the block number is ignored
+        let (cell, output, _) = loader
+            .load_eoa_live_cell(address.clone())
+            .map_err(|_err| convert_err(format!("Account not found: {:x}", address)))?;
+        let type_id_args = output
+            .type_()
+            .to_opt()
+            .map(|script| JsonBytes::from_bytes(script.args().raw_data()));
+        Ok(AccountJson::from_eoa(address, cell, type_id_args))
+    }
+
+    fn get_transfers(
+        &self,
+        address: H160,
+        from_block: u64,
+        to_block: Option<u64>,
+        cursor: Option<TransferCursor>,
+    ) -> RpcResult<TransfersJson> {
+        log::debug!(
+            "get_transfers(address: {:x}, from_block: {}, to_block: {:?}, cursor: {:?})",
+            address,
+            from_block,
+            to_block,
+            cursor
+        );
+        self.loader
+            .load_eoa_transfers(address, from_block, to_block, cursor, TRANSFERS_PAGE_SIZE)
+            .map(|(transfers, next_cursor)| TransfersJson {
+                transfers,
+                next_cursor,
+            })
+            .map_err(convert_err)
+    }
+
+    fn get_token_balances(&self, address: H160) -> RpcResult<Vec<TokenBalanceInfo>> {
+        log::debug!("get_token_balances(address: {:x})", address);
+        self.loader
+            .load_token_balances(address)
+            .map_err(convert_err)
+    }
+
+    fn get_token_holders(
+        &self,
+        token: ContractAddress,
+        cursor: Option<H160>,
+    ) -> RpcResult<TokenHoldersJson> {
+        log::debug!(
+            "get_token_holders(token: {:x}, cursor: {:?})",
+            token.0,
+            cursor
+        );
+        self.loader
+            .load_token_holders(token, cursor, TOKEN_HOLDERS_PAGE_SIZE)
+            .map(|(holders, next_cursor)| TokenHoldersJson {
+                holders,
+                next_cursor,
+            })
+            .map_err(convert_err)
+    }
+
+    fn get_token_owner(&self, token: ContractAddress, token_id: String) -> RpcResult<Option<H160>> {
+        log::debug!(
+            "get_token_owner(token: {:x}, token_id: {})",
+            token.0,
+            token_id
+        );
+        let token_id = parse_uint(&Value::String(token_id)).map_err(convert_err)?;
+        self.loader
+            .load_token_owner(token, H256(token_id))
+            .map_err(convert_err)
+    }
+
+    fn get_token_transfers(
+        &self,
+        address: H160,
+        token: Option<ContractAddress>,
+        from_block: u64,
+        to_block: Option<u64>,
+        cursor: Option<TransferCursor>,
+    ) -> RpcResult<TokenTransfersJson> {
+        log::debug!(
+            "get_token_transfers(address: {:x}, token: {:?}, from_block: {}, to_block: {:?}, cursor: {:?})",
+            address,
+            token,
+            from_block,
+            to_block,
+            cursor
+        );
+        self.loader
+            .load_token_transfers(
+                address,
+                token,
+                from_block,
+                to_block,
+                cursor,
+                TRANSFERS_PAGE_SIZE,
+            )
+            .map(|(transfers, next_cursor)| TokenTransfersJson {
+                transfers,
+                next_cursor,
+            })
+            .map_err(convert_err)
+    }
+
+    fn get_sync_status(&self) -> RpcResult<SyncStatus> {
+        Loader::clone(&self.loader)
+            .load_ckb_tip()
+            .map_err(convert_err)?;
+        self.loader.load_sync_status().map_err(convert_err)
+    }
 }
 
 fn convert_err(err: String) -> Error {
@@ -235,6 +621,8 @@ pub struct ContractChangeJson {
     pub tx_hash: H256,
     pub new_storage: Vec<(H256, H256)>,
     pub logs: Vec<(Vec<H256>, JsonBytes)>,
+    /// The decoded events of the logs (same order), None if can't decode
+    pub events: Vec<Option<DecodedEvent>>,
     /// The change is create the contract
     pub is_create: bool,
 }
@@ -254,6 +642,7 @@ impl From<ContractChange> for ContractChangeJson {
                 .into_iter()
                 .map(|(topics, data)| (topics, JsonBytes::from_bytes(data)))
                 .collect::<Vec<_>>(),
+            events: Vec::new(),
             is_create: change.is_create,
         }
     }
@@ -289,6 +678,9 @@ pub struct LogEntry {
     address: ContractAddress,
     topics: Vec<H256>,
     data: JsonBytes,
+    /// Decoded by the uploaded ABI of the contract
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    event: Option<DecodedEvent>,
 }
 
 impl LogEntry {
@@ -297,6 +689,7 @@ impl LogEntry {
             address,
             topics,
             data: JsonBytes::from_bytes(data),
+            event: None,
         }
     }
 }
@@ -320,6 +713,8 @@ impl TryFrom<CsalRunContext> for TransactionReceipt {
     fn try_from(mut context: CsalRunContext) -> Result<TransactionReceipt, String> {
         let tx = context.build_tx().map_err(|err| err.to_string())?;
         let tx_hash: H256 = tx.calc_tx_hash().unpack();
+        // The following transactions will build on it once it's sent by `send_transaction`
+        context.loader.add_built_tx(context.pending_tx(&tx));
         let entrance_contract = context.entrance_contract();
         let created_addresses = context.created_contracts();
         let destructed_addresses = context.destructed_contracts();
@@ -366,6 +761,22 @@ impl TryFrom<CsalRunContext> for StaticCallResponse {
     }
 }
 
+/// The `TransactionReceipt` with the decoded return values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCallReceipt {
+    #[serde(flatten)]
+    pub receipt: TransactionReceipt,
+    pub return_values: Vec<DecodedParam>,
+}
+
+/// The `StaticCallResponse` with the decoded return values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaticCallFunctionResponse {
+    #[serde(flatten)]
+    pub response: StaticCallResponse,
+    pub return_values: Vec<DecodedParam>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractMetaJson {
     /// The block where the contract created
@@ -399,3 +810,208 @@ impl ContractMetaJson {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    Eoa,
+    Contract,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountJson {
+    pub kind: AccountKind,
+    pub address: H160,
+    /// The balance of the account (capacity minus the minimal capacity of the cell)
+    pub balance: u64,
+    /// The capacity of the account's live cell
+    pub capacity: u64,
+    /// The account's current live cell
+    pub out_point: OutPoint,
+
+    /// The type id args of the EoA account cell
+    pub type_id_args: Option<JsonBytes>,
+
+    /// The merkle root of the contract storage
+    pub storage_root: Option<H256>,
+    /// The contract code hash
+    pub code_hash: Option<H256>,
+    /// The transaction where the contract created
+    pub creation_out_point: Option<OutPoint>,
+    /// Check if the contract is destructed
+    pub destructed: Option<bool>,
+}
+
+impl AccountJson {
+    pub fn from_eoa(
+        address: H160,
+        cell: value::EoaLiveCell,
+        type_id_args: Option<JsonBytes>,
+    ) -> AccountJson {
+        AccountJson {
+            kind: AccountKind::Eoa,
+            address,
+            balance: cell.balance(),
+            capacity: cell.capacity(),
+            out_point: cell.out_point().into(),
+            type_id_args,
+            storage_root: None,
+            code_hash: None,
+            creation_out_point: None,
+            destructed: None,
+        }
+    }
+
+    pub fn from_contract(meta: ContractMeta, change: ContractChange) -> AccountJson {
+        let code_hash = H256::from_slice(&blake2b_256(meta.code.as_ref())[..]).unwrap();
+        let storage_root = smth256_to_h256(change.merkle_tree().root());
+        let creation_out_point = packed::OutPoint::new(meta.tx_hash.pack(), meta.output_index);
+        AccountJson {
+            kind: AccountKind::Contract,
+            address: meta.address.0,
+            balance: change.balance,
+            capacity: change.capacity,
+            out_point: change.out_point().into(),
+            type_id_args: None,
+            storage_root: Some(storage_root),
+            code_hash: Some(code_hash),
+            creation_out_point: Some(creation_out_point.into()),
+            destructed: Some(meta.destructed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransfersJson {
+    pub transfers: Vec<TransferInfo>,
+    /// Pass it to next `get_transfers` call to load next page
+    pub next_cursor: Option<TransferCursor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenHoldersJson {
+    pub holders: Vec<TokenBalanceInfo>,
+    /// Pass it to next `get_token_holders` call to load next page
+    pub next_cursor: Option<H160>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenTransfersJson {
+    pub transfers: Vec<TokenTransferInfo>,
+    /// Pass it to next `get_token_transfers` call to load next page
+    pub next_cursor: Option<TransferCursor>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_chain::MockChain;
+    use crate::storage::Key;
+    use bincode::serialize;
+    use ckb_types::{core::ScriptHashType, h160, h256};
+    use rocksdb::DB;
+
+    fn new_rpc(db: DB) -> RpcImpl {
+        let loader = Loader::new(Arc::new(db), Box::new(MockChain::new(Vec::new())));
+        RpcImpl {
+            loader: Arc::new(loader),
+            run_config: RunConfig::default(),
+            max_lag: None,
+        }
+    }
+
+    #[test]
+    fn test_get_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DB::open_default(dir.path()).unwrap();
+
+        // EoA account
+        let eoa_address = h160!("0xe1");
+        let eoa_tx_hash = h256!("0x1");
+        let cell = value::EoaLiveCell::new(eoa_tx_hash.clone(), 2, h160!("0xa1"), 1100, 1000);
+        db.put(
+            &Bytes::from(&Key::EoaLiveCell {
+                address: eoa_address.clone(),
+                out_point: Some(cell.out_point()),
+            }),
+            &serialize(&cell).unwrap(),
+        )
+        .unwrap();
+        let type_script = packed::Script::new_builder()
+            .hash_type(ScriptHashType::Type.into())
+            .args(Bytes::from(vec![7u8; 32]).pack())
+            .build();
+        let output = packed::CellOutput::new_builder()
+            .capacity(1100u64.pack())
+            .type_(Some(type_script).pack())
+            .build();
+        let out_point = packed::OutPoint::new(eoa_tx_hash.pack(), 2);
+        db.put(
+            &Bytes::from(&Key::CellData(out_point)),
+            &serialize(&value::CellData::new(&output, Bytes::from(vec![0u8; 16]))).unwrap(),
+        )
+        .unwrap();
+
+        // Contract account
+        let contract_address = ContractAddress(h160!("0xc1"));
+        let meta = value::ContractMeta {
+            code: Bytes::from(vec![0x60]),
+            tx_hash: h256!("0x2"),
+            output_index: 0,
+            balance: 300,
+            destructed: false,
+        };
+        db.put(
+            &Bytes::from(&Key::ContractMeta(contract_address.clone())),
+            &serialize(&meta).unwrap(),
+        )
+        .unwrap();
+        for (number, balance) in vec![(1, 100), (3, 300)] {
+            let change = value::ContractChange {
+                tx_hash: h256!("0x2"),
+                tx_origin: EoaAddress(eoa_address.clone()),
+                new_storage: Vec::new(),
+                capacity: 500 + balance,
+                balance,
+                is_create: number == 1,
+            };
+            let key = Key::ContractChange {
+                address: contract_address.clone(),
+                number: Some(number),
+                tx_index: Some(1),
+                output_index: Some(0),
+            };
+            db.put(&Bytes::from(&key), &serialize(&change).unwrap())
+                .unwrap();
+        }
+
+        let rpc = new_rpc(db);
+        let account = rpc.get_account(eoa_address.clone(), None).unwrap();
+        assert_eq!(account.kind, AccountKind::Eoa);
+        assert_eq!(account.address, eoa_address);
+        assert_eq!(account.balance, 1000);
+        assert_eq!(account.capacity, 1100);
+        assert_eq!(account.out_point, OutPoint::from(cell.out_point()));
+        assert_eq!(
+            account.type_id_args,
+            Some(JsonBytes::from_bytes(Bytes::from(vec![7u8; 32])))
+        );
+        assert!(account.storage_root.is_none());
+
+        let account = rpc.get_account(contract_address.0.clone(), None).unwrap();
+        assert_eq!(account.kind, AccountKind::Contract);
+        assert_eq!(account.balance, 300);
+        assert_eq!(account.capacity, 800);
+        assert_eq!(account.destructed, Some(false));
+        assert!(account.storage_root.is_some());
+        let code_hash = H256::from_slice(&blake2b_256(&[0x60u8][..])[..]).unwrap();
+        assert_eq!(account.code_hash, Some(code_hash));
+        // Query the history state
+        let account = rpc
+            .get_account(contract_address.0.clone(), Some(2))
+            .unwrap();
+        assert_eq!(account.balance, 100);
+
+        assert!(rpc.get_account(h160!("0xe2"), None).is_err());
+    }
+}
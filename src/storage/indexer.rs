@@ -1,6 +1,5 @@
-use bincode::serialize;
+use bincode::{deserialize, serialize};
 use ckb_chain_spec::consensus::TYPE_ID_CODE_HASH;
-use ckb_hash::new_blake2b;
 use ckb_jsonrpc_types::{CellOutput, JsonBytes, Script, ScriptHashType};
 use ckb_simple_account_layer::{run_with_context, CkbBlake2bHasher, Config, RunContext, RunResult};
 use ckb_types::{
@@ -20,41 +19,171 @@ use std::convert::TryFrom;
 use std::error::Error as StdError;
 use std::sync::Arc;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use super::{db_get, value, Key, Loader};
-use crate::client::HttpRpcClient;
+use super::prefetcher::BlockPrefetcher;
+use super::processor::{BlockProcessor, ProcessedBlock};
+use super::{db_get, value, Key, KeyType, Loader};
+use crate::client::ChainClient;
+use crate::eth_tx::eoa_cell_nonce;
+use crate::metrics;
 use crate::types::{
     cell_balance, contract_account_balance, h256_to_smth256, parse_log, smth256_to_h256,
     vm_load_data, vm_load_h160, vm_load_h256, vm_load_i32, vm_load_i64, vm_load_u256, vm_load_u32,
-    vm_load_u8, CallKind, ContractAddress, ContractChange, ContractMeta, EoaAddress, RunConfig,
-    WitnessData, ONE_CKB,
+    vm_load_u8, AddressMode, CallKind, ContractAddress, ContractChange, ContractMeta, EoaAddress,
+    RunConfig, WitnessData, ONE_CKB,
 };
 
 pub const TYPE_ARGS_LEN: usize = 20;
 // 32 bytes storage root + 32 bytes code_hash
 pub const OUTPUT_DATA_LEN: usize = 32 + 32;
+// The interval to refresh the CKB tip (for the metrics) while syncing
+const TIP_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
 pub struct Indexer {
     pub db: Arc<DB>,
     pub loader: Loader,
-    pub client: HttpRpcClient,
+    pub client: Box<dyn ChainClient>,
     pub run_config: RunConfig,
+    /// The BlockDelta of blocks deeper than this from the tip will be compacted
+    pub rollback_window: u64,
+    /// Return from `index()` when reach the tip
+    pub stop_at_tip: bool,
+    /// Start indexing from this block instead of genesis
+    pub start_block: Option<value::StartBlock>,
+    /// The number of blocks to download concurrently
+    pub prefetch_blocks: usize,
+    /// The custom indexes built from the processed blocks
+    pub processors: Vec<Box<dyn BlockProcessor>>,
 }
 
 impl Indexer {
-    pub fn new(db: Arc<DB>, ckb_uri: &str, run_config: RunConfig) -> Self {
-        let loader = Loader::new(Arc::clone(&db), ckb_uri).unwrap();
+    pub fn new(
+        db: Arc<DB>,
+        client: Box<dyn ChainClient>,
+        run_config: RunConfig,
+        rollback_window: u64,
+    ) -> Self {
+        let loader = Loader::new(Arc::clone(&db), client.clone());
         Indexer {
             db,
             loader,
-            client: HttpRpcClient::new(ckb_uri.to_string()),
+            client,
             run_config,
+            rollback_window,
+            stop_at_tip: false,
+            start_block: None,
+            prefetch_blocks: 1,
+            processors: Vec::new(),
+        }
+    }
+
+    // Check the start block against the one persisted in database, persist
+    // it if the database is empty.
+    fn init_start_block(&mut self) -> Result<(), String> {
+        let start_block_key_bytes = Bytes::from(&Key::StartBlock);
+        let stored_start_block: Option<value::StartBlock> =
+            db_get(&self.db, &start_block_key_bytes)?;
+        match (stored_start_block, self.start_block.clone()) {
+            (Some(stored), Some(start_block)) if stored != start_block => {
+                return Err(format!(
+                    "Start block not match the database: {}({:#x}) != {}({:#x})",
+                    start_block.number, start_block.hash, stored.number, stored.hash
+                ));
+            }
+            (Some(stored), _) => {
+                self.start_block = Some(stored);
+            }
+            (None, Some(start_block)) => {
+                if db_get::<_, value::Last>(&self.db, &Bytes::from(&Key::Last))?.is_some() {
+                    return Err(String::from(
+                        "Can not set start block, the database already indexed from genesis",
+                    ));
+                }
+                let header = self
+                    .client
+                    .get_header_by_number(start_block.number)?
+                    .ok_or_else(|| format!("Start block not found: {}", start_block.number))?;
+                if header.hash != start_block.hash {
+                    return Err(format!(
+                        "Start block hash not match: {:#x} != {:#x}",
+                        start_block.hash, header.hash
+                    ));
+                }
+                log::info!(
+                    "Persist start block: {}({:#x})",
+                    start_block.number,
+                    start_block.hash
+                );
+                self.db
+                    .put(&start_block_key_bytes, &serialize(&start_block).unwrap())
+                    .map_err(|err| err.to_string())?;
+            }
+            (None, None) => {}
+        }
+        Ok(())
+    }
+
+    // Compact the BlockDelta out of rollback window into BlockSummary, the
+    // rollback only data is removed.
+    pub fn prune_block_deltas(&self, tip_number: u64) -> Result<(), String> {
+        if tip_number <= self.rollback_window {
+            return Ok(());
+        }
+        let prune_to = tip_number - self.rollback_window;
+        let key_prefix = [KeyType::BlockDelta as u8];
+        let mut batch = WriteBatch::default();
+        let mut pruned_count = 0;
+        let mut iter = self.db.raw_iterator();
+        iter.seek(&key_prefix);
+        while iter.valid() {
+            if let Some((key_bytes, value_bytes)) = iter
+                .key()
+                .filter(|key| key.starts_with(&key_prefix))
+                .and_then(|key| iter.value().map(|value| (key, value)))
+            {
+                let number = match Key::try_from(key_bytes)? {
+                    Key::BlockDelta(number) => number,
+                    _ => {
+                        panic!("DB corrupted deserialize Key::BlockDelta");
+                    }
+                };
+                if number >= prune_to {
+                    break;
+                }
+                let block_delta: value::BlockDelta =
+                    deserialize(value_bytes).map_err(|err| err.to_string())?;
+                // The consumed cells can not be restored any more
+                for (_, _, _, _, value) in block_delta.removed_cells {
+                    batch.delete(&Bytes::from(&Key::CellData(value.out_point())));
+                }
+                let block_summary = value::BlockSummary {
+                    contracts: block_delta.contracts,
+                };
+                batch.put(
+                    &Bytes::from(&Key::BlockSummary(number)),
+                    &serialize(&block_summary).unwrap(),
+                );
+                batch.delete(key_bytes);
+                pruned_count += 1;
+            } else {
+                break;
+            }
+            iter.next();
         }
+        if pruned_count > 0 {
+            log::debug!(
+                "Compacted {} block deltas before block {}",
+                pruned_count,
+                prune_to
+            );
+            self.db.write(batch).map_err(|err| err.to_string())?;
+        }
+        Ok(())
     }
 
-    // Ideally this should never return. The caller is responsible for wrapping
-    // it into a separate thread.
+    // Ideally this should never return (unless `stop_at_tip` is set). The
+    // caller is responsible for wrapping it into a separate thread.
     pub fn index(&mut self) -> Result<(), String> {
         let type_code_hash: H256 = self.run_config.type_script.code_hash().unpack();
         let type_hash_type = {
@@ -72,163 +201,80 @@ impl Indexer {
         log::info!("type hash type: {:?}", type_hash_type);
         log::info!("eoa lock code hash: {:x}", eoa_lock_code_hash);
         log::info!("eoa lock hash type: {:?}", eoa_lock_hash_type);
+        let address_mode = self.run_config.address_mode;
+        log::info!("eoa address mode: {:?}", address_mode);
+        self.init_start_block()?;
+        let start_number = self
+            .start_block
+            .as_ref()
+            .map(|start_block| start_block.number)
+            .unwrap_or(0);
         let last_block_key_bytes = Bytes::from(&Key::Last);
+        let mut prefetcher = BlockPrefetcher::new(self.client.clone(), self.prefetch_blocks);
+        let mut last_tip_check: Option<Instant> = None;
         loop {
-            let next_header = if let Some(value::Last { number, hash }) =
-                db_get(&self.db, &last_block_key_bytes)?
-            {
-                match self.client.get_header_by_number(number + 1) {
-                    Ok(Some(header)) if header.inner.parent_hash == hash => header,
-                    // Rollback
-                    Ok(Some(_header)) => {
-                        log::info!("Rollback block, nubmer={}, hash={}", number, hash);
-                        let block_delta_key = Bytes::from(&Key::BlockDelta(number));
-                        let block_delta: value::BlockDelta = db_get(&self.db, &block_delta_key)?
-                            .unwrap_or_else(|| panic!("Can not load BlockDelta({})", number));
-                        let last_block_info_opt = if number >= 1 {
-                            let last_block_map_key = Bytes::from(&Key::BlockMap(number - 1));
-                            let block_hash: value::BlockMap =
-                                db_get(&self.db, &last_block_map_key)?.unwrap_or_else(|| {
-                                    panic!("Can not load BlockMap({})", number - 1)
-                                });
-                            Some(value::Last {
-                                number: number - 1,
-                                hash: block_hash.0,
-                            })
-                        } else {
-                            None
-                        };
-
-                        let mut batch = WriteBatch::default();
-                        for (address, is_create) in block_delta.contracts {
-                            let change_start_key = Key::ContractChange {
-                                address: address.clone(),
-                                number: Some(number),
-                                tx_index: None,
-                                output_index: None,
-                            };
-                            let change_end_key = Key::ContractChange {
-                                address: address.clone(),
-                                number: Some(number + 1),
-                                tx_index: None,
-                                output_index: None,
-                            };
-                            let logs_start_key = Key::ContractLogs {
-                                address: address.clone(),
-                                number: Some(number),
-                                tx_index: None,
-                                output_index: None,
-                            };
-                            let logs_end_key = Key::ContractLogs {
-                                address: address.clone(),
-                                number: Some(number + 1),
-                                tx_index: None,
-                                output_index: None,
-                            };
-                            batch.delete_range(
-                                &Bytes::from(&change_start_key),
-                                &Bytes::from(&change_end_key),
-                            );
-                            batch.delete_range(
-                                &Bytes::from(&logs_start_key),
-                                &Bytes::from(&logs_end_key),
-                            );
-                            if is_create {
-                                batch.delete(&Bytes::from(&Key::ContractMeta(address)));
-                            }
-                        }
-                        for (lock_hash, tx_index, output_index, value) in block_delta.added_cells {
-                            batch.delete(&Bytes::from(&Key::LockLiveCell {
-                                lock_hash,
-                                number: Some(number),
-                                tx_index: Some(tx_index),
-                                output_index: Some(output_index),
-                            }));
-                            batch.delete(&Bytes::from(&Key::LiveCellMap(value.out_point())));
-                        }
-                        for (lock_hash, old_number, tx_index, output_index, value) in
-                            block_delta.removed_cells
-                        {
-                            let key = Key::LockLiveCell {
-                                lock_hash,
-                                number: Some(old_number),
-                                tx_index: Some(tx_index),
-                                output_index: Some(output_index),
-                            };
-                            batch.put(&Bytes::from(&key), &serialize(&value).unwrap());
-                            let map_key = Key::LiveCellMap(value.out_point());
-                            let map_value = value::LiveCellMap {
-                                number: old_number,
-                                tx_index,
-                            };
-                            batch.put(&Bytes::from(&map_key), &serialize(&map_value).unwrap());
-                        }
-                        for eoa_address in block_delta.eoa_added_cells {
-                            batch.delete(&Bytes::from(&Key::EoaLiveCell(eoa_address)));
-                        }
-                        for (eoa_address, value) in block_delta.eoa_removed_cells {
-                            let key = Key::EoaLiveCell(eoa_address);
-                            batch.put(&Bytes::from(&key), &serialize(&value).unwrap());
-                        }
-                        for contract_address in block_delta.destructed_contracts {
-                            let key_bytes =
-                                Bytes::from(&Key::ContractMeta(contract_address.clone()));
-                            let mut meta: value::ContractMeta = db_get(&self.db, &key_bytes)?
-                                .ok_or_else(|| {
-                                    format!("no such contract: {:x}", contract_address.0)
-                                })?;
-                            assert_eq!(meta.destructed, true);
-                            meta.destructed = false;
-                            batch.put(&key_bytes, &serialize(&meta).unwrap());
-                        }
-                        batch.delete(&Bytes::from(&Key::BlockMap(number)));
-                        batch.delete(&block_delta_key);
-                        // Update last block info
-                        if let Some(block_info) = last_block_info_opt {
-                            let value_bytes =
-                                serialize(&block_info).map_err(|err| err.to_string())?;
-                            batch.put(&last_block_key_bytes, &value_bytes);
+            self.loader.indexer_heartbeat();
+            let last_block_info: Option<value::Last> = db_get(&self.db, &last_block_key_bytes)?;
+            if let Some(last) = last_block_info.as_ref() {
+                metrics::INDEXER_TIP.set(last.number as i64);
+            }
+            let next_number = last_block_info
+                .as_ref()
+                .map(|last| last.number + 1)
+                .unwrap_or(start_number);
+            let next_block = match prefetcher.get_block(next_number) {
+                Ok(Some(block)) => block,
+                Ok(None) => {
+                    if let Some(value::Last { number, .. }) = last_block_info {
+                        if self.stop_at_tip {
+                            log::info!("Reach the tip: {}", number);
+                            return Ok(());
                         }
-                        self.db.write(batch).map_err(|err| err.to_string())?;
-                        continue;
-                    }
-                    Ok(None) => {
+                        metrics::CKB_TIP.set(number as i64);
+                        self.loader.record_ckb_tip(number);
                         // Reach the tip, wait 50ms for next block
                         sleep(Duration::from_millis(50));
-                        // TODO: clean up OLD block delta here (before tip-200)
-                        continue;
-                    }
-                    Err(err) => {
-                        log::warn!("RPC error: {}", err);
-                        sleep(Duration::from_millis(5000));
-                        continue;
+                        // Drop the timeout pending transactions
+                        self.loader
+                            .remove_pending_txs(&HashSet::default(), &HashSet::default());
+                    } else {
+                        log::warn!("Start block not found: {}", next_number);
+                        sleep(Duration::from_millis(1000));
                     }
-                }
-            } else {
-                self.client.get_header_by_number(0)?.unwrap()
-            };
-
-            log::debug!(
-                "get block {} => {:x}",
-                next_header.inner.number.value(),
-                next_header.hash
-            );
-            let next_block = match self.client.get_block(next_header.hash.clone()) {
-                Ok(Some(block)) => block,
-                Ok(None) => {
-                    log::warn!("Can not get block by hash: {:?}", next_header.hash);
-                    sleep(Duration::from_millis(200));
                     continue;
                 }
                 Err(err) => {
-                    log::error!("RPC error: {}", err);
-                    sleep(Duration::from_millis(1000));
+                    // The request already retried by the client
+                    log::warn!("RPC error: {}", err);
+                    sleep(self.client.error_backoff());
                     continue;
                 }
             };
+            if let Some(value::Last { number, hash }) = last_block_info {
+                // Rollback (the prefetched blocks may also be orphaned)
+                if next_block.header.inner.parent_hash != hash {
+                    log::info!("Rollback block, nubmer={}, hash={}", number, hash);
+                    rollback_block(&self.db, number, &self.processors)?;
+                    self.loader.record_rollback(number, hash);
+                    metrics::BLOCKS_ROLLED_BACK.inc();
+                    prefetcher.reset();
+                    continue;
+                }
+            }
 
-            let next_number = next_header.inner.number.value();
-            let next_hash = next_header.hash;
+            // Refresh the CKB tip for the lag metric while syncing
+            if last_tip_check.map_or(true, |time| time.elapsed() >= TIP_CHECK_INTERVAL) {
+                match self.client.get_tip_block_number() {
+                    Ok(tip) => {
+                        metrics::CKB_TIP.set(tip as i64);
+                        self.loader.record_ckb_tip(tip);
+                    }
+                    Err(err) => log::warn!("Get CKB tip failed: {}", err),
+                }
+                last_tip_check = Some(Instant::now());
+            }
+            let process_start = Instant::now();
+            let next_hash = next_block.header.hash.clone();
 
             log::info!(
                 "Process block: hash={:#x}, number={}",
@@ -239,21 +285,33 @@ impl Indexer {
             let mut block_changes: Vec<ContractChange> = Vec::new();
             let mut block_codes: Vec<ContractMeta> = Vec::new();
             let mut destructed_contracts: Vec<ContractAddress> = Vec::new();
+            // (eoa_address, tx_index, transfer_index, transfer)
+            let mut eoa_transfers: Vec<(H160, u32, u32, value::EoaTransfer)> = Vec::new();
 
-            let mut eoa_added_cells: HashMap<H160, value::EoaLiveCell> = HashMap::new();
-            let mut eoa_removed_cells: HashMap<H160, value::EoaLiveCell> = HashMap::new();
+            // One address may own many cells (Ethereum address mode), so index by out point
+            let mut eoa_added_cells: HashMap<packed::OutPoint, (H160, value::EoaLiveCell)> =
+                HashMap::new();
+            let mut eoa_removed_cells: HashMap<packed::OutPoint, (H160, value::EoaLiveCell)> =
+                HashMap::new();
+            // The next nonce of the owners (lock args) sent Ethereum transactions
+            let mut block_eth_nonces: HashMap<H160, u64> = HashMap::new();
             let mut added_cells: HashSet<(H256, u32, u32, value::LockLiveCell)> = HashSet::new();
             let mut removed_cells: HashSet<(H256, u64, u32, u32, value::LockLiveCell)> =
                 HashSet::new();
             let mut block_added_cells: HashMap<value::LockLiveCell, value::LiveCellMap> =
                 HashMap::default();
             let mut block_removed_cells: HashSet<value::LockLiveCell> = HashSet::default();
+            // Output and data of contract and EoA cells
+            let mut block_cell_data: HashMap<value::LockLiveCell, value::CellData> =
+                HashMap::default();
+            let mut block_tx_hashes: HashSet<H256> = HashSet::default();
             for (tx_index, (tx, tx_hash)) in next_block
                 .transactions
                 .into_iter()
                 .map(|tx| (tx.inner, tx.hash))
                 .enumerate()
             {
+                block_tx_hashes.insert(tx_hash.clone());
                 log::debug!("process tx: hash={:#x}, tx_index: {}", tx_hash, tx_index);
                 // Information from upper level
                 //   1. block number
@@ -261,7 +319,18 @@ impl Indexer {
                 //   3. tx_index
                 let mut script_groups: HashMap<ContractAddress, ContractInfo> = HashMap::default();
                 let mut eoa_accounts: HashMap<H160, (u64, u64)> = HashMap::default();
+                // The EoA inputs: address => (lock args, capacity)
+                let mut eoa_inputs: HashMap<H160, (H160, u64)> = HashMap::default();
+                let mut eoa_outputs: HashSet<H160> = HashSet::default();
 
+                // Fetch all the previous transactions in one batch
+                let prev_tx_hashes = tx
+                    .inputs
+                    .iter()
+                    .map(|input| input.previous_output.tx_hash.clone())
+                    .filter(|tx_hash| tx_hash != &H256::default())
+                    .collect::<Vec<_>>();
+                let mut prev_txs = self.client.get_transactions(prev_tx_hashes)?.into_iter();
                 for (input_index, input) in tx.inputs.into_iter().enumerate() {
                     // Information from input
                     //   1. is_create
@@ -271,10 +340,10 @@ impl Indexer {
                         continue;
                     }
 
-                    let prev_tx = self
-                        .client
-                        .get_transaction(input.previous_output.tx_hash.clone())?
-                        .unwrap()
+                    let prev_tx = prev_txs
+                        .next()
+                        .flatten()
+                        .expect("previous transaction")
                         .transaction
                         .inner;
                     let prev_index = input.previous_output.index.value() as usize;
@@ -328,6 +397,7 @@ impl Indexer {
                         &lock_script,
                         &eoa_lock_code_hash,
                         &eoa_lock_hash_type,
+                        address_mode,
                     );
                     let value = value::LockLiveCell {
                         tx_hash: prev_tx_hash,
@@ -350,16 +420,27 @@ impl Indexer {
                         let (eoa_address, eoa_value) = eoa_record(
                             &type_script,
                             &lock_script,
-                            &tx_hash,
+                            &value.tx_hash,
                             prev_output_index as u32,
                             &output,
                             output_data_size,
+                            address_mode,
+                        );
+                        // The other cells of the same address (Ethereum address
+                        // mode) are merged into the first one with their
+                        // occupied capacity, the same as the validator
+                        eoa_accounts.entry(eoa_address.clone()).or_insert((0, 0)).0 +=
+                            if eoa_inputs.contains_key(&eoa_address) {
+                                eoa_value.capacity()
+                            } else {
+                                eoa_value.balance()
+                            };
+                        eoa_inputs.insert(
+                            eoa_address.clone(),
+                            (eoa_value.lock_args.clone(), eoa_value.capacity()),
                         );
-                        eoa_accounts.insert(eoa_address.clone(), (eoa_value.balance(), 0));
-                        if eoa_added_cells.contains_key(&eoa_address) {
-                            let _ = eoa_added_cells.remove(&eoa_address);
-                        } else {
-                            eoa_removed_cells.insert(eoa_address, eoa_value);
+                        if eoa_added_cells.remove(&out_point).is_none() {
+                            eoa_removed_cells.insert(out_point.clone(), (eoa_address, eoa_value));
                         }
                     }
                     block_removed_cells.insert(value.clone());
@@ -392,11 +473,11 @@ impl Indexer {
                     }
                     let type_script = output.type_.clone().unwrap_or_default();
                     let lock_script = output.lock.clone();
-                    if data.len() == OUTPUT_DATA_LEN
+                    let is_contract = data.len() == OUTPUT_DATA_LEN
                         && type_script.code_hash == type_code_hash
                         && type_script.hash_type == type_hash_type
-                        && type_script.args.len() == TYPE_ARGS_LEN
-                    {
+                        && type_script.args.len() == TYPE_ARGS_LEN;
+                    if is_contract {
                         log::debug!("match type script: output_index={}", output_index);
                         let address = ContractAddress::try_from(type_script.args.as_bytes())
                             .expect("checked length");
@@ -418,6 +499,7 @@ impl Indexer {
                         &lock_script,
                         &eoa_lock_code_hash,
                         &eoa_lock_hash_type,
+                        address_mode,
                     );
                     let value = value::LockLiveCell {
                         tx_hash: tx_hash.clone(),
@@ -437,6 +519,15 @@ impl Indexer {
                             tx_index: tx_index as u32,
                         },
                     );
+                    if is_contract || is_eoa {
+                        block_cell_data.insert(
+                            value.clone(),
+                            value::CellData::new(
+                                &packed::CellOutput::from(output.clone()),
+                                data.clone(),
+                            ),
+                        );
+                    }
                     if is_eoa {
                         let (eoa_address, eoa_value) = eoa_record(
                             &type_script,
@@ -445,15 +536,23 @@ impl Indexer {
                             output_index as u32,
                             &output,
                             data_size,
+                            address_mode,
                         );
-                        if eoa_removed_cells.contains_key(&eoa_address) {
-                            let _ = eoa_removed_cells.remove(&eoa_address);
-                            eoa_accounts
-                                .get_mut(&eoa_address)
-                                .expect("eoa not exists")
-                                .1 = eoa_value.balance();
+                        // The new EoA cell of an unknown recipient has no input
+                        eoa_accounts.entry(eoa_address.clone()).or_insert((0, 0)).1 +=
+                            eoa_value.balance();
+                        // The nonce of the Ethereum transactions in the cell
+                        // data is verified by the validator
+                        if address_mode == AddressMode::Ethereum {
+                            if let Ok(nonce) = eoa_cell_nonce(&data) {
+                                let max_nonce = block_eth_nonces
+                                    .entry(eoa_value.lock_args.clone())
+                                    .or_insert(0);
+                                *max_nonce = std::cmp::max(*max_nonce, nonce);
+                            }
                         }
-                        eoa_added_cells.insert(eoa_address, eoa_value);
+                        eoa_outputs.insert(eoa_address.clone());
+                        eoa_added_cells.insert(eoa_value.out_point(), (eoa_address, eoa_value));
                     }
                     added_cells.insert((lock_hash, tx_index as u32, output_index as u32, value));
                 }
@@ -482,10 +581,16 @@ impl Indexer {
                         script_groups,
                         eoa_accounts,
                     )? {
+                        extractor.merge_tx_origin_cells(&eoa_inputs, &eoa_outputs);
                         extractor.run().map_err(|err| err.to_string())?;
                         block_changes.extend(extractor.get_contract_changes(next_number));
                         block_codes.extend(extractor.get_created_contracts());
                         destructed_contracts.extend(extractor.get_destructed_contracts());
+                        eoa_transfers.extend(extractor.get_eoa_transfers().into_iter().map(
+                            |(address, transfer_index, transfer)| {
+                                (address, tx_index as u32, transfer_index, transfer)
+                            },
+                        ));
                     }
                 }
             }
@@ -506,6 +611,22 @@ impl Indexer {
             let last_block_info_bytes = serialize(&last_block_info).unwrap();
             batch.put(&last_block_key_bytes, &last_block_info_bytes);
 
+            let processed_block = ProcessedBlock {
+                number: next_number,
+                hash: &next_hash,
+                contract_changes: &block_changes,
+                created_contracts: &block_codes,
+                destructed_contracts: &destructed_contracts,
+                eoa_added_cells: &eoa_added_cells,
+                eoa_removed_cells: &eoa_removed_cells,
+            };
+            let mut processor_undos: Vec<(String, Bytes)> = Vec::new();
+            for processor in self.processors.iter_mut() {
+                let undo = processor.process(&self.db, &mut batch, &processed_block)?;
+                if !undo.is_empty() {
+                    processor_undos.push((processor.name().to_string(), undo));
+                }
+            }
             let mut block_contracts: HashMap<ContractAddress, bool> = HashMap::default();
             for change in block_changes {
                 block_contracts.insert(change.address.clone(), change.is_create);
@@ -564,6 +685,13 @@ impl Indexer {
                 };
                 batch.put(&Bytes::from(&map_key), &serialize(&map_value).unwrap());
             }
+            for (value, cell_data) in block_cell_data {
+                if common_cells.contains(&value) {
+                    continue;
+                }
+                let key = Key::CellData(value.out_point());
+                batch.put(&Bytes::from(&key), &serialize(&cell_data).unwrap());
+            }
             for (lock_hash, number, tx_index, output_index, value) in removed_cells.clone() {
                 if common_cells.contains(&value) {
                     continue;
@@ -582,17 +710,60 @@ impl Indexer {
                 batch.delete(&Bytes::from(&key));
                 batch.delete(&Bytes::from(&Key::LiveCellMap(value.out_point())));
             }
-            for (eoa_address, eoa_value) in eoa_added_cells.clone() {
-                log::debug!("add eoa account: {:x}", eoa_address);
-                let key = Key::EoaLiveCell(eoa_address);
-                batch.put(&Bytes::from(&key), &serialize(&eoa_value).unwrap());
+            for (out_point, (eoa_address, eoa_value)) in eoa_added_cells.iter() {
+                log::debug!("add eoa cell: {:x}, {}", eoa_address, out_point);
+                let eoa_value_bytes = serialize(eoa_value).unwrap();
+                let owner_key = Key::EoaOwnerCell {
+                    lock_args: eoa_value.lock_args.clone(),
+                    address: Some(eoa_address.clone()),
+                    out_point: Some(out_point.clone()),
+                };
+                batch.put(&Bytes::from(&owner_key), &eoa_value_bytes);
+                let key = Key::EoaLiveCell {
+                    address: eoa_address.clone(),
+                    out_point: Some(out_point.clone()),
+                };
+                batch.put(&Bytes::from(&key), &eoa_value_bytes);
             }
-            for (eoa_address, _) in eoa_removed_cells.iter() {
-                log::debug!("remove eoa account: {:x}", eoa_address);
-                let key = Key::EoaLiveCell(eoa_address.clone());
+            for (out_point, (eoa_address, eoa_value)) in eoa_removed_cells.iter() {
+                log::debug!("remove eoa cell: {:x}, {}", eoa_address, out_point);
+                batch.delete(&Bytes::from(&Key::EoaOwnerCell {
+                    lock_args: eoa_value.lock_args.clone(),
+                    address: Some(eoa_address.clone()),
+                    out_point: Some(out_point.clone()),
+                }));
+                let key = Key::EoaLiveCell {
+                    address: eoa_address.clone(),
+                    out_point: Some(out_point.clone()),
+                };
                 batch.delete(&Bytes::from(&key));
             }
 
+            let mut eth_nonces: Vec<(H160, Option<u64>)> = Vec::new();
+            for (lock_args, nonce) in block_eth_nonces {
+                // The nonce of an owner is the largest nonce of its cells
+                let key_bytes = Bytes::from(&Key::EthNonce(lock_args.clone()));
+                let old_nonce =
+                    db_get::<_, value::EthNonce>(&self.db, &key_bytes)?.map(|nonce| nonce.0);
+                if old_nonce.map_or(nonce > 0, |old_nonce| nonce > old_nonce) {
+                    batch.put(&key_bytes, &serialize(&value::EthNonce(nonce)).unwrap());
+                    eth_nonces.push((lock_args, old_nonce));
+                }
+            }
+
+            let mut transfer_addresses: HashSet<H160> = HashSet::default();
+            for (eoa_address, tx_index, transfer_index, transfer) in eoa_transfers {
+                // Key::EoaTransfer
+                let key = Key::EoaTransfer {
+                    address: eoa_address.clone(),
+                    number: Some(next_number),
+                    tx_index: Some(tx_index),
+                    transfer_index: Some(transfer_index),
+                };
+                batch.put(&Bytes::from(&key), &serialize(&transfer).unwrap());
+                transfer_addresses.insert(eoa_address);
+            }
+
             // selfdestruct
             for contract_address in &destructed_contracts {
                 // For clean up logs when rollback
@@ -604,17 +775,24 @@ impl Indexer {
                 meta.destructed = true;
                 batch.put(&key_bytes, &serialize(&meta).unwrap());
             }
+            let consumed_cells = removed_cells
+                .iter()
+                .map(|(_, _, _, _, value)| (value.tx_hash.clone(), value.output_index))
+                .collect::<HashSet<_>>();
             // Key::BlockDelta
             let block_delta = value::BlockDelta {
                 contracts: block_contracts.into_iter().collect(),
                 added_cells: added_cells.into_iter().collect(),
                 removed_cells: removed_cells.into_iter().collect(),
-                eoa_added_cells: eoa_added_cells
+                eoa_added_cells: eoa_added_cells.into_iter().map(|(_, cell)| cell).collect(),
+                eoa_removed_cells: eoa_removed_cells
                     .into_iter()
-                    .map(|(eoa_address, _)| eoa_address)
+                    .map(|(_, cell)| cell)
                     .collect(),
-                eoa_removed_cells: eoa_removed_cells.into_iter().collect(),
                 destructed_contracts,
+                eoa_transfers: transfer_addresses.into_iter().collect(),
+                processors: processor_undos,
+                eth_nonces,
             };
             let block_contracts_bytes = serialize(&block_delta).unwrap();
             batch.put(
@@ -623,8 +801,213 @@ impl Indexer {
             );
 
             self.db.write(batch).map_err(|err| err.to_string())?;
+            self.loader
+                .remove_pending_txs(&block_tx_hashes, &consumed_cells);
+            // Prune the blocks out of the rollback window while syncing too
+            self.prune_block_deltas(next_number)?;
+            metrics::BLOCK_PROCESS_SECONDS.observe(process_start.elapsed().as_secs_f64());
+            metrics::BLOCKS_PROCESSED.inc();
+        }
+    }
+}
+
+/// Rollback the block (must be the last indexed block) by applying the
+/// BlockDelta in reverse, the processors which have undo data in the block
+/// must be provided.
+pub fn rollback_block(
+    db: &DB,
+    number: u64,
+    processors: &[Box<dyn BlockProcessor>],
+) -> Result<(), String> {
+    let block_delta_key = Bytes::from(&Key::BlockDelta(number));
+    let block_delta: value::BlockDelta = db_get(db, &block_delta_key)?.ok_or_else(|| {
+        format!(
+            "Can not load BlockDelta({}), rollback beyond rollback window?",
+            number
+        )
+    })?;
+    let start_block: Option<value::StartBlock> = db_get(db, &Bytes::from(&Key::StartBlock))?;
+    let start_number = start_block
+        .map(|start_block| start_block.number)
+        .unwrap_or(0);
+    let last_block_info_opt = if number > start_number {
+        let last_block_map_key = Bytes::from(&Key::BlockMap(number - 1));
+        let block_hash: value::BlockMap = db_get(db, &last_block_map_key)?
+            .ok_or_else(|| format!("Can not load BlockMap({})", number - 1))?;
+        Some(value::Last {
+            number: number - 1,
+            hash: block_hash.0,
+        })
+    } else {
+        None
+    };
+
+    let mut batch = WriteBatch::default();
+    for (address, is_create) in block_delta.contracts {
+        let change_start_key = Key::ContractChange {
+            address: address.clone(),
+            number: Some(number),
+            tx_index: None,
+            output_index: None,
+        };
+        let change_end_key = Key::ContractChange {
+            address: address.clone(),
+            number: Some(number + 1),
+            tx_index: None,
+            output_index: None,
+        };
+        let logs_start_key = Key::ContractLogs {
+            address: address.clone(),
+            number: Some(number),
+            tx_index: None,
+            output_index: None,
+        };
+        let logs_end_key = Key::ContractLogs {
+            address: address.clone(),
+            number: Some(number + 1),
+            tx_index: None,
+            output_index: None,
+        };
+        batch.delete_range(
+            &Bytes::from(&change_start_key),
+            &Bytes::from(&change_end_key),
+        );
+        batch.delete_range(&Bytes::from(&logs_start_key), &Bytes::from(&logs_end_key));
+        if is_create {
+            batch.delete(&Bytes::from(&Key::ContractMeta(address)));
+        }
+    }
+    for (lock_hash, tx_index, output_index, value) in block_delta.added_cells {
+        batch.delete(&Bytes::from(&Key::LockLiveCell {
+            lock_hash,
+            number: Some(number),
+            tx_index: Some(tx_index),
+            output_index: Some(output_index),
+        }));
+        batch.delete(&Bytes::from(&Key::LiveCellMap(value.out_point())));
+        batch.delete(&Bytes::from(&Key::CellData(value.out_point())));
+    }
+    for (lock_hash, old_number, tx_index, output_index, value) in block_delta.removed_cells {
+        let key = Key::LockLiveCell {
+            lock_hash,
+            number: Some(old_number),
+            tx_index: Some(tx_index),
+            output_index: Some(output_index),
+        };
+        batch.put(&Bytes::from(&key), &serialize(&value).unwrap());
+        let map_key = Key::LiveCellMap(value.out_point());
+        let map_value = value::LiveCellMap {
+            number: old_number,
+            tx_index,
+        };
+        batch.put(&Bytes::from(&map_key), &serialize(&map_value).unwrap());
+    }
+    for (eoa_address, value) in block_delta.eoa_added_cells {
+        batch.delete(&Bytes::from(&Key::EoaOwnerCell {
+            lock_args: value.lock_args.clone(),
+            address: Some(eoa_address.clone()),
+            out_point: Some(value.out_point()),
+        }));
+        batch.delete(&Bytes::from(&Key::EoaLiveCell {
+            address: eoa_address,
+            out_point: Some(value.out_point()),
+        }));
+    }
+    for (eoa_address, value) in block_delta.eoa_removed_cells {
+        let value_bytes = serialize(&value).unwrap();
+        let owner_key = Key::EoaOwnerCell {
+            lock_args: value.lock_args.clone(),
+            address: Some(eoa_address.clone()),
+            out_point: Some(value.out_point()),
+        };
+        batch.put(&Bytes::from(&owner_key), &value_bytes);
+        let key = Key::EoaLiveCell {
+            address: eoa_address,
+            out_point: Some(value.out_point()),
+        };
+        batch.put(&Bytes::from(&key), &value_bytes);
+    }
+    for (lock_args, old_nonce) in block_delta.eth_nonces {
+        let key_bytes = Bytes::from(&Key::EthNonce(lock_args));
+        if let Some(nonce) = old_nonce {
+            batch.put(&key_bytes, &serialize(&value::EthNonce(nonce)).unwrap());
+        } else {
+            batch.delete(&key_bytes);
         }
     }
+    for eoa_address in block_delta.eoa_transfers {
+        let transfer_start_key = Key::EoaTransfer {
+            address: eoa_address.clone(),
+            number: Some(number),
+            tx_index: None,
+            transfer_index: None,
+        };
+        let transfer_end_key = Key::EoaTransfer {
+            address: eoa_address,
+            number: Some(number + 1),
+            tx_index: None,
+            transfer_index: None,
+        };
+        batch.delete_range(
+            &Bytes::from(&transfer_start_key),
+            &Bytes::from(&transfer_end_key),
+        );
+    }
+    for (name, undo) in block_delta.processors {
+        let processor = processors
+            .iter()
+            .find(|processor| processor.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "Block processor {} is required to rollback block {}",
+                    name, number
+                )
+            })?;
+        processor.rollback(db, &mut batch, number, &undo)?;
+    }
+    for contract_address in block_delta.destructed_contracts {
+        let key_bytes = Bytes::from(&Key::ContractMeta(contract_address.clone()));
+        let mut meta: value::ContractMeta = db_get(db, &key_bytes)?
+            .ok_or_else(|| format!("no such contract: {:x}", contract_address.0))?;
+        assert_eq!(meta.destructed, true);
+        meta.destructed = false;
+        batch.put(&key_bytes, &serialize(&meta).unwrap());
+    }
+    batch.delete(&Bytes::from(&Key::BlockMap(number)));
+    batch.delete(&block_delta_key);
+    // Update last block info
+    let last_block_key_bytes = Bytes::from(&Key::Last);
+    if let Some(block_info) = last_block_info_opt {
+        let value_bytes = serialize(&block_info).map_err(|err| err.to_string())?;
+        batch.put(&last_block_key_bytes, &value_bytes);
+    } else {
+        // The start block (genesis by default) is rolled back
+        batch.delete(&last_block_key_bytes);
+    }
+    db.write(batch).map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Rollback the indexed blocks until the last indexed block is `number`,
+/// rollback all blocks if `number` is None.
+pub fn rollback_to(
+    db: &DB,
+    number: Option<u64>,
+    processors: &[Box<dyn BlockProcessor>],
+) -> Result<(), String> {
+    let last_block_key_bytes = Bytes::from(&Key::Last);
+    while let Some(value::Last {
+        number: last_number,
+        hash,
+    }) = db_get(db, &last_block_key_bytes)?
+    {
+        if Some(last_number) <= number {
+            break;
+        }
+        log::info!("Rollback block, nubmer={}, hash={}", last_number, hash);
+        rollback_block(db, last_number, processors)?;
+    }
+    Ok(())
 }
 
 fn is_eoa(
@@ -632,31 +1015,37 @@ fn is_eoa(
     lock_script: &Script,
     eoa_lock_code_hash: &H256,
     eoa_lock_hash_type: &ScriptHashType,
+    address_mode: AddressMode,
 ) -> bool {
     type_script.code_hash == TYPE_ID_CODE_HASH
         && type_script.hash_type == ScriptHashType::Type
         && &lock_script.code_hash == eoa_lock_code_hash
         && &lock_script.hash_type == eoa_lock_hash_type
+        && address_mode.is_valid_lock_args(lock_script.args.as_bytes())
 }
 
-fn eoa_record(
+pub(super) fn eoa_record(
     type_script: &Script,
     lock_script: &Script,
     tx_hash: &H256,
     output_index: u32,
     output: &CellOutput,
     data_size: u32,
+    address_mode: AddressMode,
 ) -> (H160, value::EoaLiveCell) {
-    let mut blake2b = new_blake2b();
-    blake2b.update(type_script.args.as_bytes());
-    blake2b.update(lock_script.args.as_bytes());
-    let mut result = [0u8; 32];
-    blake2b.finalize(&mut result);
-    let eoa_address = H160::from_slice(&result[0..20]).expect("convert to h160");
+    let eoa_address =
+        address_mode.eoa_address(type_script.args.as_bytes(), lock_script.args.as_bytes());
+    let lock_args = lock_script
+        .args
+        .as_bytes()
+        .get(0..20)
+        .and_then(|args| H160::from_slice(args).ok())
+        .unwrap_or_default();
     let packed_output = packed::CellOutput::from(output.clone());
     let eoa_value = value::EoaLiveCell::new(
         tx_hash.clone(),
         output_index,
+        lock_args,
         output.capacity.value(),
         cell_balance(&packed_output, (data_size as u64) * ONE_CKB),
     );
@@ -682,6 +1071,8 @@ pub struct ContractExtractor {
     script_groups: HashMap<ContractAddress, ContractInfo>,
     // EoA address => (input_balance, output_balance)
     eoa_accounts: HashMap<H160, (u64, u64)>,
+    // (sender, destination, value)
+    transfers: Vec<(H160, H160, u64)>,
 }
 
 #[derive(Default)]
@@ -708,6 +1099,10 @@ pub struct ContractInfo {
 impl ContractInfo {
     // aka current contract address
     pub fn selfdestruct(&self) -> Option<ContractAddress> {
+        // The contract only received the selfdestruct beneficiary capacity
+        if self.programs.is_empty() {
+            return None;
+        }
         assert_eq!(
             self.output.is_none(),
             self.programs[self.programs.len() - 1]
@@ -781,6 +1176,7 @@ impl ContractInfo {
     pub fn get_change(
         &self,
         address: &ContractAddress,
+        tx_origin: &EoaAddress,
         number: u64,
         tx_index: u32,
         tx_hash: &H256,
@@ -793,7 +1189,7 @@ impl ContractInfo {
                 .values()
                 .map(|leaf| (smth256_to_h256(&leaf.key), smth256_to_h256(&leaf.value)))
                 .collect();
-            let tx_origin = self.programs[0].program.tx_origin.clone();
+            let tx_origin = tx_origin.clone();
             let capacity: u64 = output.capacity().unpack();
             let balance: u64 = contract_account_balance(&output);
             Some(ContractChange {
@@ -886,10 +1282,33 @@ impl ContractExtractor {
                 current_contract,
                 script_groups,
                 eoa_accounts,
+                transfers: Vec::new(),
             }
         }))
     }
 
+    /// The other EoA cells of the tx_origin owner (same lock args) without
+    /// output are merged into the tx_origin output (the same as the
+    /// validator), their capacity is added to tx_origin.
+    pub fn merge_tx_origin_cells(
+        &mut self,
+        eoa_inputs: &HashMap<H160, (H160, u64)>,
+        eoa_outputs: &HashSet<H160>,
+    ) {
+        let tx_origin = self.tx_origin.0.clone();
+        let owner = match eoa_inputs.get(&tx_origin) {
+            Some((lock_args, _)) => lock_args.clone(),
+            None => return,
+        };
+        for (address, (lock_args, capacity)) in eoa_inputs {
+            if address != &tx_origin && lock_args == &owner && !eoa_outputs.contains(address) {
+                log::debug!("merge eoa {:x} into tx_origin", address);
+                self.eoa_accounts.remove(address);
+                self.eoa_add_balance(&tx_origin, *capacity);
+            }
+        }
+    }
+
     pub fn run(&mut self) -> Result<(), Box<dyn StdError>> {
         let entrance_contract = self.entrance_contract.clone();
         self.run_with(&entrance_contract, false).map(|_| ())
@@ -967,6 +1386,10 @@ impl ContractExtractor {
             sender,
             destination
         );
+        if value > 0 {
+            self.transfers
+                .push((sender.clone(), destination.clone(), value));
+        }
         if &self.tx_origin.0 == sender {
             self.eoa_sub_balance(sender, value);
         } else {
@@ -982,7 +1405,9 @@ impl ContractExtractor {
     pub fn get_contract_changes(&self, number: u64) -> Vec<ContractChange> {
         self.script_groups
             .iter()
-            .filter_map(|(addr, info)| info.get_change(addr, number, self.tx_index, &self.tx_hash))
+            .filter_map(|(addr, info)| {
+                info.get_change(addr, &self.tx_origin, number, self.tx_index, &self.tx_hash)
+            })
             .collect()
     }
     pub fn get_created_contracts(&self) -> Vec<ContractMeta> {
@@ -991,6 +1416,37 @@ impl ContractExtractor {
             .filter_map(|(addr, info)| info.get_meta(addr, &self.tx_hash))
             .collect()
     }
+    /// Transfers from/to EoA accounts: (eoa_address, transfer_index, transfer)
+    pub fn get_eoa_transfers(&self) -> Vec<(H160, u32, value::EoaTransfer)> {
+        let mut eoa_transfers = Vec::new();
+        for (transfer_index, (sender, destination, value)) in self.transfers.iter().enumerate() {
+            if self.eoa_accounts.contains_key(sender) {
+                eoa_transfers.push((
+                    sender.clone(),
+                    transfer_index as u32,
+                    value::EoaTransfer {
+                        tx_hash: self.tx_hash.clone(),
+                        counterparty: destination.clone(),
+                        value: *value,
+                        incoming: false,
+                    },
+                ));
+            }
+            if self.eoa_accounts.contains_key(destination) {
+                eoa_transfers.push((
+                    destination.clone(),
+                    transfer_index as u32,
+                    value::EoaTransfer {
+                        tx_hash: self.tx_hash.clone(),
+                        counterparty: sender.clone(),
+                        value: *value,
+                        incoming: true,
+                    },
+                ));
+            }
+        }
+        eoa_transfers
+    }
     pub fn get_destructed_contracts(&self) -> Vec<ContractAddress> {
         self.script_groups
             .values()
@@ -1033,6 +1489,44 @@ impl ContractExtractor {
         }
         info.input_balance -= value;
     }
+    // Move the capacity of current contract to the selfdestruct beneficiary, the
+    // capacity of the contract selfdestructed to itself is refunded to tx_origin.
+    // The validator rejects the beneficiary which is not an account.
+    fn selfdestruct_to(&mut self, address: &H160) -> Result<(), String> {
+        let input_capacity = self
+            .script_groups
+            .get(&self.current_contract)
+            .expect("selfdestruct account info")
+            .input_capacity;
+        let beneficiary = if address == &self.current_contract.0 {
+            log::debug!("selfdestruct to itself: {:x}", address);
+            self.tx_origin.0.clone()
+        } else {
+            address.clone()
+        };
+        let contract_beneficiary = ContractAddress(beneficiary.clone());
+        if self.script_groups.contains_key(&contract_beneficiary) {
+            self.contract_add_balance(&contract_beneficiary, input_capacity);
+        } else if self.eoa_accounts.contains_key(&beneficiary) {
+            self.eoa_add_balance(&beneficiary, input_capacity);
+            if input_capacity > 0 {
+                self.transfers
+                    .push((self.current_contract.0.clone(), beneficiary, input_capacity));
+            }
+        } else {
+            return Err(format!(
+                "selfdestruct beneficiary not found: {:x}",
+                beneficiary
+            ));
+        }
+        let info = self
+            .script_groups
+            .get_mut(&self.current_contract)
+            .expect("selfdestruct account info");
+        info.input_balance = 0;
+        info.input_capacity = 0;
+        Ok(())
+    }
     pub fn contract_add_balance(&mut self, address: &ContractAddress, value: u64) {
         log::debug!("adding {} value to contract {:x}", value, address.0);
         let info = self
@@ -1129,23 +1623,14 @@ impl<Mac: SupportMachine> RunContext<Mac> for ContractExtractor {
                 let data_length = machine.registers()[A1].to_u32();
                 let data = vm_load_data(machine, data_address, data_length)?;
                 let address = H160::from_slice(&data).expect("parse selfdestruct H160");
-                let input_capacity = self
-                    .script_groups
-                    .get(&self.current_contract)
-                    .expect("selfdestruct account info")
-                    .input_capacity;
-                if self.eoa_accounts.contains_key(&address) {
-                    self.eoa_add_balance(&address, input_capacity);
-                } else {
-                    self.contract_add_balance(&ContractAddress(address), input_capacity);
-                }
-                let info = self
-                    .script_groups
+                self.selfdestruct_to(&address).map_err(|err| {
+                    log::warn!("{}", err);
+                    VMError::Unexpected
+                })?;
+                self.script_groups
                     .get_mut(&self.current_contract)
-                    .expect("selfdestruct account info");
-                info.selfdestruct = Some(data.into());
-                info.input_balance = 0;
-                info.input_capacity = 0;
+                    .expect("selfdestruct account info")
+                    .selfdestruct = Some(data.into());
                 Ok(true)
             }
             // CALL
@@ -1291,8 +1776,7 @@ impl<Mac: SupportMachine> RunContext<Mac> for ContractExtractor {
                 let number = self.tip_block.number();
                 let timestamp = self.tip_block.timestamp() / 1000;
                 let difficulty = self.tip_block.difficulty();
-                // TODO: config chain ID
-                let chain_id = U256::one();
+                let chain_id = U256::from(self.run_config.chain_id);
                 // FIXME: only recognize secp_blake160 for now
                 let cellbase_lock = self
                     .tip_block
@@ -1355,3 +1839,625 @@ impl<Mac: SupportMachine> RunContext<Mac> for ContractExtractor {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::eth_tx::eoa_cell_data;
+    use crate::mock_chain::MockChain;
+    use crate::types::DEFAULT_CHAIN_ID;
+    use ckb_types::{core::TransactionBuilder, h160};
+
+    /// Count the new EoA cells of each block
+    struct EoaCellCounter;
+
+    impl EoaCellCounter {
+        fn key(number: u64) -> Key {
+            Key::Processor {
+                name: String::from("eoa-cell-counter"),
+                key: Bytes::from(number.to_be_bytes().to_vec()),
+            }
+        }
+    }
+
+    impl BlockProcessor for EoaCellCounter {
+        fn name(&self) -> &str {
+            "eoa-cell-counter"
+        }
+
+        fn process(
+            &mut self,
+            _db: &DB,
+            batch: &mut WriteBatch,
+            block: &ProcessedBlock,
+        ) -> Result<Bytes, String> {
+            if block.eoa_added_cells.is_empty() {
+                return Ok(Bytes::new());
+            }
+            let count = block.eoa_added_cells.len() as u32;
+            batch.put(&Bytes::from(&Self::key(block.number)), &count.to_be_bytes());
+            Ok(Bytes::from(vec![1]))
+        }
+
+        fn rollback(
+            &self,
+            _db: &DB,
+            batch: &mut WriteBatch,
+            number: u64,
+            undo: &[u8],
+        ) -> Result<(), String> {
+            assert_eq!(undo, &[1]);
+            batch.delete(&Bytes::from(&Self::key(number)));
+            Ok(())
+        }
+    }
+
+    fn empty_block_delta() -> value::BlockDelta {
+        value::BlockDelta {
+            contracts: Vec::new(),
+            added_cells: Vec::new(),
+            removed_cells: Vec::new(),
+            eoa_added_cells: Vec::new(),
+            eoa_removed_cells: Vec::new(),
+            destructed_contracts: Vec::new(),
+            eoa_transfers: Vec::new(),
+            processors: Vec::new(),
+            eth_nonces: Vec::new(),
+        }
+    }
+
+    /// The contracts have 100 shannons balance and capacity in the inputs
+    fn new_extractor(contracts: &[&ContractAddress], eoa_addresses: &[&H160]) -> ContractExtractor {
+        let script_groups = contracts
+            .iter()
+            .map(|address| {
+                let info = ContractInfo {
+                    input_balance: 100,
+                    input_capacity: 100,
+                    ..Default::default()
+                };
+                ((*address).clone(), info)
+            })
+            .collect();
+        let eoa_accounts = eoa_addresses
+            .iter()
+            .map(|address| ((*address).clone(), (0, 0)))
+            .collect();
+        ContractExtractor {
+            run_config: RunConfig::default(),
+            tip_block: core::BlockBuilder::default().build(),
+            header_deps: HashMap::default(),
+            tx_hash: H256::default(),
+            tx_index: 0,
+            entrance_contract: contracts[0].clone(),
+            current_contract: contracts[0].clone(),
+            tx_origin: EoaAddress(eoa_addresses[0].clone()),
+            script_groups,
+            eoa_accounts,
+            transfers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_merge_tx_origin_cells() {
+        let contract = ContractAddress(h160!("0xc1"));
+        let tx_origin = h160!("0xe1");
+        let merged = h160!("0xe2");
+        let other = h160!("0xe3");
+        let mut extractor = new_extractor(&[&contract], &[&tx_origin, &merged, &other]);
+        let owner = h160!("0xa1");
+        let eoa_inputs = vec![
+            (tx_origin.clone(), (owner.clone(), 200)),
+            (merged.clone(), (owner, 300)),
+            (other.clone(), (h160!("0xa2"), 400)),
+        ]
+        .into_iter()
+        .collect();
+        let eoa_outputs = vec![tx_origin.clone(), other.clone()].into_iter().collect();
+
+        extractor.merge_tx_origin_cells(&eoa_inputs, &eoa_outputs);
+        assert_eq!(extractor.eoa_accounts[&tx_origin].0, 300);
+        assert!(!extractor.eoa_accounts.contains_key(&merged));
+        assert_eq!(extractor.eoa_accounts[&other].0, 0);
+    }
+
+    #[test]
+    fn test_selfdestruct_to() {
+        let contract = ContractAddress(h160!("0xc1"));
+        let other_contract = ContractAddress(h160!("0xc2"));
+        let eoa_address = h160!("0xe1");
+        let mut extractor = new_extractor(&[&contract, &other_contract], &[&eoa_address]);
+
+        extractor.selfdestruct_to(&eoa_address).unwrap();
+        assert_eq!(extractor.eoa_accounts[&eoa_address].0, 100);
+        assert_eq!(
+            extractor.transfers,
+            vec![(contract.0.clone(), eoa_address.clone(), 100)]
+        );
+        assert_eq!(extractor.script_groups[&contract].input_capacity, 0);
+
+        // The beneficiary is not an account of the transaction
+        extractor.current_contract = other_contract.clone();
+        assert!(extractor.selfdestruct_to(&h160!("0xdead")).is_err());
+        assert_eq!(extractor.script_groups[&other_contract].input_capacity, 100);
+
+        // Selfdestruct to itself, the capacity is refunded to tx_origin
+        extractor.selfdestruct_to(&other_contract.0).unwrap();
+        assert_eq!(extractor.script_groups[&other_contract].input_capacity, 0);
+        assert_eq!(extractor.script_groups[&other_contract].input_balance, 0);
+        assert_eq!(extractor.eoa_accounts[&eoa_address].0, 200);
+        assert_eq!(
+            extractor.transfers[1],
+            (other_contract.0.clone(), eoa_address, 100)
+        );
+    }
+
+    #[test]
+    fn test_rollback_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DB::open_default(dir.path()).unwrap();
+        let address = ContractAddress(h160!("0xab"));
+        let put = |key: Key, value_bytes: Vec<u8>| {
+            db.put(&Bytes::from(&key), &value_bytes).unwrap();
+        };
+        let empty_delta = empty_block_delta();
+        let mut block_delta = empty_delta.clone();
+        block_delta.contracts.push((address.clone(), true));
+        let cell = value::LockLiveCell {
+            tx_hash: H256::default(),
+            output_index: 0,
+            capacity: 0,
+            type_script_hash: None,
+            data_size: 0,
+        };
+        block_delta
+            .added_cells
+            .push((H256::default(), 0, 0, cell.clone()));
+        let change = ContractChange {
+            address: address.clone(),
+            number: 1,
+            ..Default::default()
+        };
+        let meta = ContractMeta {
+            address: address.clone(),
+            code: Bytes::from(vec![0x60]),
+            tx_hash: H256::default(),
+            output_index: 0,
+            balance: 0,
+            destructed: false,
+        };
+        put(
+            Key::BlockMap(0),
+            serialize(&value::BlockMap(H256::default())).unwrap(),
+        );
+        put(
+            Key::BlockMap(1),
+            serialize(&value::BlockMap(H256::default())).unwrap(),
+        );
+        put(Key::BlockDelta(0), serialize(&empty_delta).unwrap());
+        put(Key::BlockDelta(1), serialize(&block_delta).unwrap());
+        put(change.db_key(), serialize(&change.db_value()).unwrap());
+        put(meta.db_key(), serialize(&meta.db_value()).unwrap());
+        let cell_data = value::CellData::new(&packed::CellOutput::default(), Bytes::new());
+        put(
+            Key::CellData(cell.out_point()),
+            serialize(&cell_data).unwrap(),
+        );
+        let last = value::Last {
+            number: 1,
+            hash: H256::default(),
+        };
+        put(Key::Last, serialize(&last).unwrap());
+
+        rollback_block(&db, 1, &[]).unwrap();
+        let last: value::Last = db_get(&db, &Bytes::from(&Key::Last)).unwrap().unwrap();
+        assert_eq!(last.number, 0);
+        for key in vec![
+            change.db_key(),
+            meta.db_key(),
+            Key::BlockDelta(1),
+            Key::BlockMap(1),
+            Key::CellData(cell.out_point()),
+        ] {
+            assert!(db.get(&Bytes::from(&key)).unwrap().is_none());
+        }
+        // Can not rollback a block without BlockDelta
+        assert!(rollback_block(&db, 1, &[]).is_err());
+
+        rollback_to(&db, None, &[]).unwrap();
+        assert!(db.get(&Bytes::from(&Key::Last)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_eoa_transfers() {
+        let contract = ContractAddress(h160!("0xc1"));
+        let sender = h160!("0xe1");
+        let receiver = h160!("0xe2");
+        let mut extractor = new_extractor(&[&contract], &[&sender, &receiver]);
+        extractor.transfers = vec![
+            (sender.clone(), contract.0.clone(), 10),
+            (contract.0.clone(), receiver.clone(), 5),
+            (sender.clone(), receiver.clone(), 3),
+        ];
+        let transfers = extractor
+            .get_eoa_transfers()
+            .into_iter()
+            .map(|(address, transfer_index, transfer)| {
+                (
+                    address,
+                    transfer_index,
+                    transfer.counterparty,
+                    transfer.value,
+                    transfer.incoming,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            transfers,
+            vec![
+                (sender.clone(), 0, contract.0.clone(), 10, false),
+                (receiver.clone(), 1, contract.0.clone(), 5, true),
+                (sender.clone(), 2, receiver.clone(), 3, false),
+                (receiver.clone(), 2, sender.clone(), 3, true),
+            ]
+        );
+
+        // Only the transfers of the rolled back block are removed
+        let dir = tempfile::tempdir().unwrap();
+        let db = DB::open_default(dir.path()).unwrap();
+        let transfer_key = |number: u64| Key::EoaTransfer {
+            address: sender.clone(),
+            number: Some(number),
+            tx_index: Some(1),
+            transfer_index: Some(0),
+        };
+        let transfer = value::EoaTransfer {
+            tx_hash: H256::default(),
+            counterparty: receiver.clone(),
+            value: 3,
+            incoming: false,
+        };
+        for number in 1..=2 {
+            db.put(
+                &Bytes::from(&transfer_key(number)),
+                &serialize(&transfer).unwrap(),
+            )
+            .unwrap();
+            db.put(
+                &Bytes::from(&Key::BlockMap(number)),
+                &serialize(&value::BlockMap(H256::default())).unwrap(),
+            )
+            .unwrap();
+        }
+        let mut block_delta = empty_block_delta();
+        block_delta.eoa_transfers.push(sender.clone());
+        db.put(
+            &Bytes::from(&Key::BlockDelta(2)),
+            &serialize(&block_delta).unwrap(),
+        )
+        .unwrap();
+        rollback_block(&db, 2, &[]).unwrap();
+        assert!(db.get(&Bytes::from(&transfer_key(1))).unwrap().is_some());
+        assert!(db.get(&Bytes::from(&transfer_key(2))).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_prune_block_deltas() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let removed_cell = |number: u64| value::LockLiveCell {
+            tx_hash: H256::default(),
+            output_index: number as u32,
+            capacity: 0,
+            type_script_hash: None,
+            data_size: 0,
+        };
+        let cell_data = value::CellData::new(&packed::CellOutput::default(), Bytes::new());
+        for number in 1..=5 {
+            let cell = removed_cell(number);
+            let mut block_delta = empty_block_delta();
+            block_delta
+                .contracts
+                .push((ContractAddress(h160!("0xc1")), number == 1));
+            block_delta
+                .removed_cells
+                .push((H256::default(), number - 1, 1, 0, cell.clone()));
+            db.put(
+                &Bytes::from(&Key::BlockDelta(number)),
+                &serialize(&block_delta).unwrap(),
+            )
+            .unwrap();
+            db.put(
+                &Bytes::from(&Key::CellData(cell.out_point())),
+                &serialize(&cell_data).unwrap(),
+            )
+            .unwrap();
+        }
+        let chain = MockChain::new(Vec::new());
+        let indexer = Indexer::new(Arc::clone(&db), Box::new(chain), RunConfig::default(), 2);
+        // Still in the rollback window
+        indexer.prune_block_deltas(2).unwrap();
+        assert!(db.get(&Bytes::from(&Key::BlockDelta(1))).unwrap().is_some());
+
+        indexer.prune_block_deltas(5).unwrap();
+        for number in 1..=5 {
+            let pruned = number < 3;
+            let delta_key = Bytes::from(&Key::BlockDelta(number));
+            let summary_key = Bytes::from(&Key::BlockSummary(number));
+            let cell_data_key = Bytes::from(&Key::CellData(removed_cell(number).out_point()));
+            assert_eq!(db.get(&delta_key).unwrap().is_none(), pruned);
+            assert_eq!(db.get(&summary_key).unwrap().is_some(), pruned);
+            assert_eq!(db.get(&cell_data_key).unwrap().is_none(), pruned);
+            // The contracts of the block are still available
+            assert_eq!(
+                indexer.loader.load_block_contracts(number).unwrap(),
+                Some(vec![(ContractAddress(h160!("0xc1")), number == 1)])
+            );
+        }
+        // Can not rollback a pruned block
+        assert!(rollback_block(&db, 2, &[]).is_err());
+    }
+
+    #[test]
+    fn test_prune_block_deltas_while_syncing() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let chain = MockChain::new(Vec::new());
+        for _ in 0..5 {
+            chain.append_block(Vec::new());
+        }
+        let mut indexer = Indexer::new(Arc::clone(&db), Box::new(chain), RunConfig::default(), 2);
+        indexer.stop_at_tip = true;
+        indexer.index().unwrap();
+        // Pruned before reaching the tip (5)
+        for number in 0..=5 {
+            let pruned = number < 3;
+            let delta_key = Bytes::from(&Key::BlockDelta(number));
+            assert_eq!(db.get(&delta_key).unwrap().is_none(), pruned);
+        }
+    }
+
+    #[test]
+    fn test_start_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let chain = MockChain::new(Vec::new());
+        for _ in 0..4 {
+            chain.append_block(Vec::new());
+        }
+        let new_indexer = |start_block: Option<value::StartBlock>| {
+            let mut indexer = Indexer::new(
+                Arc::clone(&db),
+                Box::new(chain.clone()),
+                RunConfig::default(),
+                10,
+            );
+            indexer.stop_at_tip = true;
+            indexer.start_block = start_block;
+            indexer
+        };
+        let start_block = |number: u64, hash: H256| Some(value::StartBlock { number, hash });
+        let block_hash = |number: u64| -> H256 {
+            chain
+                .clone()
+                .get_header_by_number(number)
+                .unwrap()
+                .unwrap()
+                .hash
+        };
+
+        // The hash of the start block must match the chain
+        assert!(new_indexer(start_block(2, block_hash(3))).index().is_err());
+        assert!(db.get(&Bytes::from(&Key::StartBlock)).unwrap().is_none());
+
+        new_indexer(start_block(2, block_hash(2))).index().unwrap();
+        let last: value::Last = db_get(&db, &Bytes::from(&Key::Last)).unwrap().unwrap();
+        assert_eq!(last.number, 4);
+        assert!(db.get(&Bytes::from(&Key::BlockMap(1))).unwrap().is_none());
+        assert!(db.get(&Bytes::from(&Key::BlockMap(2))).unwrap().is_some());
+
+        // The stored start block is used when restart
+        chain.append_block(Vec::new());
+        new_indexer(None).index().unwrap();
+        let last: value::Last = db_get(&db, &Bytes::from(&Key::Last)).unwrap().unwrap();
+        assert_eq!(last.number, 5);
+        // A different start block is refused
+        assert!(new_indexer(start_block(1, block_hash(1))).index().is_err());
+
+        // Rollback stops at the start block
+        rollback_to(&db, None, &[]).unwrap();
+        assert!(db.get(&Bytes::from(&Key::Last)).unwrap().is_none());
+        assert!(db.get(&Bytes::from(&Key::BlockMap(2))).unwrap().is_none());
+        assert!(db.get(&Bytes::from(&Key::StartBlock)).unwrap().is_some());
+    }
+
+    fn eoa_lock_script() -> packed::Script {
+        packed::Script::new_builder()
+            .code_hash(h256!("0xe0a").pack())
+            .hash_type(core::ScriptHashType::Data.into())
+            .build()
+    }
+
+    fn eoa_run_config(address_mode: AddressMode) -> RunConfig {
+        RunConfig {
+            generator: Bytes::new(),
+            type_dep: packed::CellDep::default(),
+            type_script: packed::Script::default(),
+            lock_dep: packed::CellDep::default(),
+            lock_script: packed::Script::default(),
+            eoa_lock_dep: packed::CellDep::default(),
+            eoa_lock_script: eoa_lock_script(),
+            address_mode,
+            create_eoa_recipient: false,
+            chain_id: DEFAULT_CHAIN_ID,
+        }
+    }
+
+    /// An EoA cell with the type id args and anyone-can-pay lock args
+    fn eoa_output(type_args: &Bytes, lock_args: &Bytes, capacity: u64) -> packed::CellOutput {
+        let type_script = packed::Script::new_builder()
+            .code_hash(TYPE_ID_CODE_HASH.pack())
+            .hash_type(core::ScriptHashType::Type.into())
+            .args(type_args.pack())
+            .build();
+        packed::CellOutput::new_builder()
+            .capacity(core::Capacity::bytes(capacity as usize).unwrap().pack())
+            .lock(
+                eoa_lock_script()
+                    .as_builder()
+                    .args(lock_args.pack())
+                    .build(),
+            )
+            .type_(
+                packed::ScriptOpt::new_builder()
+                    .set(Some(type_script))
+                    .build(),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_index_mock_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let run_config = eoa_run_config(AddressMode::Ckb);
+        let type_args = Bytes::from(vec![0x11; 32]);
+        let lock_args = Bytes::from(vec![0x22; 20]);
+        let eoa_output = |capacity: u64| eoa_output(&type_args, &lock_args, capacity);
+        let eoa_address = AddressMode::Ckb.eoa_address(&type_args, &lock_args);
+
+        let genesis_tx = TransactionBuilder::default()
+            .output(eoa_output(1000))
+            .output_data(Bytes::new().pack())
+            .build();
+        let transfer_tx = TransactionBuilder::default()
+            .input(packed::CellInput::new(
+                packed::OutPoint::new(genesis_tx.hash(), 0),
+                0,
+            ))
+            .output(eoa_output(900))
+            .output_data(Bytes::new().pack())
+            .build();
+        let chain = MockChain::new(vec![genesis_tx.clone()]);
+        chain.append_block(vec![transfer_tx.clone()]);
+
+        let mut indexer = Indexer::new(Arc::clone(&db), Box::new(chain.clone()), run_config, 10);
+        indexer.stop_at_tip = true;
+        indexer.processors.push(Box::new(EoaCellCounter));
+        indexer.index().unwrap();
+        let count_key_bytes = Bytes::from(&EoaCellCounter::key(1));
+        assert_eq!(
+            db.get(&count_key_bytes).unwrap().unwrap().as_ref(),
+            &1u32.to_be_bytes()
+        );
+        let (cell, output, _) = indexer
+            .loader
+            .load_eoa_live_cell(eoa_address.clone())
+            .unwrap();
+        assert_eq!(cell.tx_hash, transfer_tx.hash().unpack());
+        assert_eq!(output.as_slice(), eoa_output(900).as_slice());
+
+        // The transfer is rolled back by the reorg
+        chain.reorg(0, vec![Vec::new(), Vec::new()]);
+        indexer.index().unwrap();
+        let last: value::Last = db_get(&db, &Bytes::from(&Key::Last)).unwrap().unwrap();
+        assert_eq!(last.number, 2);
+        assert_eq!(last.hash, chain.tip().hash().unpack());
+        assert!(db.get(&count_key_bytes).unwrap().is_none());
+        let (cell, output, _) = indexer.loader.load_eoa_live_cell(eoa_address).unwrap();
+        assert_eq!(cell.tx_hash, genesis_tx.hash().unpack());
+        assert_eq!(output.as_slice(), eoa_output(1000).as_slice());
+
+        let status = indexer.loader.load_sync_status().unwrap();
+        assert_eq!(status.indexed_number, Some(2));
+        assert_eq!(status.tip_number, 2);
+        assert_eq!(status.lag, 0);
+        assert_eq!(status.last_rollback.map(|event| event.number), Some(1));
+        assert!(status.indexer_alive);
+    }
+
+    #[test]
+    fn test_index_ethereum_address_cells() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let run_config = eoa_run_config(AddressMode::Ethereum);
+        // Two cells of one address (the lock args) with different type id
+        let type_args_a = Bytes::from(vec![0x11; 32]);
+        let type_args_b = Bytes::from(vec![0x12; 32]);
+        let lock_args = Bytes::from(vec![0x22; 20]);
+        let address = H160::from_slice(&lock_args).unwrap();
+
+        let genesis_tx = TransactionBuilder::default()
+            .output(eoa_output(&type_args_a, &lock_args, 1000))
+            .output(eoa_output(&type_args_b, &lock_args, 2000))
+            .outputs_data(vec![Bytes::new().pack(), Bytes::new().pack()])
+            .build();
+        let transfer_tx = TransactionBuilder::default()
+            .input(packed::CellInput::new(
+                packed::OutPoint::new(genesis_tx.hash(), 0),
+                0,
+            ))
+            .output(eoa_output(&type_args_a, &lock_args, 900))
+            // The cell data of the Ethereum transaction with nonce 9
+            .output_data(eoa_cell_data(10).pack())
+            .build();
+        let chain = MockChain::new(vec![genesis_tx.clone()]);
+        chain.append_block(vec![transfer_tx.clone()]);
+
+        let mut indexer = Indexer::new(Arc::clone(&db), Box::new(chain.clone()), run_config, 10);
+        indexer.stop_at_tip = true;
+        indexer.index().unwrap();
+        let out_points = |indexer: &Indexer| {
+            indexer
+                .loader
+                .load_eoa_address_cells(address.clone())
+                .unwrap()
+                .into_iter()
+                .map(|cell| cell.out_point())
+                .collect::<HashSet<_>>()
+        };
+        let expected = vec![
+            packed::OutPoint::new(genesis_tx.hash(), 1),
+            packed::OutPoint::new(transfer_tx.hash(), 0),
+        ];
+        assert_eq!(out_points(&indexer), expected.into_iter().collect());
+        assert_eq!(
+            indexer
+                .loader
+                .load_eoa_owner_cells(address.clone())
+                .unwrap()
+                .len(),
+            2
+        );
+        // The cell with largest nonce (not the largest balance)
+        let (cell, output, data) = indexer.loader.load_eoa_live_cell(address.clone()).unwrap();
+        assert_eq!(cell.tx_hash, transfer_tx.hash().unpack());
+        assert_eq!(
+            output.as_slice(),
+            eoa_output(&type_args_a, &lock_args, 900).as_slice()
+        );
+        assert_eq!(data, eoa_cell_data(10));
+        assert_eq!(indexer.loader.load_eth_nonce(&address).unwrap(), 10);
+
+        // Only the consumed cell is restored by the rollback
+        chain.reorg(0, vec![Vec::new(), Vec::new()]);
+        indexer.index().unwrap();
+        let expected = vec![
+            packed::OutPoint::new(genesis_tx.hash(), 0),
+            packed::OutPoint::new(genesis_tx.hash(), 1),
+        ];
+        assert_eq!(out_points(&indexer), expected.into_iter().collect());
+        assert_eq!(
+            indexer
+                .loader
+                .load_eoa_owner_cells(address.clone())
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(indexer.loader.load_eth_nonce(&address).unwrap(), 0);
+        // The cell with largest balance when the nonces are the same
+        let (cell, _, _) = indexer.loader.load_eoa_live_cell(address.clone()).unwrap();
+        assert_eq!(cell.output_index, 1);
+    }
+}
@@ -1,4 +1,4 @@
-use bincode::deserialize;
+use bincode::{deserialize, serialize};
 use ckb_jsonrpc_types as json_types;
 use ckb_types::{
     bytes::Bytes,
@@ -10,67 +10,470 @@ use ckb_types::{
 use rocksdb::DB;
 use std::collections::HashSet;
 use std::convert::TryFrom;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use super::pending::{PendingPool, PendingTx};
 use super::{db_get, value, Key};
-use crate::client::HttpRpcClient;
+use crate::abi::{to_decimal, Abi};
+use crate::client::ChainClient;
+use crate::eth_tx::eoa_cell_nonce;
 use crate::types::{
-    ContractAddress, ContractChange, ContractMeta, EoaAddress, LogInfo, CELLBASE_MATURITY,
+    ContractAddress, ContractChange, ContractMeta, EoaAddress, LogInfo, RollbackEvent, SyncStatus,
+    TokenBalanceInfo, TokenTransferInfo, TransferCursor, TransferInfo, CELLBASE_MATURITY,
     SIGHASH_TYPE_HASH,
 };
 
+/// The indexer is considered dead if no heartbeat in this duration
+const INDEXER_LIVENESS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The indexer events reported to the rpc server
+#[derive(Default)]
+struct SyncState {
+    last_heartbeat: Option<Instant>,
+    last_rollback: Option<RollbackEvent>,
+    /// The CKB tip block number last seen by the indexer
+    ckb_tip: Option<u64>,
+}
+
 #[derive(Clone)]
 pub struct Loader {
     pub db: Arc<DB>,
-    client: HttpRpcClient,
+    client: Box<dyn ChainClient>,
+    /// Shared by all the clones
+    pending: Arc<Mutex<PendingPool>>,
+    /// Shared by all the clones
+    sync_state: Arc<Mutex<SyncState>>,
+    /// Load the latest contract/EoA state from pending transactions (for building
+    /// new transactions)
+    pub overlay_pending: bool,
 }
 
 impl Loader {
-    pub fn new(db: Arc<DB>, ckb_uri: &str) -> Result<Loader, String> {
-        Ok(Loader {
+    pub fn new(db: Arc<DB>, client: Box<dyn ChainClient>) -> Loader {
+        Loader {
             db,
-            client: HttpRpcClient::new(ckb_uri.to_string()),
+            client,
+            pending: Arc::new(Mutex::new(PendingPool::default())),
+            sync_state: Arc::new(Mutex::new(SyncState::default())),
+            overlay_pending: false,
+        }
+    }
+
+    fn pending_pool(&self) -> MutexGuard<PendingPool> {
+        self.pending.lock().expect("lock pending pool")
+    }
+
+    fn sync_state(&self) -> MutexGuard<SyncState> {
+        self.sync_state.lock().expect("lock sync state")
+    }
+
+    /// Report the indexer is alive (called in every indexer loop)
+    pub fn indexer_heartbeat(&self) {
+        self.sync_state().last_heartbeat = Some(Instant::now());
+    }
+
+    /// Remember the block rolled back by the indexer
+    pub fn record_rollback(&self, number: u64, hash: H256) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0);
+        self.sync_state().last_rollback = Some(RollbackEvent {
+            number,
+            hash,
+            timestamp,
+        });
+    }
+
+    /// Remember the CKB tip block number fetched by the indexer
+    pub fn record_ckb_tip(&self, number: u64) {
+        self.sync_state().ckb_tip = Some(number);
+    }
+
+    /// Load the cached CKB tip block number, request it from CKB if the
+    /// indexer has not fetched it yet
+    pub fn load_ckb_tip(&mut self) -> Result<u64, String> {
+        if let Some(number) = self.sync_state().ckb_tip {
+            return Ok(number);
+        }
+        let number = self.client.get_tip_block_number()?;
+        self.record_ckb_tip(number);
+        Ok(number)
+    }
+
+    /// Load the sync status with the cached CKB tip (no RPC request)
+    pub fn load_sync_status(&self) -> Result<SyncStatus, String> {
+        let last: Option<value::Last> = db_get(&self.db, &Bytes::from(&Key::Last))?;
+        let (indexed_number, indexed_hash) = match last {
+            Some(value::Last { number, hash }) => (Some(number), Some(hash)),
+            None => (None, None),
+        };
+        let state = self.sync_state();
+        // The cached tip may fall behind the indexed blocks
+        let tip_number = state.ckb_tip.unwrap_or(0).max(indexed_number.unwrap_or(0));
+        let lag = tip_number.saturating_sub(indexed_number.unwrap_or(0));
+        let indexer_alive = state
+            .last_heartbeat
+            .map(|time| time.elapsed() < INDEXER_LIVENESS_TIMEOUT)
+            .unwrap_or(false);
+        Ok(SyncStatus {
+            indexed_number,
+            indexed_hash,
+            tip_number,
+            lag,
+            last_rollback: state.last_rollback.clone(),
+            indexer_alive,
         })
     }
 
+    /// Remember the transaction built by polyjuice
+    pub fn add_built_tx(&self, tx: PendingTx) {
+        self.pending_pool().add_built(tx);
+    }
+
+    /// Send the transaction to CKB, the later built transactions will use
+    /// it's outputs if it's built by polyjuice.
+    pub fn send_transaction(&mut self, tx: json_types::Transaction) -> Result<H256, String> {
+        let tx_hash = self.client.send_transaction(tx)?;
+        if !self.pending_pool().submit(&tx_hash) {
+            log::warn!("Transaction not built by polyjuice: {:#x}", tx_hash);
+        }
+        Ok(tx_hash)
+    }
+
+    /// Detect the built transactions sent to CKB by other tools (e.g.
+    /// ckb-cli) instead of `send_transaction`, so the following transactions
+    /// can build on them too.
+    pub fn detect_submitted_txs(&mut self) {
+        let tx_hashes = self.pending_pool().built_tx_hashes();
+        if tx_hashes.is_empty() {
+            return;
+        }
+        let txs = match self.client.get_transactions(tx_hashes.clone()) {
+            Ok(txs) => txs,
+            Err(err) => {
+                log::warn!("Detect submitted transactions failed: {}", err);
+                return;
+            }
+        };
+        let mut pending_pool = self.pending_pool();
+        for (tx_hash, tx_opt) in tx_hashes.into_iter().zip(txs.into_iter()) {
+            match tx_opt.map(|tx| tx.tx_status.status) {
+                Some(json_types::Status::Pending) | Some(json_types::Status::Proposed) => {
+                    log::info!("Transaction submitted by other tool: {:#x}", tx_hash);
+                    pending_pool.submit(&tx_hash);
+                }
+                // The state is loaded from the index once the block is indexed
+                Some(json_types::Status::Committed) => {
+                    pending_pool.remove_built(&tx_hash);
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Remove the pending transactions committed or conflicted in a block
+    pub fn remove_pending_txs(&self, committed: &HashSet<H256>, consumed: &HashSet<(H256, u32)>) {
+        self.pending_pool().remove_txs(committed, consumed);
+    }
+
+    /// Load the latest contract change, include the pending ones if
+    /// `overlay_pending` is set.
+    pub fn load_latest_contract_state(
+        &self,
+        address: ContractAddress,
+    ) -> Result<ContractChange, String> {
+        if self.overlay_pending {
+            if let Some(change) = self.pending_pool().contract_change(&address) {
+                return Ok(change.clone());
+            }
+        }
+        self.load_latest_contract_change(address, None, false, false)
+    }
+
+    /// Load the output and data of an indexed contract/EoA cell, return None
+    /// if the cell data is not indexed.
+    pub fn load_cell_data(
+        &self,
+        tx_hash: H256,
+        output_index: u32,
+    ) -> Result<Option<(packed::CellOutput, Bytes)>, String> {
+        if let Some(cell) = self.pending_pool().cell(&tx_hash, output_index) {
+            return Ok(Some(cell.clone()));
+        }
+        let out_point = packed::OutPoint::new(tx_hash.pack(), output_index);
+        let key_bytes = Bytes::from(&Key::CellData(out_point));
+        Ok(db_get::<_, value::CellData>(&self.db, &key_bytes)?
+            .map(|cell_data| (cell_data.output(), cell_data.data)))
+    }
+
     pub fn load_contract_live_cell(
         &mut self,
         tx_hash: H256,
         output_index: u32,
     ) -> Result<(packed::CellOutput, Bytes), String> {
-        let out_point = json_types::OutPoint {
-            tx_hash: tx_hash.clone(),
-            index: json_types::Uint32::from(output_index),
-        };
-        let cell_with_status = self.client.get_live_cell(out_point, true)?;
-        let cell = cell_with_status.cell.ok_or_else(|| {
-            format!(
-                "contract cell is not live cell, tx_hash={:x}, output_index={}",
-                tx_hash, output_index
-            )
-        })?;
-        Ok((cell.output.into(), cell.data.unwrap().content.into_bytes()))
+        self.load_live_cells(vec![(tx_hash, output_index)])
+            .map(|mut cells| cells.remove(0))
     }
 
     pub fn load_eoa_live_cell(
         &mut self,
         eoa_address: H160,
     ) -> Result<(value::EoaLiveCell, packed::CellOutput, Bytes), String> {
-        let key_bytes = Bytes::from(&Key::EoaLiveCell(eoa_address.clone()));
-        let value = db_get::<_, value::EoaLiveCell>(&self.db, &key_bytes)?
-            .ok_or_else(|| format!("eoa live cell not found: {:x}", eoa_address))?;
-        let cell_with_status = self.client.get_live_cell(value.out_point().into(), true)?;
-        let cell = cell_with_status.cell.ok_or_else(|| {
-            format!(
-                "eoa cell is not live cell, tx_hash={:x}, output_index={}",
-                value.tx_hash, value.output_index
-            )
-        })?;
-        Ok((
-            value,
-            cell.output.into(),
-            cell.data.unwrap().content.into_bytes(),
-        ))
+        let value = self.load_eoa_live_cell_value(eoa_address)?;
+        let (output, data) = self
+            .load_live_cells(vec![(value.tx_hash.clone(), value.output_index)])?
+            .remove(0);
+        Ok((value, output, data))
+    }
+
+    /// Load the EoA cell of the address, the cell with largest nonce then
+    /// largest balance is selected when the address owns many cells (Ethereum
+    /// address mode).
+    pub fn load_eoa_live_cell_value(
+        &self,
+        eoa_address: H160,
+    ) -> Result<value::EoaLiveCell, String> {
+        if self.overlay_pending {
+            if let Some(cell) = self.pending_pool().eoa_cell(&eoa_address) {
+                return Ok(cell.clone());
+            }
+        }
+        let mut cells = Vec::new();
+        for cell in self.load_eoa_address_cells(eoa_address.clone())? {
+            cells.push((self.eoa_cell_priority(&cell)?, cell));
+        }
+        cells
+            .into_iter()
+            .max_by_key(|(priority, _)| *priority)
+            .map(|(_, cell)| cell)
+            .ok_or_else(|| format!("eoa live cell not found: {:x}", eoa_address))
+    }
+
+    /// The nonce in the cell data (Ethereum address mode) and the balance of
+    /// the EoA cell, the validator only accepts the eth transaction signed with
+    /// the nonce of the transaction origin cell.
+    fn eoa_cell_priority(&self, cell: &value::EoaLiveCell) -> Result<(u64, u64), String> {
+        let nonce = self
+            .load_cell_data(cell.tx_hash.clone(), cell.output_index)?
+            .and_then(|(_, data)| eoa_cell_nonce(&data).ok())
+            .unwrap_or(0);
+        Ok((nonce, cell.balance()))
+    }
+
+    /// Load all the EoA cells of the address
+    pub fn load_eoa_address_cells(
+        &self,
+        eoa_address: H160,
+    ) -> Result<Vec<value::EoaLiveCell>, String> {
+        let key_prefix_bytes = Bytes::from(&Key::EoaLiveCell {
+            address: eoa_address,
+            out_point: None,
+        });
+        let mut cells = Vec::new();
+        let mut iter = self.db.raw_iterator();
+        iter.seek(&key_prefix_bytes);
+        while iter.valid() {
+            if let Some(value_bytes) = iter
+                .key()
+                .filter(|key| key.starts_with(&key_prefix_bytes))
+                .and_then(|_| iter.value())
+            {
+                let value: value::EoaLiveCell =
+                    deserialize(value_bytes).map_err(|err| err.to_string())?;
+                cells.push(value);
+            } else {
+                break;
+            }
+            iter.next();
+        }
+        Ok(cells)
+    }
+
+    /// Load multiple live cells (with data) from the index, the cells not
+    /// indexed are loaded from CKB in one batch request.
+    pub fn load_live_cells(
+        &mut self,
+        out_points: Vec<(H256, u32)>,
+    ) -> Result<Vec<(packed::CellOutput, Bytes)>, String> {
+        let mut cells = out_points
+            .iter()
+            .map(|(tx_hash, output_index)| self.load_cell_data(tx_hash.clone(), *output_index))
+            .collect::<Result<Vec<_>, String>>()?;
+        let missing_out_points = out_points
+            .iter()
+            .zip(cells.iter())
+            .filter(|(_, cell_opt)| cell_opt.is_none())
+            .map(|(out_point, _)| out_point.clone())
+            .collect::<Vec<_>>();
+        if !missing_out_points.is_empty() {
+            log::debug!("Load {} live cells from CKB", missing_out_points.len());
+            let json_out_points = missing_out_points
+                .iter()
+                .map(|(tx_hash, output_index)| json_types::OutPoint {
+                    tx_hash: tx_hash.clone(),
+                    index: json_types::Uint32::from(*output_index),
+                })
+                .collect();
+            let mut missing_cells = self
+                .client
+                .get_live_cells(json_out_points, true)?
+                .into_iter()
+                .zip(missing_out_points.into_iter());
+            for cell_opt in cells.iter_mut().filter(|cell_opt| cell_opt.is_none()) {
+                let (cell_with_status, (tx_hash, output_index)) =
+                    missing_cells.next().expect("missing cell");
+                let cell = cell_with_status.cell.ok_or_else(|| {
+                    format!(
+                        "cell is not live cell, tx_hash={:x}, output_index={}",
+                        tx_hash, output_index
+                    )
+                })?;
+                *cell_opt = Some((cell.output.into(), cell.data.unwrap().content.into_bytes()));
+            }
+        }
+        Ok(cells
+            .into_iter()
+            .map(|cell_opt| cell_opt.expect("loaded cell"))
+            .collect())
+    }
+
+    /// The nonce of next Ethereum transaction signed by the owner (lock args)
+    pub fn load_eth_nonce(&self, lock_args: &H160) -> Result<u64, String> {
+        if self.overlay_pending {
+            if let Some(nonce) = self.pending_pool().eth_nonce(lock_args) {
+                return Ok(nonce);
+            }
+        }
+        let key_bytes = Bytes::from(&Key::EthNonce(lock_args.clone()));
+        Ok(db_get::<_, value::EthNonce>(&self.db, &key_bytes)?
+            .map(|nonce| nonce.0)
+            .unwrap_or(0))
+    }
+
+    /// Load all EoA cells owned by the anyone-can-pay lock args
+    pub fn load_eoa_owner_cells(
+        &self,
+        lock_args: H160,
+    ) -> Result<Vec<(H160, value::EoaLiveCell)>, String> {
+        let key_prefix_bytes = Bytes::from(&Key::EoaOwnerCell {
+            lock_args,
+            address: None,
+            out_point: None,
+        });
+        let mut cells = Vec::new();
+        let mut iter = self.db.raw_iterator();
+        iter.seek(&key_prefix_bytes);
+        while iter.valid() {
+            if let Some((key_bytes, value_bytes)) = iter
+                .key()
+                .filter(|key| key.starts_with(&key_prefix_bytes))
+                .and_then(|key| iter.value().map(|value| (key, value)))
+            {
+                let value: value::EoaLiveCell =
+                    deserialize(value_bytes).map_err(|err| err.to_string())?;
+                let address = match Key::try_from(key_bytes)? {
+                    Key::EoaOwnerCell { address, .. } => address.expect("illegal key"),
+                    _ => {
+                        panic!("DB corrupted deserialize Key::EoaOwnerCell");
+                    }
+                };
+                cells.push((address, value));
+            } else {
+                break;
+            }
+            iter.next();
+        }
+        Ok(cells)
+    }
+
+    /// The balance of an EoA account (all the cells of the address), or the
+    /// aggregated balance of all the EoA cells when the address is an owner's
+    /// lock args.
+    pub fn load_eoa_balance(&self, address: H160) -> Result<u64, String> {
+        let address_cells = self.load_eoa_address_cells(address.clone())?;
+        if !address_cells.is_empty() {
+            return Ok(address_cells.iter().map(|cell| cell.balance()).sum());
+        }
+        let cells = self.load_eoa_owner_cells(address.clone())?;
+        if cells.is_empty() {
+            return Err(format!("eoa live cell not found: {:x}", address));
+        }
+        Ok(cells.iter().map(|(_, cell)| cell.balance()).sum())
+    }
+
+    /// Select the EoA account to send the transaction. If the sender is an
+    /// owner's lock args, the owned EoA cell with largest balance is selected.
+    /// When the selected cell don't have enough balance, the other cells of
+    /// the owner (the other cells of the address in Ethereum address mode)
+    /// are merged into the tx_origin output, largest balance first. Returns
+    /// (selected address, [(address, merged cell)]).
+    pub fn select_eoa_address(
+        &self,
+        sender: H160,
+        min_balance: u64,
+    ) -> Result<(H160, Vec<(H160, value::EoaLiveCell)>), String> {
+        let address_cells = self.load_eoa_address_cells(sender.clone())?;
+        let cells = if address_cells.is_empty() {
+            self.load_eoa_owner_cells(sender.clone())?
+        } else {
+            address_cells
+                .into_iter()
+                .map(|cell| (sender.clone(), cell))
+                .collect()
+        };
+        let mut priorities = Vec::with_capacity(cells.len());
+        for (_, cell) in &cells {
+            priorities.push(self.eoa_cell_priority(cell)?);
+        }
+        let (address, cell) = cells
+            .iter()
+            .zip(priorities)
+            .max_by_key(|(_, priority)| *priority)
+            .map(|(entry, _)| entry)
+            .ok_or_else(|| format!("eoa live cell not found: {:x}", sender))?;
+        log::debug!("select eoa account {:x} for owner {:x}", address, sender);
+        if cell.balance() >= min_balance {
+            return Ok((address.clone(), Vec::new()));
+        }
+
+        let mut other_cells = self
+            .load_eoa_owner_cells(cell.lock_args.clone())?
+            .into_iter()
+            .filter(|(_, other_cell)| other_cell != cell)
+            .collect::<Vec<_>>();
+        if self.overlay_pending {
+            // The cells consumed by the pending transactions can't be merged
+            let pending_pool = self.pending_pool();
+            other_cells.retain(|(_, other_cell)| {
+                !pending_pool.is_consumed(&other_cell.tx_hash, other_cell.output_index)
+            });
+        }
+        other_cells.sort_by_key(|(_, other_cell)| std::cmp::Reverse(other_cell.balance()));
+        let mut total_balance = cell.balance();
+        let mut merged_cells = Vec::new();
+        for (other_address, other_cell) in other_cells {
+            if total_balance >= min_balance {
+                break;
+            }
+            // The occupied capacity of the merged cell also becomes balance
+            total_balance += other_cell.capacity();
+            merged_cells.push((other_address, other_cell));
+        }
+        if total_balance < min_balance {
+            return Err(format!(
+                "No eoa cells of {:x} have enough balance: {} < {}",
+                sender, total_balance, min_balance
+            ));
+        }
+        log::debug!(
+            "merge {} eoa cells into {:x}, total balance: {}",
+            merged_cells.len(),
+            address,
+            total_balance
+        );
+        Ok((address.clone(), merged_cells))
     }
 
     // TODO: remove #[allow(unused)] later
@@ -94,7 +497,7 @@ impl Loader {
             output_index: None,
         });
 
-        let max_mature_number = get_max_mature_number(&mut self.client)?;
+        let max_mature_number = get_max_mature_number(self.client.as_mut())?;
         let mut total_capacity: u64 = 0;
         let mut live_cells = Vec::new();
 
@@ -244,6 +647,31 @@ impl Loader {
         Err(format!("Latest contract change not found: {:x}", address.0))
     }
 
+    /// The block number where the indexer started from
+    pub fn load_start_block_number(&self) -> Result<u64, String> {
+        let key_bytes = Bytes::from(&Key::StartBlock);
+        Ok(db_get::<_, value::StartBlock>(&self.db, &key_bytes)?
+            .map(|start_block| start_block.number)
+            .unwrap_or(0))
+    }
+
+    /// The contracts touched in the block, read from BlockDelta or BlockSummary
+    /// (the block is out of the rollback window).
+    pub fn load_block_contracts(
+        &self,
+        number: u64,
+    ) -> Result<Option<Vec<(ContractAddress, bool)>>, String> {
+        let delta_key_bytes = Bytes::from(&Key::BlockDelta(number));
+        if let Some(block_delta) = db_get::<_, value::BlockDelta>(&self.db, &delta_key_bytes)? {
+            return Ok(Some(block_delta.contracts));
+        }
+        let summary_key_bytes = Bytes::from(&Key::BlockSummary(number));
+        Ok(
+            db_get::<_, value::BlockSummary>(&self.db, &summary_key_bytes)?
+                .map(|block_summary| block_summary.contracts),
+        )
+    }
+
     pub fn load_contract_meta_list(
         &mut self,
         from_block: u64,
@@ -253,17 +681,16 @@ impl Loader {
             .map(Ok)
             .unwrap_or_else(|| self.client.get_tip_block_number())?;
 
+        let from_block = std::cmp::max(from_block, self.load_start_block_number()?);
         let mut all_metas = Vec::new();
         for number in from_block..=to_block {
-            let key_bytes = Bytes::from(&Key::BlockDelta(number));
-            let block_delta = match db_get::<_, value::BlockDelta>(&self.db, &key_bytes)? {
-                Some(block_delta) => block_delta,
+            let block_contracts = match self.load_block_contracts(number)? {
+                Some(block_contracts) => block_contracts,
                 None => {
                     return Ok(all_metas);
                 }
             };
-            for addr in block_delta
-                .contracts
+            for addr in block_contracts
                 .into_iter()
                 .filter(|(_, is_create)| *is_create)
                 .map(|(addr, _)| addr)
@@ -288,6 +715,11 @@ impl Loader {
 
     pub fn load_contract_meta(&self, address: ContractAddress) -> Result<ContractMeta, String> {
         let key_bytes = Bytes::from(&Key::ContractMeta(address.clone()));
+        if self.overlay_pending {
+            if let Some(meta) = self.pending_pool().contract_meta(&address) {
+                return Ok(meta.clone());
+            }
+        }
         if let Some(value) = db_get::<_, value::ContractMeta>(&self.db, &key_bytes)? {
             Ok(ContractMeta {
                 address,
@@ -302,6 +734,23 @@ impl Loader {
         }
     }
 
+    pub fn load_contract_abi(&self, address: &ContractAddress) -> Result<Option<Abi>, String> {
+        let key_bytes = Bytes::from(&Key::ContractAbi(address.clone()));
+        db_get::<_, value::ContractAbi>(&self.db, &key_bytes)?
+            .map(|value| Abi::from_json(value.0.as_str()))
+            .transpose()
+    }
+
+    /// Save the ABI of an existing contract, replace the old one
+    pub fn save_contract_abi(&self, address: ContractAddress, abi: &Abi) -> Result<(), String> {
+        self.load_contract_meta(address.clone())?;
+        let key_bytes = Bytes::from(&Key::ContractAbi(address));
+        let value_bytes = serialize(&value::ContractAbi(abi.to_json())).unwrap();
+        self.db
+            .put(&key_bytes, &value_bytes)
+            .map_err(|err| err.to_string())
+    }
+
     pub fn load_logs(
         &mut self,
         from_block: u64,
@@ -315,16 +764,16 @@ impl Loader {
             .unwrap_or_else(|| self.client.get_tip_block_number())?;
         let filter_topics = filter_topics.map(|topics| topics.into_iter().collect::<HashSet<_>>());
 
+        let from_block = std::cmp::max(from_block, self.load_start_block_number()?);
         let mut all_logs = Vec::new();
         for number in from_block..=to_block {
-            let key_bytes = Bytes::from(&Key::BlockDelta(number));
-            let block_delta = match db_get::<_, value::BlockDelta>(&self.db, &key_bytes)? {
-                Some(block_delta) => block_delta,
+            let block_contracts = match self.load_block_contracts(number)? {
+                Some(block_contracts) => block_contracts,
                 None => {
                     return Ok(all_logs);
                 }
             };
-            for (addr, _is_create) in block_delta.contracts {
+            for (addr, _is_create) in block_contracts {
                 if let Some(target_address) = address.as_ref() {
                     if &addr != target_address {
                         continue;
@@ -381,13 +830,302 @@ impl Loader {
         Ok(all_logs)
     }
 
+    /// Load transfers of an EoA account, start from the cursor position if
+    /// given. Return the transfers and the cursor of next page.
+    pub fn load_eoa_transfers(
+        &self,
+        address: H160,
+        from_block: u64,
+        to_block: Option<u64>,
+        cursor: Option<TransferCursor>,
+        limit: usize,
+    ) -> Result<(Vec<TransferInfo>, Option<TransferCursor>), String> {
+        let key_prefix_bytes = Bytes::from(&Key::EoaTransfer {
+            address: address.clone(),
+            number: None,
+            tx_index: None,
+            transfer_index: None,
+        });
+        let start_key = match cursor {
+            Some(cursor) => Key::EoaTransfer {
+                address,
+                number: Some(cursor.block_number),
+                tx_index: Some(cursor.tx_index),
+                transfer_index: Some(cursor.transfer_index),
+            },
+            None => Key::EoaTransfer {
+                address,
+                number: Some(from_block),
+                tx_index: None,
+                transfer_index: None,
+            },
+        };
+        let to_block = to_block.unwrap_or(std::u64::MAX);
+
+        let mut transfers = Vec::new();
+        let mut iter = self.db.raw_iterator();
+        iter.seek(&Bytes::from(&start_key));
+        while iter.valid() {
+            if let Some((key_bytes, value_bytes)) = iter
+                .key()
+                .filter(|key| key.starts_with(&key_prefix_bytes))
+                .and_then(|key| iter.value().map(|value| (key, value)))
+            {
+                let (number, tx_index, transfer_index) = match Key::try_from(key_bytes)? {
+                    Key::EoaTransfer {
+                        number,
+                        tx_index,
+                        transfer_index,
+                        ..
+                    } => (
+                        number.expect("illegal key"),
+                        tx_index.expect("illegal key"),
+                        transfer_index.expect("illegal key"),
+                    ),
+                    _ => {
+                        panic!("DB corrupted deserialize Key::EoaTransfer");
+                    }
+                };
+                if number > to_block {
+                    break;
+                }
+                if transfers.len() >= limit {
+                    let next_cursor = TransferCursor {
+                        block_number: number,
+                        tx_index,
+                        transfer_index,
+                    };
+                    return Ok((transfers, Some(next_cursor)));
+                }
+                let value: value::EoaTransfer =
+                    deserialize(value_bytes).map_err(|err| err.to_string())?;
+                transfers.push(TransferInfo {
+                    block_number: number,
+                    tx_index,
+                    transfer_index,
+                    tx_hash: value.tx_hash,
+                    counterparty: value.counterparty,
+                    value: value.value,
+                    incoming: value.incoming,
+                });
+            } else {
+                break;
+            }
+            iter.next();
+        }
+        Ok((transfers, None))
+    }
+
+    /// Load the token balances of a holder (token tracker)
+    pub fn load_token_balances(&self, holder: H160) -> Result<Vec<TokenBalanceInfo>, String> {
+        let key_prefix_bytes = Bytes::from(&Key::HolderToken {
+            holder: holder.clone(),
+            token: None,
+        });
+        let mut balances = Vec::new();
+        let mut iter = self.db.raw_iterator();
+        iter.seek(&key_prefix_bytes);
+        while iter.valid() {
+            if let Some((key_bytes, value_bytes)) = iter
+                .key()
+                .filter(|key| key.starts_with(&key_prefix_bytes))
+                .and_then(|key| iter.value().map(|value| (key, value)))
+            {
+                let token = match Key::try_from(key_bytes)? {
+                    Key::HolderToken { token, .. } => token.expect("illegal key"),
+                    _ => {
+                        panic!("DB corrupted deserialize Key::HolderToken");
+                    }
+                };
+                let value: value::TokenBalance =
+                    deserialize(value_bytes).map_err(|err| err.to_string())?;
+                balances.push(TokenBalanceInfo {
+                    token,
+                    holder: holder.clone(),
+                    kind: value.kind,
+                    balance: to_decimal(value.balance.as_bytes()),
+                });
+            } else {
+                break;
+            }
+            iter.next();
+        }
+        Ok(balances)
+    }
+
+    /// Load the holders of a token start from the cursor holder (token tracker)
+    pub fn load_token_holders(
+        &self,
+        token: ContractAddress,
+        cursor: Option<H160>,
+        limit: usize,
+    ) -> Result<(Vec<TokenBalanceInfo>, Option<H160>), String> {
+        let key_prefix_bytes = Bytes::from(&Key::TokenHolder {
+            token: token.clone(),
+            holder: None,
+        });
+        let start_key = Key::TokenHolder {
+            token: token.clone(),
+            holder: cursor,
+        };
+        let mut holders = Vec::new();
+        let mut iter = self.db.raw_iterator();
+        iter.seek(&Bytes::from(&start_key));
+        while iter.valid() {
+            if let Some((key_bytes, value_bytes)) = iter
+                .key()
+                .filter(|key| key.starts_with(&key_prefix_bytes))
+                .and_then(|key| iter.value().map(|value| (key, value)))
+            {
+                let holder = match Key::try_from(key_bytes)? {
+                    Key::TokenHolder { holder, .. } => holder.expect("illegal key"),
+                    _ => {
+                        panic!("DB corrupted deserialize Key::TokenHolder");
+                    }
+                };
+                if holders.len() >= limit {
+                    return Ok((holders, Some(holder)));
+                }
+                let value: value::TokenBalance =
+                    deserialize(value_bytes).map_err(|err| err.to_string())?;
+                holders.push(TokenBalanceInfo {
+                    token: token.clone(),
+                    holder,
+                    kind: value.kind,
+                    balance: to_decimal(value.balance.as_bytes()),
+                });
+            } else {
+                break;
+            }
+            iter.next();
+        }
+        Ok((holders, None))
+    }
+
+    /// Load the owner of an ERC721 token (token tracker)
+    pub fn load_token_owner(
+        &self,
+        token: ContractAddress,
+        token_id: H256,
+    ) -> Result<Option<H160>, String> {
+        let key_bytes = Bytes::from(&Key::TokenOwner { token, token_id });
+        db_get::<_, value::TokenOwner>(&self.db, &key_bytes).map(|owner| owner.map(|owner| owner.0))
+    }
+
+    /// Load the token transfers of an account, filtered by the token if
+    /// provided (token tracker)
+    pub fn load_token_transfers(
+        &self,
+        address: H160,
+        token: Option<ContractAddress>,
+        from_block: u64,
+        to_block: Option<u64>,
+        cursor: Option<TransferCursor>,
+        limit: usize,
+    ) -> Result<(Vec<TokenTransferInfo>, Option<TransferCursor>), String> {
+        let key_prefix_bytes = Bytes::from(&Key::TokenTransfer {
+            address: address.clone(),
+            number: None,
+            tx_index: None,
+            transfer_index: None,
+        });
+        let start_key = match cursor {
+            Some(cursor) => Key::TokenTransfer {
+                address,
+                number: Some(cursor.block_number),
+                tx_index: Some(cursor.tx_index),
+                transfer_index: Some(cursor.transfer_index),
+            },
+            None => Key::TokenTransfer {
+                address,
+                number: Some(from_block),
+                tx_index: None,
+                transfer_index: None,
+            },
+        };
+        let to_block = to_block.unwrap_or(std::u64::MAX);
+
+        let mut transfers = Vec::new();
+        let mut iter = self.db.raw_iterator();
+        iter.seek(&Bytes::from(&start_key));
+        while iter.valid() {
+            if let Some((key_bytes, value_bytes)) = iter
+                .key()
+                .filter(|key| key.starts_with(&key_prefix_bytes))
+                .and_then(|key| iter.value().map(|value| (key, value)))
+            {
+                let (number, tx_index, transfer_index) = match Key::try_from(key_bytes)? {
+                    Key::TokenTransfer {
+                        number,
+                        tx_index,
+                        transfer_index,
+                        ..
+                    } => (
+                        number.expect("illegal key"),
+                        tx_index.expect("illegal key"),
+                        transfer_index.expect("illegal key"),
+                    ),
+                    _ => {
+                        panic!("DB corrupted deserialize Key::TokenTransfer");
+                    }
+                };
+                if number > to_block {
+                    break;
+                }
+                let value: value::TokenTransfer =
+                    deserialize(value_bytes).map_err(|err| err.to_string())?;
+                if token.as_ref().map(|token| token != &value.token) == Some(true) {
+                    iter.next();
+                    continue;
+                }
+                if transfers.len() >= limit {
+                    let next_cursor = TransferCursor {
+                        block_number: number,
+                        tx_index,
+                        transfer_index,
+                    };
+                    return Ok((transfers, Some(next_cursor)));
+                }
+                transfers.push(TokenTransferInfo {
+                    block_number: number,
+                    tx_index,
+                    transfer_index,
+                    tx_hash: value.tx_hash,
+                    token: value.token,
+                    kind: value.kind,
+                    counterparty: value.counterparty,
+                    value: to_decimal(value.value.as_bytes()),
+                    incoming: value.incoming,
+                });
+            } else {
+                break;
+            }
+            iter.next();
+        }
+        Ok((transfers, None))
+    }
+
     pub fn load_header_deps(&mut self, inputs: &[packed::CellInput]) -> Result<Vec<H256>, String> {
+        // The inputs from pending transactions have no header
+        let inputs = {
+            let pending_pool = self.pending_pool();
+            inputs
+                .iter()
+                .filter(|input| {
+                    !pending_pool.is_pending(&input.previous_output().tx_hash().unpack())
+                })
+                .collect::<Vec<_>>()
+        };
+        let tx_hashes = inputs
+            .iter()
+            .map(|input| Unpack::<H256>::unpack(&input.previous_output().tx_hash()))
+            .collect::<Vec<_>>();
+        let txs = self.client.get_transactions(tx_hashes.clone())?;
         inputs
             .iter()
-            .map(|input| {
-                let tx_hash = Unpack::<H256>::unpack(&input.previous_output().tx_hash());
-                self.client
-                    .get_transaction(tx_hash.clone())?
+            .zip(tx_hashes.into_iter().zip(txs.into_iter()))
+            .map(|(input, (tx_hash, tx_opt))| {
+                tx_opt
                     .ok_or_else(|| format!("Transaction not found for input: {:?}", input))?
                     .tx_status
                     .block_hash
@@ -421,7 +1159,7 @@ impl Loader {
 }
 
 // Get max mature block number
-pub fn get_max_mature_number(client: &mut HttpRpcClient) -> Result<u64, String> {
+pub fn get_max_mature_number(client: &mut dyn ChainClient) -> Result<u64, String> {
     let tip_epoch = client
         .get_tip_header()
         .map(|header| EpochNumberWithFraction::from_full_value(header.inner.epoch.value()))?;
@@ -472,3 +1210,306 @@ pub fn is_mature(max_mature_number: u64, number: u64, tx_index: u32) -> bool {
         || number == 0
         || number <= max_mature_number
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_chain::MockChain;
+    use ckb_types::{core, h160, h256};
+
+    fn put_eoa_cell(db: &DB, address: H160, lock_args: H160, output_index: u32, balance: u64) {
+        let cell = value::EoaLiveCell::new(
+            H256::default(),
+            output_index,
+            lock_args.clone(),
+            balance + 100,
+            balance,
+        );
+        let value_bytes = serialize(&cell).unwrap();
+        let owner_key = Key::EoaOwnerCell {
+            lock_args,
+            address: Some(address.clone()),
+            out_point: Some(cell.out_point()),
+        };
+        db.put(&Bytes::from(&owner_key), &value_bytes).unwrap();
+        let key = Key::EoaLiveCell {
+            address,
+            out_point: Some(cell.out_point()),
+        };
+        db.put(&Bytes::from(&key), &value_bytes).unwrap();
+    }
+
+    #[test]
+    fn test_select_eoa_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let owner = h160!("0xa1");
+        put_eoa_cell(&db, h160!("0xe1"), owner.clone(), 0, 300);
+        put_eoa_cell(&db, h160!("0xe2"), owner.clone(), 1, 500);
+        let loader = Loader::new(db, Box::new(MockChain::new(Vec::new())));
+
+        // The EoA address is used directly
+        assert_eq!(
+            loader.select_eoa_address(h160!("0xe1"), 0).unwrap(),
+            (h160!("0xe1"), Vec::new())
+        );
+        // The owned cell with largest balance
+        assert_eq!(
+            loader.select_eoa_address(owner.clone(), 400).unwrap().0,
+            h160!("0xe2")
+        );
+        // The other owned cell is merged
+        let (address, merged_cells) = loader.select_eoa_address(owner.clone(), 700).unwrap();
+        assert_eq!(address, h160!("0xe2"));
+        assert_eq!(
+            merged_cells
+                .iter()
+                .map(|(address, cell)| (address.clone(), cell.output_index, cell.balance()))
+                .collect::<Vec<_>>(),
+            vec![(h160!("0xe1"), 0, 300)]
+        );
+        assert!(loader.select_eoa_address(owner, 1000).is_err());
+        assert!(loader.select_eoa_address(h160!("0xa2"), 0).is_err());
+    }
+
+    #[test]
+    fn test_select_eoa_address_cells() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        // Ethereum address mode: the address is the lock args, and one address
+        // can own many cells
+        let address = h160!("0xe1");
+        put_eoa_cell(&db, address.clone(), address.clone(), 0, 300);
+        put_eoa_cell(&db, address.clone(), address.clone(), 1, 500);
+        let loader = Loader::new(db, Box::new(MockChain::new(Vec::new())));
+
+        assert_eq!(
+            loader
+                .load_eoa_address_cells(address.clone())
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(loader.load_eoa_balance(address.clone()).unwrap(), 800);
+        let cell = loader.load_eoa_live_cell_value(address.clone()).unwrap();
+        assert_eq!((cell.output_index, cell.balance()), (1, 500));
+        assert_eq!(
+            loader.select_eoa_address(address.clone(), 400).unwrap(),
+            (address.clone(), Vec::new())
+        );
+        let (selected, merged_cells) = loader.select_eoa_address(address.clone(), 700).unwrap();
+        assert_eq!(selected, address);
+        assert_eq!(merged_cells.len(), 1);
+        assert_eq!(merged_cells[0].1.output_index, 0);
+    }
+
+    #[test]
+    fn test_load_eoa_transfers() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let address = h160!("0xe1");
+        for (number, tx_index, transfer_index) in vec![(1, 1, 0), (1, 1, 2), (3, 2, 0), (5, 1, 1)] {
+            let key = Key::EoaTransfer {
+                address: address.clone(),
+                number: Some(number),
+                tx_index: Some(tx_index),
+                transfer_index: Some(transfer_index),
+            };
+            let transfer = value::EoaTransfer {
+                tx_hash: H256::default(),
+                counterparty: h160!("0xe2"),
+                value: number,
+                incoming: transfer_index > 0,
+            };
+            db.put(&Bytes::from(&key), &serialize(&transfer).unwrap())
+                .unwrap();
+        }
+        // Transfers of other address
+        let other_key = Key::EoaTransfer {
+            address: h160!("0xe2"),
+            number: Some(1),
+            tx_index: Some(1),
+            transfer_index: Some(0),
+        };
+        db.put(&Bytes::from(&other_key), &[]).unwrap();
+        let loader = Loader::new(db, Box::new(MockChain::new(Vec::new())));
+
+        let (transfers, cursor) = loader
+            .load_eoa_transfers(address.clone(), 0, None, None, 2)
+            .unwrap();
+        let positions = |transfers: &[TransferInfo]| {
+            transfers
+                .iter()
+                .map(|info| (info.block_number, info.tx_index, info.transfer_index))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(positions(&transfers), vec![(1, 1, 0), (1, 1, 2)]);
+        assert!(transfers[1].incoming);
+        let cursor = cursor.unwrap();
+        assert_eq!(
+            cursor,
+            TransferCursor {
+                block_number: 3,
+                tx_index: 2,
+                transfer_index: 0,
+            }
+        );
+        let (transfers, cursor) = loader
+            .load_eoa_transfers(address.clone(), 0, None, Some(cursor), 2)
+            .unwrap();
+        assert_eq!(positions(&transfers), vec![(3, 2, 0), (5, 1, 1)]);
+        assert!(cursor.is_none());
+
+        // Block range
+        let (transfers, cursor) = loader
+            .load_eoa_transfers(address, 2, Some(4), None, 2)
+            .unwrap();
+        assert_eq!(positions(&transfers), vec![(3, 2, 0)]);
+        assert!(cursor.is_none());
+    }
+
+    #[test]
+    fn test_load_live_cells() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let output = |capacity: u64| {
+            packed::CellOutput::new_builder()
+                .capacity(capacity.pack())
+                .build()
+        };
+        let genesis_tx = core::TransactionBuilder::default()
+            .output(output(100))
+            .output_data(Bytes::from(vec![1]).pack())
+            .output(output(200))
+            .output_data(Bytes::from(vec![2]).pack())
+            .build();
+        let genesis_tx_hash: H256 = genesis_tx.hash().unpack();
+        // The indexed cell is not required to be in the chain
+        let indexed_tx_hash = h256!("0x33");
+        let indexed_out_point = packed::OutPoint::new(indexed_tx_hash.pack(), 0);
+        let cell_data = value::CellData::new(&output(300), Bytes::from(vec![3]));
+        db.put(
+            &Bytes::from(&Key::CellData(indexed_out_point)),
+            &serialize(&cell_data).unwrap(),
+        )
+        .unwrap();
+        let mut loader = Loader::new(db, Box::new(MockChain::new(vec![genesis_tx])));
+
+        let cells = loader
+            .load_live_cells(vec![
+                (genesis_tx_hash.clone(), 1),
+                (indexed_tx_hash.clone(), 0),
+                (genesis_tx_hash.clone(), 0),
+            ])
+            .unwrap();
+        let cells = cells
+            .into_iter()
+            .map(|(output, data)| (output.as_bytes(), data))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cells,
+            vec![
+                (output(200).as_bytes(), Bytes::from(vec![2])),
+                (output(300).as_bytes(), Bytes::from(vec![3])),
+                (output(100).as_bytes(), Bytes::from(vec![1])),
+            ]
+        );
+        assert!(loader.load_live_cells(vec![(indexed_tx_hash, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_detect_submitted_txs() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let genesis_tx = core::TransactionBuilder::default()
+            .output(packed::CellOutput::default())
+            .output_data(Bytes::new().pack())
+            .output(packed::CellOutput::default())
+            .output_data(Bytes::new().pack())
+            .build();
+        let spend_tx = |index: u32| {
+            core::TransactionBuilder::default()
+                .input(packed::CellInput::new(
+                    packed::OutPoint::new(genesis_tx.hash(), index),
+                    0,
+                ))
+                .output(packed::CellOutput::default())
+                .output_data(Bytes::new().pack())
+                .build()
+        };
+        let pending_tx = |tx_hash: H256, address: &ContractAddress| PendingTx {
+            tx_hash: tx_hash.clone(),
+            inputs: Vec::new(),
+            contracts: Vec::new(),
+            metas: vec![ContractMeta {
+                address: address.clone(),
+                code: Bytes::from(vec![0x60]),
+                tx_hash,
+                output_index: 0,
+                balance: 0,
+                destructed: false,
+            }],
+            eoa_cells: Vec::new(),
+            eth_nonce: None,
+            cells: Default::default(),
+            timestamp: Instant::now(),
+        };
+        let mut chain = MockChain::new(vec![genesis_tx.clone()]);
+        let external_tx = spend_tx(0);
+        let committed_tx = spend_tx(1);
+        let mut loader = Loader::new(db, Box::new(chain.clone()));
+        let address = ContractAddress(h160!("0xc1"));
+        let other_address = ContractAddress(h160!("0xc2"));
+        loader.add_built_tx(pending_tx(external_tx.hash().unpack(), &address));
+        loader.add_built_tx(pending_tx(committed_tx.hash().unpack(), &other_address));
+        loader.add_built_tx(pending_tx(h256!("0x99"), &other_address));
+        // Sent by other tool
+        chain.send_transaction(committed_tx.data().into()).unwrap();
+        chain.mine_block();
+        chain.send_transaction(external_tx.data().into()).unwrap();
+
+        loader.detect_submitted_txs();
+        assert!(loader
+            .pending_pool()
+            .is_pending(&external_tx.hash().unpack()));
+        assert_eq!(loader.pending_pool().built_tx_hashes(), vec![h256!("0x99")]);
+        // The pending contracts are only visible in overlay mode
+        assert!(loader.load_contract_meta(address.clone()).is_err());
+        loader.overlay_pending = true;
+        assert_eq!(
+            loader.load_contract_meta(address).unwrap().tx_hash,
+            external_tx.hash().unpack()
+        );
+        assert!(loader.load_contract_meta(other_address).is_err());
+    }
+
+    #[test]
+    fn test_load_sync_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let chain = MockChain::new(Vec::new());
+        chain.mine_block();
+        chain.mine_block();
+        let last = value::Last {
+            number: 1,
+            hash: H256::default(),
+        };
+        db.put(&Bytes::from(&Key::Last), &serialize(&last).unwrap())
+            .unwrap();
+        let mut loader = Loader::new(db, Box::new(chain.clone()));
+
+        // The tip is not fetched by the indexer yet
+        let status = loader.load_sync_status().unwrap();
+        assert_eq!(status.tip_number, 1);
+        assert_eq!(status.lag, 0);
+        assert_eq!(loader.load_ckb_tip().unwrap(), 2);
+        assert_eq!(loader.load_sync_status().unwrap().lag, 1);
+        // The cached tip is used (no RPC request)
+        chain.mine_block();
+        assert_eq!(loader.load_ckb_tip().unwrap(), 2);
+        loader.record_ckb_tip(3);
+        let status = loader.load_sync_status().unwrap();
+        assert_eq!(status.tip_number, 3);
+        assert_eq!(status.lag, 2);
+    }
+}
@@ -0,0 +1,551 @@
+use bincode::{deserialize, serialize};
+use ckb_jsonrpc_types::Transaction;
+use ckb_types::{bytes::Bytes, H160, H256};
+use rocksdb::{WriteBatch, DB};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::indexer::eoa_record;
+use super::{db_get, value, Key, KeyType};
+use crate::client::ChainClient;
+use crate::types::{AddressMode, ContractAddress};
+
+/// The schema version of current database layout, increase it when the layout
+/// of `Key` or `value::*` changed and add a migration to `MIGRATIONS`. Never
+/// reuse a version number once it's written to a database.
+pub const SCHEMA_VERSION: u32 = 6;
+
+/// The versions written by the development builds, their layouts are not
+/// released (and the numbers were reused), so they can't be migrated.
+const DEV_SCHEMA_VERSIONS: &[u32] = &[1, 2, 3, 4, 5];
+
+/// Rewrite the keys/values from one schema version to the next
+type Migration = fn(&DB, &mut WriteBatch) -> Result<(), String>;
+
+/// (from_version, migration), the migration upgrade the database to `from_version + 1`
+const MIGRATIONS: &[(u32, Migration)] = &[];
+
+/// The number of transactions to load in one batch request
+const TX_BATCH_SIZE: usize = 100;
+
+/// The `value::EoaLiveCell` before the schema version, without `lock_args`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct BaselineEoaLiveCell {
+    tx_hash: H256,
+    output_index: u32,
+    capacity: u64,
+    balance: u64,
+}
+
+/// The `value::BlockDelta` before the schema version, the added EoA cells are
+/// the addresses only.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct BaselineBlockDelta {
+    contracts: Vec<(ContractAddress, bool)>,
+    added_cells: Vec<(H256, u32, u32, value::LockLiveCell)>,
+    removed_cells: Vec<(H256, u64, u32, u32, value::LockLiveCell)>,
+    eoa_added_cells: Vec<H160>,
+    eoa_removed_cells: Vec<(H160, BaselineEoaLiveCell)>,
+    destructed_contracts: Vec<ContractAddress>,
+}
+
+/// Load all the (key, value) pairs start with the prefix
+fn prefix_entries(db: &DB, key_prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut entries = Vec::new();
+    let mut iter = db.raw_iterator();
+    iter.seek(key_prefix);
+    while iter.valid() {
+        if let Some((key_bytes, value_bytes)) = iter
+            .key()
+            .filter(|key| key.starts_with(key_prefix))
+            .and_then(|key| iter.value().map(|value| (key, value)))
+        {
+            entries.push((key_bytes.to_vec(), value_bytes.to_vec()));
+        } else {
+            break;
+        }
+        iter.next();
+    }
+    entries
+}
+
+fn not_baseline() -> String {
+    String::from(
+        "The database is not created by a released polyjuice, please remove it and index again",
+    )
+}
+
+/// Decode a value of the baseline layout, the value must be serialized back
+/// to the same bytes (bincode ignores the trailing bytes).
+fn decode_baseline<T: DeserializeOwned + Serialize>(value_bytes: &[u8]) -> Result<T, String> {
+    deserialize(value_bytes)
+        .ok()
+        .filter(|value: &T| serialize(value).ok().as_deref() == Some(value_bytes))
+        .ok_or_else(not_baseline)
+}
+
+/// Load the transactions in batches
+fn load_transactions(
+    client: &mut dyn ChainClient,
+    tx_hashes: Vec<H256>,
+) -> Result<HashMap<H256, Transaction>, String> {
+    let mut txs = HashMap::default();
+    for chunk in tx_hashes.chunks(TX_BATCH_SIZE) {
+        let chunk_txs = client.get_transactions(chunk.to_vec())?;
+        for (tx_hash, tx_opt) in chunk.iter().zip(chunk_txs) {
+            let tx = tx_opt
+                .ok_or_else(|| format!("Transaction not found: {:#x}", tx_hash))?
+                .transaction
+                .inner;
+            txs.insert(tx_hash.clone(), tx);
+        }
+    }
+    Ok(txs)
+}
+
+/// The EoA address and cell of a transaction output
+fn load_eoa_cell(
+    txs: &HashMap<H256, Transaction>,
+    tx_hash: &H256,
+    output_index: u32,
+) -> Result<(H160, value::EoaLiveCell), String> {
+    let tx = txs.get(tx_hash).expect("transaction loaded");
+    let index = output_index as usize;
+    let output = tx
+        .outputs
+        .get(index)
+        .ok_or_else(|| format!("Output not found: {:#x}, {}", tx_hash, output_index))?;
+    let type_script = output
+        .type_
+        .as_ref()
+        .ok_or_else(|| format!("Not an EoA cell: {:#x}, {}", tx_hash, output_index))?;
+    let data_size = tx.outputs_data[index].len() as u32;
+    // The baseline only supports the CKB address mode
+    Ok(eoa_record(
+        type_script,
+        &output.lock,
+        tx_hash,
+        output_index,
+        output,
+        data_size,
+        AddressMode::Ckb,
+    ))
+}
+
+/// Add the lock args to the EoA cell of the baseline layout
+fn migrate_eoa_cell(
+    txs: &HashMap<H256, Transaction>,
+    address: &H160,
+    cell: &BaselineEoaLiveCell,
+) -> Result<value::EoaLiveCell, String> {
+    let (eoa_address, eoa_cell) = load_eoa_cell(txs, &cell.tx_hash, cell.output_index)?;
+    if &eoa_address != address {
+        return Err(format!(
+            "The EoA cell {:#x}, {} is not owned by {:x}",
+            cell.tx_hash, cell.output_index, address
+        ));
+    }
+    Ok(value::EoaLiveCell::new(
+        cell.tx_hash.clone(),
+        cell.output_index,
+        eoa_cell.lock_args,
+        cell.capacity,
+        cell.balance,
+    ))
+}
+
+// The layout before the schema version (the last release) keyed the EoA cells
+// by address only (one cell per address) without `lock_args`, and
+// `value::BlockDelta` recorded the addresses of the added EoA cells. The lock
+// args and the added cells are loaded from the transactions on CKB. The EoA
+// transfers, the processors and the Ethereum nonces start empty.
+fn migrate_baseline(
+    db: &DB,
+    batch: &mut WriteBatch,
+    client: &mut dyn ChainClient,
+) -> Result<(), String> {
+    let mut live_cells = Vec::new();
+    for (key_bytes, value_bytes) in prefix_entries(db, &[KeyType::EoaLiveCell as u8]) {
+        let address = H160::from_slice(&key_bytes[1..]).map_err(|_| not_baseline())?;
+        let cell: BaselineEoaLiveCell = decode_baseline(&value_bytes)?;
+        live_cells.push((key_bytes, address, cell));
+    }
+    let mut block_deltas = Vec::new();
+    for (key_bytes, value_bytes) in prefix_entries(db, &[KeyType::BlockDelta as u8]) {
+        let block_delta: BaselineBlockDelta = decode_baseline(&value_bytes)?;
+        block_deltas.push((key_bytes, block_delta));
+    }
+
+    // The transactions of the EoA cells, and the typed cells added in the
+    // blocks which added EoA cells
+    let mut tx_hashes: HashSet<H256> = HashSet::default();
+    tx_hashes.extend(live_cells.iter().map(|(_, _, cell)| cell.tx_hash.clone()));
+    for (_, block_delta) in &block_deltas {
+        tx_hashes.extend(
+            block_delta
+                .eoa_removed_cells
+                .iter()
+                .map(|(_, cell)| cell.tx_hash.clone()),
+        );
+        if !block_delta.eoa_added_cells.is_empty() {
+            tx_hashes.extend(
+                block_delta
+                    .added_cells
+                    .iter()
+                    .filter(|(_, _, _, cell)| cell.type_script_hash.is_some())
+                    .map(|(_, _, _, cell)| cell.tx_hash.clone()),
+            );
+        }
+    }
+    log::info!("Load {} transactions of the EoA cells", tx_hashes.len());
+    let txs = load_transactions(client, tx_hashes.into_iter().collect())?;
+
+    for (key_bytes, address, cell) in live_cells {
+        let eoa_cell = migrate_eoa_cell(&txs, &address, &cell)?;
+        let value_bytes = serialize(&eoa_cell).unwrap();
+        let owner_key = Key::EoaOwnerCell {
+            lock_args: eoa_cell.lock_args.clone(),
+            address: Some(address.clone()),
+            out_point: Some(eoa_cell.out_point()),
+        };
+        let key = Key::EoaLiveCell {
+            address,
+            out_point: Some(eoa_cell.out_point()),
+        };
+        batch.delete(&key_bytes);
+        batch.put(&Bytes::from(&owner_key), &value_bytes);
+        batch.put(&Bytes::from(&key), &value_bytes);
+    }
+    for (key_bytes, old_delta) in block_deltas {
+        let mut eoa_added_cells = Vec::new();
+        for address in &old_delta.eoa_added_cells {
+            // The last cell of the address added in the block
+            let mut added_cell: Option<((u32, u32), value::EoaLiveCell)> = None;
+            for (_, tx_index, output_index, cell) in &old_delta.added_cells {
+                if cell.type_script_hash.is_none() {
+                    continue;
+                }
+                let (eoa_address, eoa_cell) =
+                    load_eoa_cell(&txs, &cell.tx_hash, cell.output_index)?;
+                let position = (*tx_index, *output_index);
+                if &eoa_address == address
+                    && added_cell
+                        .as_ref()
+                        .map_or(true, |(last, _)| last < &position)
+                {
+                    added_cell = Some((position, eoa_cell));
+                }
+            }
+            let (_, eoa_cell) = added_cell
+                .ok_or_else(|| format!("The added EoA cell of {:x} not found", address))?;
+            eoa_added_cells.push((address.clone(), eoa_cell));
+        }
+        let eoa_removed_cells = old_delta
+            .eoa_removed_cells
+            .iter()
+            .map(|(address, cell)| Ok((address.clone(), migrate_eoa_cell(&txs, address, cell)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let block_delta = value::BlockDelta {
+            contracts: old_delta.contracts,
+            added_cells: old_delta.added_cells,
+            removed_cells: old_delta.removed_cells,
+            eoa_added_cells,
+            eoa_removed_cells,
+            destructed_contracts: old_delta.destructed_contracts,
+            eoa_transfers: Vec::new(),
+            processors: Vec::new(),
+            eth_nonces: Vec::new(),
+        };
+        batch.put(&key_bytes, &serialize(&block_delta).unwrap());
+    }
+    Ok(())
+}
+
+/// Write the schema version to a new database, or migrate an old database to
+/// current schema version. Refuse incompatible databases. The database
+/// created before the schema version is migrated with the transactions
+/// loaded from the client.
+pub fn init_or_migrate(db: &DB, client: Option<&mut dyn ChainClient>) -> Result<(), String> {
+    let version_key_bytes = Bytes::from(&Key::SchemaVersion);
+    let mut version = match db_get::<_, value::SchemaVersion>(db, &version_key_bytes)? {
+        Some(value::SchemaVersion(version)) => version,
+        None => {
+            if db_get::<_, value::Last>(db, &Bytes::from(&Key::Last))?.is_some() {
+                let client = client.ok_or_else(|| {
+                    String::from(
+                        "The database is created by an old polyjuice without schema version, run `polyjuice run` to migrate it first",
+                    )
+                })?;
+                log::info!(
+                    "Migrate database schema version: baseline => {}",
+                    SCHEMA_VERSION
+                );
+                let mut batch = WriteBatch::default();
+                migrate_baseline(db, &mut batch, client)?;
+                let value_bytes = serialize(&value::SchemaVersion(SCHEMA_VERSION)).unwrap();
+                batch.put(&version_key_bytes, &value_bytes);
+                db.write(batch).map_err(|err| err.to_string())?;
+                return Ok(());
+            }
+            log::info!("Initialize database schema version: {}", SCHEMA_VERSION);
+            let value_bytes = serialize(&value::SchemaVersion(SCHEMA_VERSION)).unwrap();
+            db.put(&version_key_bytes, &value_bytes)
+                .map_err(|err| err.to_string())?;
+            return Ok(());
+        }
+    };
+    if DEV_SCHEMA_VERSIONS.contains(&version) {
+        return Err(format!(
+            "The database schema version {} is created by a development build, please remove it and index again",
+            version
+        ));
+    }
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "The database schema version {} is newer than supported version {}, please upgrade polyjuice",
+            version, SCHEMA_VERSION
+        ));
+    }
+    while version < SCHEMA_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|(from_version, _)| *from_version == version)
+            .map(|(_, migration)| migration)
+            .ok_or_else(|| format!("No migration from schema version {}", version))?;
+        log::info!(
+            "Migrate database schema version: {} => {}",
+            version,
+            version + 1
+        );
+        let mut batch = WriteBatch::default();
+        migration(db, &mut batch)?;
+        let value_bytes = serialize(&value::SchemaVersion(version + 1)).unwrap();
+        batch.put(&version_key_bytes, &value_bytes);
+        db.write(batch).map_err(|err| err.to_string())?;
+        version += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_chain::MockChain;
+    use crate::types::{cell_balance, ONE_CKB};
+    use ckb_types::{core, packed, prelude::*};
+
+    #[test]
+    fn test_init_or_migrate() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DB::open_default(dir.path()).unwrap();
+        let version_key_bytes = Bytes::from(&Key::SchemaVersion);
+
+        init_or_migrate(&db, None).unwrap();
+        let version: value::SchemaVersion = db_get(&db, &version_key_bytes).unwrap().unwrap();
+        assert_eq!(version.0, SCHEMA_VERSION);
+        // Open again
+        init_or_migrate(&db, None).unwrap();
+
+        let value_bytes = serialize(&value::SchemaVersion(SCHEMA_VERSION + 1)).unwrap();
+        db.put(&version_key_bytes, &value_bytes).unwrap();
+        assert!(init_or_migrate(&db, None).is_err());
+        // The versions of the development builds
+        for version in DEV_SCHEMA_VERSIONS {
+            let value_bytes = serialize(&value::SchemaVersion(*version)).unwrap();
+            db.put(&version_key_bytes, &value_bytes).unwrap();
+            let err = init_or_migrate(&db, None).unwrap_err();
+            assert!(err.contains("development build"), "{}", err);
+        }
+    }
+
+    /// An EoA cell with the type id args and the lock args
+    fn eoa_output(type_args: &Bytes, lock_args: &Bytes, capacity: u64) -> packed::CellOutput {
+        let type_script = packed::Script::new_builder()
+            .hash_type(core::ScriptHashType::Type.into())
+            .args(type_args.pack())
+            .build();
+        let lock_script = packed::Script::new_builder()
+            .hash_type(core::ScriptHashType::Data.into())
+            .args(lock_args.pack())
+            .build();
+        packed::CellOutput::new_builder()
+            .capacity(core::Capacity::bytes(capacity as usize).unwrap().pack())
+            .lock(lock_script)
+            .type_(
+                packed::ScriptOpt::new_builder()
+                    .set(Some(type_script))
+                    .build(),
+            )
+            .build()
+    }
+
+    fn baseline_cell(tx: &core::TransactionView, output_index: u32) -> BaselineEoaLiveCell {
+        let output = tx.outputs().get(output_index as usize).unwrap();
+        BaselineEoaLiveCell {
+            tx_hash: tx.hash().unpack(),
+            output_index,
+            capacity: output.capacity().unpack(),
+            balance: cell_balance(&output, 0),
+        }
+    }
+
+    fn lock_live_cell(tx: &core::TransactionView, output_index: u32) -> value::LockLiveCell {
+        let output = tx.outputs().get(output_index as usize).unwrap();
+        value::LockLiveCell {
+            tx_hash: tx.hash().unpack(),
+            output_index,
+            capacity: output.capacity().unpack(),
+            type_script_hash: output
+                .type_()
+                .to_opt()
+                .map(|script| script.calc_script_hash().unpack()),
+            data_size: 0,
+        }
+    }
+
+    #[test]
+    fn test_migrate_baseline() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DB::open_default(dir.path()).unwrap();
+        let type_args = Bytes::from(vec![0x11; 32]);
+        let lock_args = Bytes::from(vec![0x22; 20]);
+        let eoa_output = |capacity: u64| eoa_output(&type_args, &lock_args, capacity);
+        let address = AddressMode::Ckb.eoa_address(&type_args, &lock_args);
+
+        let genesis_tx = core::TransactionBuilder::default()
+            .output(eoa_output(1000))
+            .output_data(Bytes::new().pack())
+            .build();
+        let transfer_tx = core::TransactionBuilder::default()
+            .input(packed::CellInput::new(
+                packed::OutPoint::new(genesis_tx.hash(), 0),
+                0,
+            ))
+            .output(eoa_output(900))
+            .output_data(Bytes::new().pack())
+            .build();
+        let mut chain = MockChain::new(vec![genesis_tx.clone()]);
+        chain.append_block(vec![transfer_tx.clone()]);
+
+        // The database indexed the two blocks by the baseline polyjuice
+        let lock_hash: H256 = eoa_output(0).lock().calc_script_hash().unpack();
+        let last = value::Last {
+            number: 1,
+            hash: chain.tip().hash().unpack(),
+        };
+        db.put(&Bytes::from(&Key::Last), &serialize(&last).unwrap())
+            .unwrap();
+        let mut live_key = vec![KeyType::EoaLiveCell as u8];
+        live_key.extend(address.as_bytes());
+        db.put(
+            &live_key,
+            &serialize(&baseline_cell(&transfer_tx, 0)).unwrap(),
+        )
+        .unwrap();
+        let genesis_delta = BaselineBlockDelta {
+            contracts: Vec::new(),
+            added_cells: vec![(lock_hash.clone(), 1, 0, lock_live_cell(&genesis_tx, 0))],
+            removed_cells: Vec::new(),
+            eoa_added_cells: vec![address.clone()],
+            eoa_removed_cells: Vec::new(),
+            destructed_contracts: Vec::new(),
+        };
+        let transfer_delta = BaselineBlockDelta {
+            contracts: Vec::new(),
+            added_cells: vec![(lock_hash.clone(), 1, 0, lock_live_cell(&transfer_tx, 0))],
+            removed_cells: vec![(lock_hash, 0, 1, 0, lock_live_cell(&genesis_tx, 0))],
+            eoa_added_cells: vec![address.clone()],
+            eoa_removed_cells: vec![(address.clone(), baseline_cell(&genesis_tx, 0))],
+            destructed_contracts: Vec::new(),
+        };
+        for (number, block_delta) in vec![genesis_delta, transfer_delta].into_iter().enumerate() {
+            let key_bytes = Bytes::from(&Key::BlockDelta(number as u64));
+            db.put(&key_bytes, &serialize(&block_delta).unwrap())
+                .unwrap();
+        }
+
+        // The client is required to load the lock args
+        assert!(init_or_migrate(&db, None).is_err());
+        init_or_migrate(&db, Some(&mut chain)).unwrap();
+        let version: value::SchemaVersion = db_get(&db, &Bytes::from(&Key::SchemaVersion))
+            .unwrap()
+            .unwrap();
+        assert_eq!(version.0, SCHEMA_VERSION);
+
+        let lock_args = H160::from_slice(&lock_args).unwrap();
+        let new_cell = |tx: &core::TransactionView, capacity: u64| {
+            let cell = baseline_cell(tx, 0);
+            assert_eq!(cell.capacity, capacity * ONE_CKB);
+            value::EoaLiveCell::new(
+                cell.tx_hash,
+                0,
+                lock_args.clone(),
+                cell.capacity,
+                cell.balance,
+            )
+        };
+        let transfer_cell = new_cell(&transfer_tx, 900);
+        let genesis_cell = new_cell(&genesis_tx, 1000);
+        assert!(db.get(&live_key).unwrap().is_none());
+        let live_key = Key::EoaLiveCell {
+            address: address.clone(),
+            out_point: Some(transfer_cell.out_point()),
+        };
+        let cell: value::EoaLiveCell = db_get(&db, &Bytes::from(&live_key)).unwrap().unwrap();
+        assert_eq!(cell, transfer_cell);
+        let owner_key = Key::EoaOwnerCell {
+            lock_args: lock_args.clone(),
+            address: Some(address.clone()),
+            out_point: Some(transfer_cell.out_point()),
+        };
+        let cell: value::EoaLiveCell = db_get(&db, &Bytes::from(&owner_key)).unwrap().unwrap();
+        assert_eq!(cell, transfer_cell);
+
+        let block_delta: value::BlockDelta = db_get(&db, &Bytes::from(&Key::BlockDelta(0)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            block_delta.eoa_added_cells,
+            vec![(address.clone(), genesis_cell.clone())]
+        );
+        let block_delta: value::BlockDelta = db_get(&db, &Bytes::from(&Key::BlockDelta(1)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            block_delta.eoa_added_cells,
+            vec![(address.clone(), transfer_cell)]
+        );
+        assert_eq!(block_delta.eoa_removed_cells, vec![(address, genesis_cell)]);
+        assert_eq!(block_delta.removed_cells.len(), 1);
+        // Open again
+        init_or_migrate(&db, None).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_not_baseline() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DB::open_default(dir.path()).unwrap();
+        let mut chain = MockChain::new(Vec::new());
+        let last = value::Last {
+            number: 0,
+            hash: chain.tip().hash().unpack(),
+        };
+        db.put(&Bytes::from(&Key::Last), &serialize(&last).unwrap())
+            .unwrap();
+        // The EoA cell with lock args is not the baseline layout
+        let cell = value::EoaLiveCell::new(H256::default(), 0, H160::default(), 100, 100);
+        let key = Key::EoaLiveCell {
+            address: H160::default(),
+            out_point: Some(cell.out_point()),
+        };
+        db.put(&Bytes::from(&key), &serialize(&cell).unwrap())
+            .unwrap();
+        let err = init_or_migrate(&db, Some(&mut chain)).unwrap_err();
+        assert!(
+            err.contains("not created by a released polyjuice"),
+            "{}",
+            err
+        );
+        assert!(db.get(&Bytes::from(&Key::SchemaVersion)).unwrap().is_none());
+    }
+}
@@ -1,10 +1,22 @@
 mod indexer;
 mod loader;
+mod migration;
+mod pending;
+mod prefetcher;
+mod processor;
 mod runner;
+mod snapshot;
+mod token;
 
-pub use indexer::Indexer;
+pub use indexer::{rollback_to, Indexer};
 pub use loader::Loader;
+pub use migration::init_or_migrate;
+pub use processor::{
+    create_processor, processor_names, register_processor, BlockProcessor, ProcessedBlock,
+    ProcessorFactory, MAX_PROCESSOR_NAME_LEN,
+};
 pub use runner::{CsalRunContext, Runner};
+pub use snapshot::{export_snapshot, import_snapshot};
 
 use crate::types::ContractAddress;
 use bincode::deserialize;
@@ -47,17 +59,72 @@ pub enum KeyType {
     ///      => (TransactionHash, OutputIndex)
     LockLiveCell = 0x05,
 
-    /// EoA account cells
-    ///   EthAddress => (TransactionHash, OutputIndex)
+    /// EoA account cells (one address may own many cells in Ethereum address mode)
+    ///   (EthAddress, OutPoint) => (TransactionHash, OutputIndex)
     EoaLiveCell = 0x06,
 
     /// Store meta info of a live cell outpoint
     ///   OutPoint => (BlockNumber, TransactionIndex)
     LiveCellMap = 0x07,
 
+    /// EoA account cells indexed by the owner (anyone-can-pay lock args)
+    ///   (LockArgs, EthAddress, OutPoint) => (TransactionHash, OutputIndex)
+    EoaOwnerCell = 0x08,
+
+    /// Value transfers of EoA account
+    ///   (EthAddress, BlockNumber, TransactionIndex, TransferIndex)
+    ///      => (TransactionHash, Counterparty, Value, Incoming)
+    EoaTransfer = 0x09,
+
+    /// Output and data of contract and EoA cells (consumed cells are removed
+    /// when out of the rollback window)
+    ///   OutPoint => (CellOutput, Data)
+    CellData = 0x0A,
+
+    /// The ABI uploaded for a contract (not chain data, kept on rollback)
+    ///   ContractAddress => value::ContractAbi
+    ContractAbi = 0x0B,
+
+    /// Token balances indexed by the token (token tracker)
+    ///   (TokenAddress, HolderAddress) => value::TokenBalance
+    TokenHolder = 0x0C,
+
+    /// Token balances indexed by the holder (token tracker)
+    ///   (HolderAddress, TokenAddress) => value::TokenBalance
+    HolderToken = 0x0D,
+
+    /// The owner of ERC721 token (token tracker)
+    ///   (TokenAddress, TokenId) => value::TokenOwner
+    TokenOwner = 0x0E,
+
+    /// Token transfers of an account (token tracker)
+    ///   (Address, BlockNumber, TransactionIndex, TransferIndex)
+    ///      => value::TokenTransfer
+    TokenTransfer = 0x0F,
+
+    /// The next nonce of the Ethereum transactions signed by the EoA owner
+    ///   LockArgs => value::EthNonce
+    EthNonce = 0x10,
+
+    /// The keys of external block processors
+    ///   (ProcessorName, ProcessorKey) => defined by the processor
+    Processor = 0xE0,
+
     /// Delta in the block (for rollback)
     ///   BlockNumber => value::BlockDelta
     BlockDelta = 0xF0,
+
+    /// Compacted BlockDelta out of the rollback window (for queries)
+    ///   BlockNumber => value::BlockSummary
+    BlockSummary = 0xF1,
+
+    /// The block where the indexer started from (checkpoint)
+    ///   "start_block" => (BlockNumber, BlockHash)
+    StartBlock = 0xFE,
+
+    /// The database schema version
+    ///   "schema_version" => value::SchemaVersion
+    SchemaVersion = 0xFF,
 }
 
 impl TryFrom<u8> for KeyType {
@@ -72,7 +139,20 @@ impl TryFrom<u8> for KeyType {
             0x05 => Ok(KeyType::LockLiveCell),
             0x06 => Ok(KeyType::EoaLiveCell),
             0x07 => Ok(KeyType::LiveCellMap),
+            0x08 => Ok(KeyType::EoaOwnerCell),
+            0x09 => Ok(KeyType::EoaTransfer),
+            0x0A => Ok(KeyType::CellData),
+            0x0B => Ok(KeyType::ContractAbi),
+            0x0C => Ok(KeyType::TokenHolder),
+            0x0D => Ok(KeyType::HolderToken),
+            0x0E => Ok(KeyType::TokenOwner),
+            0x0F => Ok(KeyType::TokenTransfer),
+            0x10 => Ok(KeyType::EthNonce),
+            0xE0 => Ok(KeyType::Processor),
             0xF0 => Ok(KeyType::BlockDelta),
+            0xF1 => Ok(KeyType::BlockSummary),
+            0xFE => Ok(KeyType::StartBlock),
+            0xFF => Ok(KeyType::SchemaVersion),
             _ => Err(format!("Invalid KeyType {}", value)),
         }
     }
@@ -107,9 +187,58 @@ pub enum Key {
         /// Output index in current transaction
         output_index: Option<u32>,
     },
-    EoaLiveCell(H160),
+    EoaLiveCell {
+        address: H160,
+        out_point: Option<packed::OutPoint>,
+    },
     LiveCellMap(packed::OutPoint),
+    EoaOwnerCell {
+        lock_args: H160,
+        address: Option<H160>,
+        /// Only serialized when `address` is given
+        out_point: Option<packed::OutPoint>,
+    },
+    EoaTransfer {
+        address: H160,
+        number: Option<BlockNumber>,
+        /// Transaction index in current block
+        tx_index: Option<u32>,
+        /// Transfer index in current transaction
+        transfer_index: Option<u32>,
+    },
+    CellData(packed::OutPoint),
+    ContractAbi(ContractAddress),
+    TokenHolder {
+        token: ContractAddress,
+        holder: Option<H160>,
+    },
+    HolderToken {
+        holder: H160,
+        token: Option<ContractAddress>,
+    },
+    TokenOwner {
+        token: ContractAddress,
+        token_id: H256,
+    },
+    TokenTransfer {
+        address: H160,
+        number: Option<BlockNumber>,
+        /// Transaction index in current block
+        tx_index: Option<u32>,
+        /// Transfer index in current transaction
+        transfer_index: Option<u32>,
+    },
+    EthNonce(H160),
+    /// Serialized as: name_length(u8) ++ name ++ key, the name is at most
+    /// `MAX_PROCESSOR_NAME_LEN` bytes
+    Processor {
+        name: String,
+        key: Bytes,
+    },
     BlockDelta(BlockNumber),
+    BlockSummary(BlockNumber),
+    StartBlock,
+    SchemaVersion,
 }
 
 impl From<&Key> for Bytes {
@@ -189,9 +318,12 @@ impl From<&Key> for Bytes {
                 serialize_output_pos(&mut bytes, *number, *tx_index, *output_index);
                 bytes.into()
             }
-            Key::EoaLiveCell(address) => {
+            Key::EoaLiveCell { address, out_point } => {
                 let mut bytes = vec![KeyType::EoaLiveCell as u8];
                 bytes.extend(address.as_bytes());
+                if let Some(out_point) = out_point {
+                    bytes.extend(out_point.as_slice());
+                }
                 bytes.into()
             }
             Key::LiveCellMap(out_point) => {
@@ -199,11 +331,103 @@ impl From<&Key> for Bytes {
                 bytes.extend(out_point.as_slice());
                 bytes.into()
             }
+            Key::EoaOwnerCell {
+                lock_args,
+                address,
+                out_point,
+            } => {
+                let mut bytes = vec![KeyType::EoaOwnerCell as u8];
+                bytes.extend(lock_args.as_bytes());
+                if let Some(address) = address {
+                    bytes.extend(address.as_bytes());
+                    if let Some(out_point) = out_point {
+                        bytes.extend(out_point.as_slice());
+                    }
+                }
+                bytes.into()
+            }
+            Key::EoaTransfer {
+                address,
+                number,
+                tx_index,
+                transfer_index,
+            } => {
+                let mut bytes = vec![KeyType::EoaTransfer as u8];
+                bytes.extend(address.as_bytes());
+                serialize_output_pos(&mut bytes, *number, *tx_index, *transfer_index);
+                bytes.into()
+            }
+            Key::CellData(out_point) => {
+                let mut bytes = vec![KeyType::CellData as u8];
+                bytes.extend(out_point.as_slice());
+                bytes.into()
+            }
+            Key::ContractAbi(address) => {
+                let mut bytes = vec![KeyType::ContractAbi as u8];
+                bytes.extend(address.0.as_bytes());
+                bytes.into()
+            }
+            Key::TokenHolder { token, holder } => {
+                let mut bytes = vec![KeyType::TokenHolder as u8];
+                bytes.extend(token.0.as_bytes());
+                if let Some(holder) = holder {
+                    bytes.extend(holder.as_bytes());
+                }
+                bytes.into()
+            }
+            Key::HolderToken { holder, token } => {
+                let mut bytes = vec![KeyType::HolderToken as u8];
+                bytes.extend(holder.as_bytes());
+                if let Some(token) = token {
+                    bytes.extend(token.0.as_bytes());
+                }
+                bytes.into()
+            }
+            Key::TokenOwner { token, token_id } => {
+                let mut bytes = vec![KeyType::TokenOwner as u8];
+                bytes.extend(token.0.as_bytes());
+                bytes.extend(token_id.as_bytes());
+                bytes.into()
+            }
+            Key::TokenTransfer {
+                address,
+                number,
+                tx_index,
+                transfer_index,
+            } => {
+                let mut bytes = vec![KeyType::TokenTransfer as u8];
+                bytes.extend(address.as_bytes());
+                serialize_output_pos(&mut bytes, *number, *tx_index, *transfer_index);
+                bytes.into()
+            }
+            Key::EthNonce(lock_args) => {
+                let mut bytes = vec![KeyType::EthNonce as u8];
+                bytes.extend(lock_args.as_bytes());
+                bytes.into()
+            }
+            Key::Processor { name, key } => {
+                assert!(
+                    name.len() <= MAX_PROCESSOR_NAME_LEN,
+                    "processor name too long: {}",
+                    name
+                );
+                let mut bytes = vec![KeyType::Processor as u8, name.len() as u8];
+                bytes.extend(name.as_bytes());
+                bytes.extend(key.as_ref());
+                bytes.into()
+            }
             Key::BlockDelta(number) => {
                 let mut bytes = vec![KeyType::BlockDelta as u8];
                 bytes.extend(&number.to_be_bytes());
                 bytes.into()
             }
+            Key::BlockSummary(number) => {
+                let mut bytes = vec![KeyType::BlockSummary as u8];
+                bytes.extend(&number.to_be_bytes());
+                bytes.into()
+            }
+            Key::StartBlock => vec![KeyType::StartBlock as u8].into(),
+            Key::SchemaVersion => vec![KeyType::SchemaVersion as u8].into(),
         }
     }
 }
@@ -304,27 +528,140 @@ impl TryFrom<&[u8]> for Key {
                 })
             }
             KeyType::EoaLiveCell => {
-                let eth_address =
-                    H160::from_slice(&content[0..20]).expect("deserialize eth address");
-                Ok(Key::EoaLiveCell(eth_address))
+                ensure_content_len(
+                    "EoaLiveCell",
+                    content,
+                    mem::size_of::<H160>() + packed::OutPoint::TOTAL_SIZE,
+                )?;
+                let address = H160::from_slice(&content[0..20]).expect("deserialize eth address");
+                let out_point = packed::OutPoint::from_slice(&content[20..]).unwrap();
+                Ok(Key::EoaLiveCell {
+                    address,
+                    out_point: Some(out_point),
+                })
             }
             KeyType::LiveCellMap => {
                 let out_point = packed::OutPoint::from_slice(content).unwrap();
                 Ok(Key::LiveCellMap(out_point))
             }
+            KeyType::EoaOwnerCell => {
+                ensure_content_len(
+                    "EoaOwnerCell",
+                    content,
+                    mem::size_of::<H160>() * 2 + packed::OutPoint::TOTAL_SIZE,
+                )?;
+                let lock_args = H160::from_slice(&content[0..20]).expect("deserialize lock args");
+                let address = H160::from_slice(&content[20..40]).expect("deserialize address");
+                let out_point = packed::OutPoint::from_slice(&content[40..]).unwrap();
+                Ok(Key::EoaOwnerCell {
+                    lock_args,
+                    address: Some(address),
+                    out_point: Some(out_point),
+                })
+            }
+            KeyType::EoaTransfer => {
+                let (address, number, tx_index, transfer_index) =
+                    deserialize_record_key("EoaTransfer", content)?;
+                Ok(Key::EoaTransfer {
+                    address: address.0,
+                    number: Some(number),
+                    tx_index: Some(tx_index),
+                    transfer_index: Some(transfer_index),
+                })
+            }
+            KeyType::CellData => {
+                let out_point = packed::OutPoint::from_slice(content)
+                    .map_err(|err| format!("Invalid Key::CellData content: {}", err))?;
+                Ok(Key::CellData(out_point))
+            }
+            KeyType::ContractAbi => {
+                ensure_content_len("ContractAbi", content, mem::size_of::<H160>())?;
+                let address = ContractAddress::from(
+                    H160::from_slice(&content[0..20]).expect("deserialize address"),
+                );
+                Ok(Key::ContractAbi(address))
+            }
+            KeyType::TokenHolder => {
+                ensure_content_len("TokenHolder", content, mem::size_of::<H160>() * 2)?;
+                let token = ContractAddress::from(
+                    H160::from_slice(&content[0..20]).expect("deserialize token address"),
+                );
+                let holder = H160::from_slice(&content[20..40]).expect("deserialize holder");
+                Ok(Key::TokenHolder {
+                    token,
+                    holder: Some(holder),
+                })
+            }
+            KeyType::HolderToken => {
+                ensure_content_len("HolderToken", content, mem::size_of::<H160>() * 2)?;
+                let holder = H160::from_slice(&content[0..20]).expect("deserialize holder");
+                let token = ContractAddress::from(
+                    H160::from_slice(&content[20..40]).expect("deserialize token address"),
+                );
+                Ok(Key::HolderToken {
+                    holder,
+                    token: Some(token),
+                })
+            }
+            KeyType::TokenOwner => {
+                ensure_content_len(
+                    "TokenOwner",
+                    content,
+                    mem::size_of::<H160>() + mem::size_of::<H256>(),
+                )?;
+                let token = ContractAddress::from(
+                    H160::from_slice(&content[0..20]).expect("deserialize token address"),
+                );
+                let token_id = H256::from_slice(&content[20..52]).expect("deserialize token id");
+                Ok(Key::TokenOwner { token, token_id })
+            }
+            KeyType::TokenTransfer => {
+                let (address, number, tx_index, transfer_index) =
+                    deserialize_record_key("TokenTransfer", content)?;
+                Ok(Key::TokenTransfer {
+                    address: address.0,
+                    number: Some(number),
+                    tx_index: Some(tx_index),
+                    transfer_index: Some(transfer_index),
+                })
+            }
+            KeyType::EthNonce => {
+                ensure_content_len("EthNonce", content, mem::size_of::<H160>())?;
+                let lock_args = H160::from_slice(content).expect("deserialize lock args");
+                Ok(Key::EthNonce(lock_args))
+            }
+            KeyType::Processor => {
+                let name_len = *content
+                    .first()
+                    .ok_or_else(|| String::from("Invalid Key::Processor content"))?
+                    as usize;
+                let name = content
+                    .get(1..1 + name_len)
+                    .and_then(|name| String::from_utf8(name.to_vec()).ok())
+                    .ok_or_else(|| String::from("Invalid Key::Processor name"))?;
+                let key = Bytes::from(content[1 + name_len..].to_vec());
+                Ok(Key::Processor { name, key })
+            }
             KeyType::BlockDelta => {
                 ensure_content_len("BlockDelta", content, mem::size_of::<BlockNumber>())?;
                 let number = deserialize_u64(&content[0..8]);
                 Ok(Key::BlockDelta(number))
             }
+            KeyType::BlockSummary => {
+                ensure_content_len("BlockSummary", content, mem::size_of::<BlockNumber>())?;
+                let number = deserialize_u64(&content[0..8]);
+                Ok(Key::BlockSummary(number))
+            }
+            KeyType::StartBlock => Ok(Key::StartBlock),
+            KeyType::SchemaVersion => Ok(Key::SchemaVersion),
         }
     }
 }
 
 pub mod value {
     use super::BlockNumber;
-    use crate::types::{ContractAddress, EoaAddress};
-    use ckb_types::{bytes::Bytes, packed, prelude::*, H160, H256};
+    use crate::types::{ContractAddress, EoaAddress, TokenKind};
+    use ckb_types::{bytes::Bytes, packed, prelude::*, H160, H256, U256};
     use serde::{Deserialize, Serialize};
 
     /// Deserialize/Serialize use bincode
@@ -337,6 +674,19 @@ pub mod value {
     #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct BlockMap(pub H256);
 
+    #[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+    pub struct SchemaVersion(pub u32);
+
+    /// The next nonce of the Ethereum transactions
+    #[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+    pub struct EthNonce(pub u64);
+
+    #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+    pub struct StartBlock {
+        pub number: BlockNumber,
+        pub hash: H256,
+    }
+
     #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct ContractChange {
         pub tx_hash: H256,
@@ -376,6 +726,8 @@ pub mod value {
     pub struct EoaLiveCell {
         pub tx_hash: H256,
         pub output_index: u32,
+        /// The anyone-can-pay lock args (owner of the cell)
+        pub lock_args: H160,
         capacity: u64,
         balance: u64,
     }
@@ -386,10 +738,17 @@ pub mod value {
         }
     }
     impl EoaLiveCell {
-        pub fn new(tx_hash: H256, output_index: u32, capacity: u64, balance: u64) -> EoaLiveCell {
+        pub fn new(
+            tx_hash: H256,
+            output_index: u32,
+            lock_args: H160,
+            capacity: u64,
+            balance: u64,
+        ) -> EoaLiveCell {
             EoaLiveCell {
                 tx_hash,
                 output_index,
+                lock_args,
                 capacity,
                 balance,
             }
@@ -406,6 +765,14 @@ pub mod value {
             }
             Ok(())
         }
+        /// The balance becomes occupied capacity (the cell data grows)
+        pub fn occupy_balance(&mut self, value: u64) -> Result<(), String> {
+            if self.balance < value {
+                return Err(format!("balance not enough: {} < {}", self.balance, value));
+            }
+            self.balance -= value;
+            Ok(())
+        }
         pub fn add_balance(&mut self, value: u64) {
             self.balance += value;
             self.capacity += value;
@@ -424,6 +791,89 @@ pub mod value {
         pub tx_index: u32,
     }
 
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct CellData {
+        /// The molecule serialized CellOutput
+        pub output: Bytes,
+        pub data: Bytes,
+    }
+
+    impl CellData {
+        pub fn new(output: &packed::CellOutput, data: Bytes) -> CellData {
+            CellData {
+                output: output.as_bytes(),
+                data,
+            }
+        }
+        pub fn output(&self) -> packed::CellOutput {
+            packed::CellOutput::from_slice(&self.output).expect("deserialize cell output")
+        }
+    }
+
+    /// The ABI JSON of a contract
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct ContractAbi(pub String);
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct EoaTransfer {
+        pub tx_hash: H256,
+        /// The sender (incoming) or the destination (outgoing) of the transfer
+        pub counterparty: H160,
+        pub value: u64,
+        pub incoming: bool,
+    }
+
+    #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+    pub struct TokenBalance {
+        pub kind: TokenKind,
+        /// Big endian U256
+        pub balance: H256,
+    }
+
+    impl TokenBalance {
+        pub fn new(kind: TokenKind, balance: &U256) -> TokenBalance {
+            TokenBalance {
+                kind,
+                balance: H256(balance.to_be_bytes()),
+            }
+        }
+        pub fn balance(&self) -> U256 {
+            U256::from_be_bytes(&self.balance.0)
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct TokenOwner(pub H160);
+
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct TokenTransfer {
+        pub token: ContractAddress,
+        pub kind: TokenKind,
+        pub tx_hash: H256,
+        /// The sender (incoming) or the destination (outgoing) of the transfer
+        pub counterparty: H160,
+        /// The amount (ERC20) or the token id (ERC721), big endian U256
+        pub value: H256,
+        pub incoming: bool,
+    }
+
+    /// The undo data of the token tracker
+    #[derive(Default, Debug, Clone, Deserialize, Serialize)]
+    pub struct TokenDelta {
+        /// The balances before current block, None if not exists
+        pub balances: Vec<(ContractAddress, H160, Option<TokenBalance>)>,
+        /// The owners of ERC721 tokens before current block
+        pub owners: Vec<(ContractAddress, H256, Option<H160>)>,
+        /// The accounts have token transfers in current block
+        pub transfers: Vec<H160>,
+    }
+
+    impl TokenDelta {
+        pub fn is_empty(&self) -> bool {
+            self.balances.is_empty() && self.owners.is_empty() && self.transfers.is_empty()
+        }
+    }
+
     /// For rollback
     #[derive(Debug, Clone, Deserialize, Serialize)]
     pub struct BlockDelta {
@@ -433,11 +883,24 @@ pub mod value {
         pub added_cells: Vec<(H256, u32, u32, LockLiveCell)>,
         /// (lock_hash, number, tx_index, output_index)
         pub removed_cells: Vec<(H256, u64, u32, u32, LockLiveCell)>,
-        /// (eoa_address)
-        pub eoa_added_cells: Vec<H160>,
+        /// (eoa_address, cell)
+        pub eoa_added_cells: Vec<(H160, EoaLiveCell)>,
         pub eoa_removed_cells: Vec<(H160, EoaLiveCell)>,
         /// The selfdestruct contracts in current block
         pub destructed_contracts: Vec<ContractAddress>,
+        /// The EoA accounts have transfers in current block
+        pub eoa_transfers: Vec<H160>,
+        /// The undo data of the block processors (name, undo)
+        pub processors: Vec<(String, Bytes)>,
+        /// The nonces before current block (lock_args, nonce), None if not exists
+        pub eth_nonces: Vec<(H160, Option<u64>)>,
+    }
+
+    /// The BlockDelta without rollback only data
+    #[derive(Debug, Clone, Deserialize, Serialize)]
+    pub struct BlockSummary {
+        /// If the bool field is true, the contract is created in this block
+        pub contracts: Vec<(ContractAddress, bool)>,
     }
 }
 
@@ -478,11 +941,50 @@ mod test {
                 output_index: Some(55),
             },
             Key::EoaLiveCell {
-                type_id_args: h256!("0x5342"),
-                anyone_can_pay_args: h160!("0xabcd"),
-            }
+                address: h160!("0x5342"),
+                out_point: Some(packed::OutPoint::new(h256!("0x44").pack(), 3)),
+            },
             Key::LiveCellMap(packed::OutPoint::default()),
+            Key::EoaOwnerCell {
+                lock_args: h160!("0xabcd"),
+                address: Some(h160!("0x5342")),
+                out_point: Some(packed::OutPoint::new(h256!("0x44").pack(), 3)),
+            },
+            Key::EoaTransfer {
+                address: h160!("0x5342"),
+                number: Some(777),
+                tx_index: Some(5),
+                transfer_index: Some(2),
+            },
+            Key::CellData(packed::OutPoint::default()),
+            Key::ContractAbi(ContractAddress(h160!("0xef"))),
+            Key::TokenHolder {
+                token: ContractAddress(h160!("0xef")),
+                holder: Some(h160!("0x5342")),
+            },
+            Key::HolderToken {
+                holder: h160!("0x5342"),
+                token: Some(ContractAddress(h160!("0xef"))),
+            },
+            Key::TokenOwner {
+                token: ContractAddress(h160!("0xef")),
+                token_id: h256!("0x07"),
+            },
+            Key::TokenTransfer {
+                address: h160!("0x5342"),
+                number: Some(888),
+                tx_index: Some(6),
+                transfer_index: Some(3),
+            },
+            Key::EthNonce(h160!("0xabcd")),
+            Key::Processor {
+                name: String::from("receipts"),
+                key: Bytes::from(vec![1, 2, 3]),
+            },
             Key::BlockDelta(8),
+            Key::BlockSummary(9),
+            Key::StartBlock,
+            Key::SchemaVersion,
         ] {
             let binary = Bytes::from(&key1);
             let key2 = Key::try_from(binary.as_ref()).unwrap();
@@ -0,0 +1,225 @@
+use ckb_types::{bytes::Bytes, packed, H160, H256};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use super::value;
+use crate::types::{ContractAddress, ContractChange, ContractMeta};
+
+/// Max built but not submitted transactions to remember
+const MAX_BUILT_TXS: usize = 1024;
+/// The pending transaction is dropped if not committed in this duration
+const PENDING_TX_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The state changes of a transaction built by polyjuice
+#[derive(Debug, Clone)]
+pub struct PendingTx {
+    pub tx_hash: H256,
+    /// The consumed cells (tx_hash, output_index)
+    pub inputs: Vec<(H256, u32)>,
+    /// The new state of the contracts
+    pub contracts: Vec<ContractChange>,
+    /// The contracts created by the transaction
+    pub metas: Vec<ContractMeta>,
+    /// The new EoA cells
+    pub eoa_cells: Vec<(H160, value::EoaLiveCell)>,
+    /// The next nonce of the sender (lock args) of the Ethereum transaction
+    pub eth_nonce: Option<(H160, u64)>,
+    /// The output and data of all the outputs
+    pub cells: HashMap<(H256, u32), (packed::CellOutput, Bytes)>,
+    /// When the transaction is built or submitted
+    pub timestamp: Instant,
+}
+
+/// The transactions built by polyjuice and submitted to CKB but not indexed
+/// yet, new transactions are built on top of their outputs.
+#[derive(Debug, Default)]
+pub struct PendingPool {
+    built: HashMap<H256, PendingTx>,
+    /// The submitted transactions, in order of submission
+    pending: Vec<PendingTx>,
+}
+
+impl PendingPool {
+    pub fn add_built(&mut self, tx: PendingTx) {
+        if self.built.len() >= MAX_BUILT_TXS {
+            if let Some(oldest_hash) = self
+                .built
+                .values()
+                .min_by_key(|tx| tx.timestamp)
+                .map(|tx| tx.tx_hash.clone())
+            {
+                self.built.remove(&oldest_hash);
+            }
+        }
+        self.built.insert(tx.tx_hash.clone(), tx);
+    }
+
+    /// Mark the built transaction as submitted, return false if the
+    /// transaction is not built by polyjuice.
+    pub fn submit(&mut self, tx_hash: &H256) -> bool {
+        if let Some(mut tx) = self.built.remove(tx_hash) {
+            tx.timestamp = Instant::now();
+            self.pending.push(tx);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The built transactions not submitted by `send_transaction` yet
+    pub fn built_tx_hashes(&self) -> Vec<H256> {
+        self.built.keys().cloned().collect()
+    }
+
+    pub fn remove_built(&mut self, tx_hash: &H256) {
+        self.built.remove(tx_hash);
+    }
+
+    pub fn is_pending(&self, tx_hash: &H256) -> bool {
+        self.pending.iter().any(|tx| &tx.tx_hash == tx_hash)
+    }
+
+    pub fn contract_change(&self, address: &ContractAddress) -> Option<&ContractChange> {
+        self.pending
+            .iter()
+            .rev()
+            .flat_map(|tx| tx.contracts.iter())
+            .find(|change| &change.address == address)
+    }
+
+    pub fn contract_meta(&self, address: &ContractAddress) -> Option<&ContractMeta> {
+        self.pending
+            .iter()
+            .flat_map(|tx| tx.metas.iter())
+            .find(|meta| &meta.address == address)
+    }
+
+    pub fn eoa_cell(&self, address: &H160) -> Option<&value::EoaLiveCell> {
+        self.pending
+            .iter()
+            .rev()
+            .flat_map(|tx| tx.eoa_cells.iter())
+            .find(|(eoa_address, _)| eoa_address == address)
+            .map(|(_, cell)| cell)
+    }
+
+    pub fn eth_nonce(&self, lock_args: &H160) -> Option<u64> {
+        self.pending
+            .iter()
+            .rev()
+            .filter_map(|tx| tx.eth_nonce.as_ref())
+            .find(|(sender, _)| sender == lock_args)
+            .map(|(_, nonce)| *nonce)
+    }
+
+    /// Check if the cell is consumed by a pending transaction
+    pub fn is_consumed(&self, tx_hash: &H256, output_index: u32) -> bool {
+        self.pending.iter().any(|tx| {
+            tx.inputs
+                .iter()
+                .any(|input| &input.0 == tx_hash && input.1 == output_index)
+        })
+    }
+
+    pub fn cell(&self, tx_hash: &H256, output_index: u32) -> Option<&(packed::CellOutput, Bytes)> {
+        self.pending
+            .iter()
+            .find(|tx| &tx.tx_hash == tx_hash)
+            .and_then(|tx| tx.cells.get(&(tx_hash.clone(), output_index)))
+    }
+
+    /// Remove the committed transactions, and drop the transactions conflict
+    /// with the consumed cells or timeout (with the transactions depend on
+    /// them).
+    pub fn remove_txs(&mut self, committed: &HashSet<H256>, consumed: &HashSet<(H256, u32)>) {
+        let now = Instant::now();
+        let mut dropped: HashSet<H256> = HashSet::default();
+        self.pending.retain(|tx| {
+            if committed.contains(&tx.tx_hash) {
+                log::debug!("Pending transaction committed: {:#x}", tx.tx_hash);
+                return false;
+            }
+            let is_dropped = now.duration_since(tx.timestamp) > PENDING_TX_TIMEOUT
+                || tx
+                    .inputs
+                    .iter()
+                    .any(|input| consumed.contains(input) || dropped.contains(&input.0));
+            if is_dropped {
+                log::info!("Drop pending transaction: {:#x}", tx.tx_hash);
+                dropped.insert(tx.tx_hash.clone());
+            }
+            !is_dropped
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::{h160, h256};
+
+    #[test]
+    fn test_pending_pool() {
+        let new_tx = |tx_hash: H256, inputs: Vec<(H256, u32)>, next_nonce: u64| PendingTx {
+            tx_hash: tx_hash.clone(),
+            inputs,
+            contracts: vec![ContractChange {
+                address: ContractAddress(h160!("0xab")),
+                tx_hash: tx_hash.clone(),
+                ..Default::default()
+            }],
+            metas: vec![ContractMeta {
+                address: ContractAddress(h160!("0xab")),
+                code: Bytes::from(vec![0x60]),
+                tx_hash,
+                output_index: 0,
+                balance: 0,
+                destructed: false,
+            }],
+            eoa_cells: Vec::new(),
+            eth_nonce: Some((h160!("0xe1"), next_nonce)),
+            cells: HashMap::default(),
+            timestamp: Instant::now(),
+        };
+        let tx1 = new_tx(h256!("0x1"), vec![(h256!("0x100"), 0)], 1);
+        let tx2 = new_tx(h256!("0x2"), vec![(h256!("0x1"), 0)], 2);
+        let tx3 = new_tx(h256!("0x3"), vec![(h256!("0x2"), 0)], 3);
+        let address = ContractAddress(h160!("0xab"));
+
+        let mut pool = PendingPool::default();
+        pool.add_built(tx1);
+        pool.add_built(tx2);
+        pool.add_built(tx3);
+        assert!(pool.contract_change(&address).is_none());
+        assert!(pool.contract_meta(&address).is_none());
+        assert!(pool.eth_nonce(&h160!("0xe1")).is_none());
+        assert_eq!(pool.built_tx_hashes().len(), 3);
+        assert!(pool.submit(&h256!("0x1")));
+        assert!(pool.submit(&h256!("0x2")));
+        assert!(pool.submit(&h256!("0x3")));
+        assert!(!pool.submit(&h256!("0x4")));
+        assert_eq!(
+            pool.contract_change(&address).map(|change| &change.tx_hash),
+            Some(&h256!("0x3"))
+        );
+        // The first created one
+        assert_eq!(
+            pool.contract_meta(&address).map(|meta| &meta.tx_hash),
+            Some(&h256!("0x1"))
+        );
+        // The nonce after the last pending transaction
+        assert_eq!(pool.eth_nonce(&h160!("0xe1")), Some(3));
+        assert!(pool.eth_nonce(&h160!("0xe2")).is_none());
+        assert!(pool.built_tx_hashes().is_empty());
+        assert!(pool.is_consumed(&h256!("0x100"), 0));
+        assert!(!pool.is_consumed(&h256!("0x3"), 0));
+
+        // tx1 committed, tx2 conflicted, tx3 depends on tx2
+        let committed = vec![h256!("0x1")].into_iter().collect();
+        let consumed = vec![(h256!("0x1"), 0)].into_iter().collect();
+        pool.remove_txs(&committed, &consumed);
+        assert!(pool.contract_change(&address).is_none());
+        assert!(pool.contract_meta(&address).is_none());
+        assert!(pool.eth_nonce(&h160!("0xe1")).is_none());
+    }
+}
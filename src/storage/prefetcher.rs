@@ -0,0 +1,141 @@
+use ckb_jsonrpc_types::BlockView;
+use std::collections::VecDeque;
+use std::thread::{self, JoinHandle};
+
+use crate::client::{ChainClient, RpcError};
+
+type FetchResult = Result<Option<BlockView>, RpcError>;
+type BatchResult = Result<Vec<Option<BlockView>>, RpcError>;
+
+/// Download the following blocks in batch requests while current block is
+/// processed. The blocks are always returned in order of block number.
+pub struct BlockPrefetcher {
+    client: Box<dyn ChainClient>,
+    /// Max blocks to download in one batch request
+    depth: usize,
+    /// Stop prefetching when the last fetched block not exists yet
+    reach_tip: bool,
+    /// The downloaded blocks
+    ready: VecDeque<(u64, BlockView)>,
+    /// The downloading batch (first block number, handle)
+    pending: Option<(u64, JoinHandle<BatchResult>)>,
+}
+
+impl BlockPrefetcher {
+    pub fn new(client: Box<dyn ChainClient>, depth: usize) -> BlockPrefetcher {
+        BlockPrefetcher {
+            client,
+            depth: std::cmp::max(depth, 1),
+            reach_tip: false,
+            ready: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    /// Get the block by number and start downloading the blocks after it.
+    pub fn get_block(&mut self, number: u64) -> FetchResult {
+        let expected_number = self
+            .ready
+            .front()
+            .map(|(ready_number, _)| *ready_number)
+            .or_else(|| self.pending.as_ref().map(|(start, _)| *start));
+        if expected_number.is_some() && expected_number != Some(number) {
+            self.reset();
+        }
+
+        if self.ready.is_empty() {
+            if self.pending.is_none() {
+                self.spawn_batch(number);
+            }
+            let (start, handle) = self.pending.take().expect("pending batch");
+            let result = handle
+                .join()
+                .map_err(|_| RpcError::Transport(format!("Prefetch block {} failed", number)))?;
+            let blocks = match result {
+                Ok(blocks) => blocks,
+                Err(err) => {
+                    self.reset();
+                    return Err(err);
+                }
+            };
+            self.reach_tip = false;
+            for (offset, block_opt) in blocks.into_iter().enumerate() {
+                if let Some(block) = block_opt {
+                    self.ready.push_back((start + offset as u64, block));
+                } else {
+                    // The blocks after it not exists either
+                    self.reach_tip = true;
+                    break;
+                }
+            }
+        }
+
+        match self.ready.pop_front() {
+            Some((_, block)) => {
+                if self.pending.is_none() && !self.reach_tip {
+                    let next_number = number + 1 + self.ready.len() as u64;
+                    self.spawn_batch(next_number);
+                }
+                Ok(Some(block))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Discard all downloaded blocks, the block after rollback must download again.
+    pub fn reset(&mut self) {
+        // The downloading thread is detached
+        self.ready.clear();
+        self.pending = None;
+    }
+
+    fn spawn_batch(&mut self, start: u64) {
+        let count = if self.reach_tip { 1 } else { self.depth as u64 };
+        let mut client = self.client.clone();
+        let handle =
+            thread::spawn(move || client.get_blocks_by_number((start..start + count).collect()));
+        self.pending = Some((start, handle));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_chain::MockChain;
+    use ckb_types::{prelude::*, H256};
+
+    #[test]
+    fn test_get_block() {
+        let chain = MockChain::new(Vec::new());
+        for _ in 0..5 {
+            chain.append_block(Vec::new());
+        }
+        let block_hash = |number: u64| -> H256 {
+            chain
+                .clone()
+                .get_header_by_number(number)
+                .unwrap()
+                .unwrap()
+                .hash
+        };
+        let mut prefetcher = BlockPrefetcher::new(Box::new(chain.clone()), 3);
+        for number in 0..=4 {
+            let block = prefetcher.get_block(number).unwrap().unwrap();
+            assert_eq!(block.header.hash, block_hash(number));
+        }
+
+        // Rollback to block 2, the prefetched blocks of the old fork are discarded
+        let old_hash = block_hash(3);
+        chain.reorg(2, vec![Vec::new(), Vec::new(), Vec::new()]);
+        assert_ne!(block_hash(3), old_hash);
+        for number in 3..=5 {
+            let block = prefetcher.get_block(number).unwrap().unwrap();
+            assert_eq!(block.header.hash, block_hash(number));
+        }
+
+        assert!(prefetcher.get_block(6).unwrap().is_none());
+        let block = chain.append_block(Vec::new());
+        let fetched = prefetcher.get_block(6).unwrap().unwrap();
+        assert_eq!(fetched.header.hash, block.hash().unpack());
+    }
+}
@@ -0,0 +1,173 @@
+use ckb_types::{bytes::Bytes, packed, H160, H256};
+use rocksdb::{WriteBatch, DB};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+use super::token::{TokenTracker, TOKEN_TRACKER_NAME};
+use super::value;
+use crate::types::{ContractAddress, ContractChange, ContractMeta};
+
+/// The max length of the processor names (the name length of
+/// `Key::Processor` is one byte)
+pub const MAX_PROCESSOR_NAME_LEN: usize = std::u8::MAX as usize;
+
+/// Create a new instance of the block processor
+pub type ProcessorFactory = fn() -> Box<dyn BlockProcessor>;
+
+lazy_static::lazy_static! {
+    /// The registered block processors (name => factory), the processors
+    /// shipped with polyjuice are registered by default.
+    static ref PROCESSORS: RwLock<BTreeMap<String, ProcessorFactory>> = {
+        let mut processors: BTreeMap<String, ProcessorFactory> = BTreeMap::new();
+        processors.insert(TOKEN_TRACKER_NAME.to_string(), || Box::new(TokenTracker));
+        RwLock::new(processors)
+    };
+}
+
+/// The changes of a block extracted by the indexer
+pub struct ProcessedBlock<'a> {
+    pub number: u64,
+    pub hash: &'a H256,
+    pub contract_changes: &'a [ContractChange],
+    pub created_contracts: &'a [ContractMeta],
+    pub destructed_contracts: &'a [ContractAddress],
+    /// The new EoA cells (out_point => (eoa_address, cell))
+    pub eoa_added_cells: &'a HashMap<packed::OutPoint, (H160, value::EoaLiveCell)>,
+    /// The consumed EoA cells (out_point => (eoa_address, cell))
+    pub eoa_removed_cells: &'a HashMap<packed::OutPoint, (H160, value::EoaLiveCell)>,
+}
+
+/// A custom index built from the processed blocks. The writes of a block are
+/// put into the same `WriteBatch` as the indexer, so they are committed (and
+/// rolled back) together with the block.
+///
+/// The keys should be `Key::Processor` with the processor's name to avoid
+/// conflicts with the indexer and other processors.
+pub trait BlockProcessor: Send {
+    /// The unique name of the processor, the undo data is stored by the name
+    fn name(&self) -> &str;
+
+    /// Put the writes of the block into the batch, return the undo data for
+    /// rollback (empty if nothing to undo).
+    fn process(
+        &mut self,
+        db: &DB,
+        batch: &mut WriteBatch,
+        block: &ProcessedBlock,
+    ) -> Result<Bytes, String>;
+
+    /// Revert the writes of the block (the last indexed block) by the undo
+    /// data returned by `process`.
+    fn rollback(
+        &self,
+        db: &DB,
+        batch: &mut WriteBatch,
+        number: u64,
+        undo: &[u8],
+    ) -> Result<(), String>;
+}
+
+/// Register a block processor, it can be enabled by `--processor <name>` of
+/// the command line (`cli::main`) after registered.
+pub fn register_processor(name: &str, factory: ProcessorFactory) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_PROCESSOR_NAME_LEN {
+        return Err(format!(
+            "Invalid block processor name length: {}, expected: 1 to {}",
+            name.len(),
+            MAX_PROCESSOR_NAME_LEN
+        ));
+    }
+    let mut processors = PROCESSORS.write().expect("lock processors");
+    if processors.contains_key(name) {
+        return Err(format!("Block processor already registered: {}", name));
+    }
+    processors.insert(name.to_string(), factory);
+    Ok(())
+}
+
+/// The names of the registered block processors
+pub fn processor_names() -> Vec<String> {
+    PROCESSORS
+        .read()
+        .expect("lock processors")
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Create a registered block processor by name
+pub fn create_processor(name: &str) -> Result<Box<dyn BlockProcessor>, String> {
+    let factory_opt = PROCESSORS
+        .read()
+        .expect("lock processors")
+        .get(name)
+        .cloned();
+    let factory = factory_opt.ok_or_else(|| {
+        format!(
+            "Unknown block processor: {}, available: {}",
+            name,
+            processor_names().join(", ")
+        )
+    })?;
+    let processor = factory();
+    // The undo data of the processor is stored by the name
+    if processor.name() != name {
+        return Err(format!(
+            "Block processor {} is registered as {}",
+            processor.name(),
+            name
+        ));
+    }
+    Ok(processor)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct NamedProcessor;
+
+    impl BlockProcessor for NamedProcessor {
+        fn name(&self) -> &str {
+            "named"
+        }
+        fn process(
+            &mut self,
+            _db: &DB,
+            _batch: &mut WriteBatch,
+            _block: &ProcessedBlock,
+        ) -> Result<Bytes, String> {
+            Ok(Bytes::new())
+        }
+        fn rollback(
+            &self,
+            _db: &DB,
+            _batch: &mut WriteBatch,
+            _number: u64,
+            _undo: &[u8],
+        ) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_register_processor() {
+        assert_eq!(
+            create_processor(TOKEN_TRACKER_NAME).unwrap().name(),
+            TOKEN_TRACKER_NAME
+        );
+        assert!(create_processor("named").is_err());
+
+        register_processor("named", || Box::new(NamedProcessor)).unwrap();
+        assert_eq!(create_processor("named").unwrap().name(), "named");
+        assert!(processor_names().contains(&String::from("named")));
+        assert!(register_processor("named", || Box::new(NamedProcessor)).is_err());
+        // The name of the created processor must be the registered name
+        register_processor("renamed", || Box::new(NamedProcessor)).unwrap();
+        assert!(create_processor("renamed").is_err());
+
+        let long_name = "n".repeat(MAX_PROCESSOR_NAME_LEN + 1);
+        assert!(register_processor(&long_name, || Box::new(NamedProcessor)).is_err());
+        assert!(register_processor("", || Box::new(NamedProcessor)).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use ckb_chain_spec::consensus::TYPE_ID_CODE_HASH;
 use ckb_hash::{blake2b_256, new_blake2b};
 use ckb_sdk::HumanCapacity;
 use ckb_simple_account_layer::{
@@ -20,18 +21,26 @@ use ckb_vm::{
 };
 use numext_fixed_uint::prelude::UintConvert;
 use sparse_merkle_tree::{default_store::DefaultStore, SparseMerkleTree, H256 as SmtH256};
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::error::Error as StdError;
+use std::fmt;
+use std::time::Instant;
 
+use super::pending::PendingTx;
 use super::{value, Loader};
+use crate::eth_tx::{eoa_cell_data, eoa_cell_nonce, EthTransaction};
+use crate::metrics;
 use crate::types::{
     h256_to_smth256, parse_log, smth256_to_h256, vm_load_data, vm_load_h160, vm_load_h256,
     vm_load_i32, vm_load_i64, vm_load_u256, vm_load_u32, vm_load_u8, CallKind, CallRecord,
-    Coinbase, ContractAddress, ContractCell, EoaAddress, Program, RunConfig, WitnessData,
-    ALWAYS_SUCCESS_SCRIPT, ONE_CKB, SIGHASH_CELL_DEP,
+    Coinbase, ContractAddress, ContractCell, ContractChange, ContractMeta, EoaAddress, Program,
+    RunConfig, WitnessData, ALWAYS_SUCCESS_SCRIPT, ONE_CKB, SIGHASH_CELL_DEP,
 };
 
+/// The transaction fee of merging EoA cells
+const MERGE_TX_FEE: u64 = ONE_CKB / 1000;
+
 pub struct Runner {
     pub loader: Loader,
     pub run_config: RunConfig,
@@ -42,12 +51,21 @@ impl Runner {
         Runner { loader, run_config }
     }
 
+    /// Build on the outputs of the pending transactions (for the
+    /// transactions will be sent)
+    pub fn with_pending(mut self) -> Runner {
+        self.loader.detect_submitted_txs();
+        self.loader.overlay_pending = true;
+        self
+    }
+
     pub fn static_call(
         &mut self,
         sender: H160,
         destination: ContractAddress,
         input: Bytes,
     ) -> Result<CsalRunContext, Box<dyn StdError>> {
+        let (sender, _) = self.loader.select_eoa_address(sender, 0)?;
         let meta = self.loader.load_contract_meta(destination.clone())?;
         if meta.destructed {
             return Err(format!("Contract already destructed: {:x}", destination.0).into());
@@ -62,15 +80,8 @@ impl Runner {
             false,
         );
 
-        let tip_block = self.loader.load_block(None)?;
-        let mut context =
-            CsalRunContext::new(self.loader.clone(), self.run_config.clone(), tip_block);
-        if let Err(err) = context.run(program) {
-            log::warn!("Error: {:?}", err);
-            return Err(err);
-        }
         // TODO: merge with context
-        Ok(context)
+        self.run_program("static_call", program, Vec::new())
     }
 
     pub fn call(
@@ -80,6 +91,7 @@ impl Runner {
         input: Bytes,
         value: u64,
     ) -> Result<CsalRunContext, Box<dyn StdError>> {
+        let (sender, merged_cells) = self.loader.select_eoa_address(sender, value)?;
         let meta = self.loader.load_contract_meta(destination.clone())?;
         if meta.destructed {
             return Err(format!("Contract already destructed: {:x}", destination.0).into());
@@ -93,15 +105,7 @@ impl Runner {
             value,
             false,
         );
-
-        let tip_block = self.loader.load_block(None)?;
-        let mut context =
-            CsalRunContext::new(self.loader.clone(), self.run_config.clone(), tip_block);
-        if let Err(err) = context.run(program) {
-            log::warn!("Error: {:?}", err);
-            return Err(err);
-        }
-        Ok(context)
+        self.run_program("call", program, merged_cells)
     }
 
     pub fn create(
@@ -110,18 +114,162 @@ impl Runner {
         code: Bytes,
         value: u64,
     ) -> Result<CsalRunContext, Box<dyn StdError>> {
+        let (sender, merged_cells) = self.loader.select_eoa_address(sender, value)?;
         let program = Program::new_create(EoaAddress(sender.clone()), sender, code, value);
+        self.run_program("create", program, merged_cells)
+    }
+
+    fn run_program(
+        &mut self,
+        kind: &str,
+        program: Program,
+        merged_cells: Vec<(H160, value::EoaLiveCell)>,
+    ) -> Result<CsalRunContext, Box<dyn StdError>> {
         let tip_block = self.loader.load_block(None)?;
         let mut context =
             CsalRunContext::new(self.loader.clone(), self.run_config.clone(), tip_block);
+        context.tx_origin_merged_cells = merged_cells;
         if let Err(err) = context.run(program) {
             log::warn!("Error: {:?}", err);
             return Err(err);
         }
+        metrics::VM_CYCLES
+            .with_label_values(&[kind])
+            .observe(context.cycles() as f64);
+        Ok(context)
+    }
+
+    /// Build a transaction to merge all the EoA cells owned by the lock args
+    /// into the one with largest balance, the other cells are destroyed. The
+    /// transaction must be signed by the owner (the witness lock of the first
+    /// input).
+    pub fn merge_eoa_cells(&mut self, lock_args: H160) -> Result<Transaction, Box<dyn StdError>> {
+        let mut cells = self.loader.load_eoa_owner_cells(lock_args.clone())?;
+        if cells.len() < 2 {
+            return Err(format!("No eoa cells to merge for owner: {:x}", lock_args).into());
+        }
+        // The cell with largest balance first
+        cells.sort_by_key(|(_, cell)| std::cmp::Reverse(cell.balance()));
+        let total_capacity: u64 = cells.iter().map(|(_, cell)| cell.capacity()).sum();
+        let (address, _) = &cells[0];
+        let mut live_cells = self.loader.load_live_cells(
+            cells
+                .iter()
+                .map(|(_, cell)| (cell.tx_hash.clone(), cell.output_index))
+                .collect(),
+        )?;
+        // Keep the largest nonce (Ethereum address mode) of the merged cells,
+        // the data of the target cell is kept when the nonces are the same.
+        let output_data = live_cells
+            .iter()
+            .rev()
+            .max_by_key(|(_, data)| eoa_cell_nonce(data).unwrap_or(0))
+            .map(|(_, data)| data.clone())
+            .unwrap_or_default();
+        let (output, _) = live_cells.remove(0);
+        let output_capacity = total_capacity
+            .checked_sub(MERGE_TX_FEE)
+            .ok_or_else(|| String::from("Not enough capacity for transaction fee"))?;
+        log::info!(
+            "merge {} eoa cells of owner {:x} into {:x}, capacity: {}",
+            cells.len(),
+            lock_args,
+            address,
+            HumanCapacity(output_capacity)
+        );
+        let inputs = cells
+            .iter()
+            .map(|(_, cell)| CellInput::new(cell.out_point(), 0))
+            .collect::<Vec<_>>();
+        let witness = WitnessArgs::new_builder()
+            .lock(Some(Bytes::from(vec![0u8; 65])).pack())
+            .build();
+        let tx = TransactionBuilder::default()
+            .cell_deps(
+                vec![
+                    SIGHASH_CELL_DEP.clone(),
+                    self.run_config.eoa_lock_dep.clone(),
+                ]
+                .pack(),
+            )
+            .inputs(inputs.pack())
+            .output(output.as_builder().capacity(output_capacity.pack()).build())
+            .output_data(output_data.pack())
+            .witness(witness.as_bytes().pack())
+            .build();
+        Ok(tx.data())
+    }
+
+    /// Run a signed Ethereum transaction, the sender is recovered from the
+    /// signature and the EoA cell is selected by the lock args.
+    pub fn eth_transaction(
+        &mut self,
+        eth_tx: EthTransaction,
+    ) -> Result<CsalRunContext, Box<dyn StdError>> {
+        if eth_tx.chain_id != self.run_config.chain_id {
+            return Err(format!(
+                "Invalid chain id: {}, expected: {}",
+                eth_tx.chain_id, self.run_config.chain_id
+            )
+            .into());
+        }
+        let pubkey = eth_tx.recover_pubkey()?;
+        let sender = self.run_config.address_mode.lock_args(&pubkey);
+        log::debug!(
+            "eth transaction sender: {:x}, nonce: {}",
+            sender,
+            eth_tx.nonce
+        );
+        let expected_nonce = self.loader.load_eth_nonce(&sender)?;
+        if eth_tx.nonce != expected_nonce {
+            return Err(format!(
+                "Invalid nonce of {:x}: {}, expected: {}",
+                sender, eth_tx.nonce, expected_nonce
+            )
+            .into());
+        }
+        let mut context = match eth_tx.to.clone() {
+            Some(to) => self.call(
+                sender,
+                ContractAddress(to),
+                eth_tx.data.clone(),
+                eth_tx.value,
+            )?,
+            None => self.create(sender, eth_tx.data.clone(), eth_tx.value)?,
+        };
+        context.eth_tx = Some(eth_tx);
         Ok(context)
     }
 }
 
+/// The errors of value transfers
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TransferError {
+    /// The sender is not an account of current transaction
+    SenderNotFound(H160),
+    /// The recipient is neither a contract nor an EoA account, and creating
+    /// EoA cell for unknown recipient is disabled
+    RecipientNotFound(H160),
+    /// The sender don't have enough balance
+    InsufficientBalance(String),
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransferError::SenderNotFound(address) => {
+                write!(f, "Transfer sender account not found: {:x}", address)
+            }
+            TransferError::RecipientNotFound(address) => {
+                write!(f, "Transfer recipient account not found: {:x}", address)
+            }
+            TransferError::InsufficientBalance(err) => write!(f, "Transfer failed: {}", err),
+        }
+    }
+}
+
+impl StdError for TransferError {}
+
 pub struct ContractInfo {
     pub address: ContractAddress,
     pub tree: SparseMerkleTree<CkbBlake2bHasher, SmtH256, DefaultStore<SmtH256>>,
@@ -309,8 +457,13 @@ impl ContractInfo {
             })
     }
 
-    // Serialize all call records to WitnessArgs
-    pub fn witness_data(&self, block_opt: Option<&BlockView>) -> WitnessArgs {
+    // Serialize all call records to WitnessArgs, the signature (of the
+    // Ethereum transaction) is put in the first program
+    pub fn witness_data(
+        &self,
+        block_opt: Option<&BlockView>,
+        signature: Option<Bytes>,
+    ) -> WitnessArgs {
         let mut witness_data_vec: Vec<WitnessData> = self
             .execute_records
             .iter()
@@ -321,6 +474,9 @@ impl ContractInfo {
             witness_data_vec[self.execute_records.len() - 1].selfdestruct =
                 self.selfdestruct.clone();
         }
+        if let (Some(signature), Some(witness_data)) = (signature, witness_data_vec.first_mut()) {
+            witness_data.signature = signature;
+        }
         for witness_data in &witness_data_vec {
             let program = &witness_data.program;
             log::debug!("[address]    : {:x}", self.address.0);
@@ -390,17 +546,28 @@ pub struct CsalRunContext {
     pub tx_origin: EoaAddress,
     pub tx_origin_cell: value::EoaLiveCell,
     pub tx_origin_output: (CellOutput, Bytes),
+    // The other EoA cells of the tx_origin owner merged into the tx_origin
+    // output (see `Loader::select_eoa_address`)
+    pub tx_origin_merged_cells: Vec<(H160, value::EoaLiveCell)>,
     pub other_eoa_cells: BTreeMap<H160, value::EoaLiveCell>,
     pub other_eoa_outputs: BTreeMap<H160, (CellOutput, Bytes)>,
+    // The unknown recipients get new EoA cells: address => received value
+    pub new_eoa_accounts: BTreeMap<H160, u64>,
     // First contract input cell (when kind.is_call())
     pub first_contract_input: Option<ContractInput>,
     // The entrance program
     pub entrance_program: Option<Program>,
+    // The Ethereum transaction of the entrance program
+    pub eth_tx: Option<EthTransaction>,
     // Current running contract
     contract_index: usize,
     contracts: Vec<(ContractAddress, ContractInfo)>,
     state_changed: bool,
     error_message: Option<String>,
+    // Total cycles of all the executed programs
+    cycles: u64,
+    // Cycles of the running program (at the last syscall)
+    vm_cycles: u64,
 }
 
 impl CsalRunContext {
@@ -413,17 +580,28 @@ impl CsalRunContext {
             // placeholder
             tx_origin: Default::default(),
             // placeholder
-            tx_origin_cell: value::EoaLiveCell::new(Default::default(), 0, 0, 0),
+            tx_origin_cell: value::EoaLiveCell::new(
+                Default::default(),
+                0,
+                Default::default(),
+                0,
+                0,
+            ),
             // placeholder
             tx_origin_output: (CellOutput::default(), Bytes::default()),
+            tx_origin_merged_cells: Vec::new(),
             other_eoa_cells: Default::default(),
             other_eoa_outputs: Default::default(),
+            new_eoa_accounts: Default::default(),
             first_contract_input: None,
             entrance_program: None,
+            eth_tx: None,
             contract_index: 0,
             contracts: Vec::new(),
             state_changed: false,
             error_message: None,
+            cycles: 0,
+            vm_cycles: 0,
         }
     }
 
@@ -480,6 +658,11 @@ impl CsalRunContext {
             inputs.push(self.tx_origin_input());
         }
         inputs.extend(other_eoa_inputs);
+        inputs.extend(
+            self.tx_origin_merged_cells
+                .iter()
+                .map(|(_, cell)| CellInput::new(cell.out_point(), 0)),
+        );
         // calculate capacity
         let tx_origin_capacity: u64 = self.tx_origin_output.0.capacity().unpack();
         let other_total_capacity: u64 = self
@@ -498,78 +681,40 @@ impl CsalRunContext {
             HumanCapacity(other_total_capacity),
             HumanCapacity(other_eoa_total_capacity),
         );
-        let total_input_capacity =
-            tx_origin_capacity + other_total_capacity + other_eoa_total_capacity;
+        let merged_total_capacity: u64 = self
+            .tx_origin_merged_cells
+            .iter()
+            .map(|(_, cell)| cell.capacity())
+            .sum();
+        let total_input_capacity = tx_origin_capacity
+            + other_total_capacity
+            + other_eoa_total_capacity
+            + merged_total_capacity;
 
         // Collect outputs/outputs_data
         let mut other_eoa_cells = self.other_eoa_cells.clone();
         let mut other_eoa_outputs = self.other_eoa_outputs.clone();
-        let (mut outputs, mut outputs_data): (Vec<CellOutput>, Vec<Bytes>) = self
-            .contracts
-            .iter()
-            .map(|(address, info)| {
-                if let Some((address, _capacity_delta)) = info.selfdestruct.as_ref() {
-                    // FIXME: selfdestruct beneficiary is a contract account
-                    let cell = other_eoa_cells
-                        .remove(address)
-                        .expect("other eoa cell must exists");
-                    let (output, output_data) = other_eoa_outputs
-                        .remove(address)
-                        .expect("other eoa output must exists");
-                    let final_output = output.as_builder().capacity(cell.capacity().pack()).build();
-                    (final_output, output_data)
-                } else {
-                    let output = info
-                        .input
-                        .as_ref()
-                        .map(|input| {
-                            // Call contract
-                            input
-                                .output
-                                .clone()
-                                .as_builder()
-                                .capacity(info.capacity().pack())
-                                .build()
-                        })
-                        .unwrap_or_else(|| {
-                            // Create contract
-                            let contract_lock_script = ALWAYS_SUCCESS_SCRIPT.clone();
-                            let contract_type_script = self
-                                .run_config
-                                .type_script
-                                .clone()
-                                .as_builder()
-                                .args(Bytes::from(address.0.as_bytes().to_vec()).pack())
-                                .build();
-                            let output = CellOutput::new_builder()
-                                .type_(
-                                    ScriptOpt::new_builder()
-                                        .set(Some(contract_type_script))
-                                        .build(),
-                                )
-                                .lock(contract_lock_script)
-                                .capacity(0.pack())
-                                .build();
-                            let data_capacity = Capacity::shannons((32 + 32) * ONE_CKB);
-                            let occupied_capacity: u64 = output
-                                .occupied_capacity(data_capacity)
-                                .expect("capacity")
-                                .as_u64();
-                            let output_capacity = occupied_capacity + info.balance as u64;
-                            output.as_builder().capacity(output_capacity.pack()).build()
-                        });
-                    (output, info.output_data())
-                }
-            })
-            .filter(|(output, _)| {
-                let capacity: u64 = output.capacity().unpack();
-                log::debug!(
-                    "[selfdestruct or contract's output.capacity]: {}",
-                    HumanCapacity(capacity)
-                );
-                true
-            })
-            .unzip();
+        let (mut outputs, mut outputs_data, contract_output_indices) =
+            self.contract_outputs(&mut other_eoa_cells, &mut other_eoa_outputs);
+        // The nonce of the signed Ethereum transaction is verified by the
+        // validator, the next nonce is saved in the tx_origin output data.
+        if let Some(eth_tx) = self.eth_tx.as_ref() {
+            let nonce = eoa_cell_nonce(&self.tx_origin_output.1)?;
+            if eth_tx.nonce != nonce {
+                return Err(format!(
+                    "Invalid nonce of {:x}: {}, expected: {}",
+                    self.tx_origin_cell.lock_args, eth_tx.nonce, nonce
+                )
+                .into());
+            }
+            let output_data = eoa_cell_data(nonce + 1);
+            let data_size = output_data
+                .len()
+                .saturating_sub(self.tx_origin_output.1.len());
+            self.tx_origin_cell
+                .occupy_balance(data_size as u64 * ONE_CKB)?;
+            self.tx_origin_output.1 = output_data;
+        }
         // handle tx_origin output cell
         let create_contracts_count = self
             .contracts
@@ -581,7 +726,15 @@ impl CsalRunContext {
             "contract_cell_min_capacity(): {}",
             HumanCapacity(contract_cell_min_capacity())
         );
-        let addition_capacity = tx_fee + create_contracts_count * contract_cell_min_capacity();
+        // The occupied capacity of the new EoA cells
+        let new_eoa_occupied_capacity: u64 = self
+            .new_eoa_accounts
+            .keys()
+            .map(|address| Unpack::<u64>::unpack(&self.new_eoa_output(address, 0, 0).capacity()))
+            .sum();
+        let addition_capacity = tx_fee
+            + create_contracts_count * contract_cell_min_capacity()
+            + new_eoa_occupied_capacity;
         if self.tx_origin_cell.balance() < addition_capacity {
             return Err(format!(
                 "tx_origin don't have enough capacity for transaction, {} < {}",
@@ -620,6 +773,17 @@ impl CsalRunContext {
             outputs.push(final_output);
             outputs_data.push(output_data.clone());
         }
+        // handle new eoa output cells
+        for (address, balance) in &self.new_eoa_accounts {
+            let output = self.new_eoa_output(address, outputs.len() as u64, *balance);
+            log::debug!(
+                "[new eoa {:x} output.capacity]: {}",
+                address,
+                HumanCapacity(output.capacity().unpack())
+            );
+            outputs.push(output);
+            outputs_data.push(Bytes::default());
+        }
         let total_output_capacity: u64 = outputs
             .iter()
             .map(|output| Unpack::<u64>::unpack(&output.capacity()))
@@ -658,17 +822,21 @@ impl CsalRunContext {
         } else {
             0
         };
-        let mut witnesses_data = vec![(None, None); self.contracts.len()];
-        for (output_index, (_, info)) in self.contracts.iter().enumerate() {
+        let mut witnesses_data =
+            vec![(None, None); std::cmp::max(self.contracts.len(), outputs.len())];
+        for (contract_index, (_, info)) in self.contracts.iter().enumerate() {
             // entrance contract
-            let block_opt = if output_index == 0 {
-                Some(&self.tip_block)
+            let (block_opt, signature) = if contract_index == 0 {
+                let signature = self.eth_tx.as_ref().map(|eth_tx| eth_tx.signature.clone());
+                (Some(&self.tip_block), signature)
             } else {
-                None
+                (None, None)
             };
-            let witness_data = info.witness_data(block_opt);
+            let witness_data = info.witness_data(block_opt, signature);
             if info.is_create() {
-                witnesses_data[output_index].1 = Some(witness_data);
+                if let Some(output_index) = contract_output_indices[contract_index] {
+                    witnesses_data[output_index].1 = Some(witness_data);
+                }
             } else {
                 witnesses_data[input_index].0 = Some(witness_data);
                 input_index += 1;
@@ -689,6 +857,23 @@ impl CsalRunContext {
             }
             witnesses.push(witness_builder.build().as_bytes());
         }
+        // The signed Ethereum transaction authorizes the tx_origin cell, it is
+        // put in the lock field of the tx_origin input's witness.
+        if let Some(eth_tx) = self.eth_tx.as_ref() {
+            let tx_origin_index = inputs
+                .iter()
+                .position(|input| input.as_slice() == self.tx_origin_input().as_slice())
+                .expect("tx_origin input");
+            while witnesses.len() <= tx_origin_index {
+                witnesses.push(WitnessArgs::default().as_bytes());
+            }
+            let witness = WitnessArgs::from_slice(&witnesses[tx_origin_index])
+                .map_err(|err| err.to_string())?
+                .as_builder()
+                .lock(Some(eth_tx.raw.clone()).pack())
+                .build();
+            witnesses[tx_origin_index] = witness.as_bytes();
+        }
 
         let tx = TransactionBuilder::default()
             .header_deps(header_deps.into_iter().map(|hash| hash.pack()))
@@ -701,6 +886,231 @@ impl CsalRunContext {
         Ok(tx.data())
     }
 
+    /// The outputs of the contracts, the output of the other EoA account
+    /// beneficiary replaces the output of the selfdestructed contract. Returns
+    /// (outputs, outputs_data, output index of every contract).
+    fn contract_outputs(
+        &self,
+        other_eoa_cells: &mut BTreeMap<H160, value::EoaLiveCell>,
+        other_eoa_outputs: &mut BTreeMap<H160, (CellOutput, Bytes)>,
+    ) -> (Vec<CellOutput>, Vec<Bytes>, Vec<Option<usize>>) {
+        let mut outputs: Vec<CellOutput> = Vec::new();
+        let mut outputs_data: Vec<Bytes> = Vec::new();
+        // The output index of every contract, None if the contract have no output
+        let mut contract_output_indices: Vec<Option<usize>> = Vec::new();
+        for (address, info) in &self.contracts {
+            let (output, output_data) = if let Some((beneficiary, _)) = info.selfdestruct.as_ref() {
+                // The beneficiary is a contract account or tx_origin (also when
+                // selfdestruct to the contract itself), the capacity already
+                // added to it's balance and it has it's own output.
+                let cell = match other_eoa_cells.remove(beneficiary) {
+                    Some(cell) => cell,
+                    None => {
+                        contract_output_indices.push(None);
+                        continue;
+                    }
+                };
+                contract_output_indices.push(Some(outputs.len()));
+                let (output, output_data) = other_eoa_outputs
+                    .remove(beneficiary)
+                    .expect("other eoa output must exists");
+                let final_output = output.as_builder().capacity(cell.capacity().pack()).build();
+                (final_output, output_data)
+            } else {
+                contract_output_indices.push(Some(outputs.len()));
+                let output = info
+                    .input
+                    .as_ref()
+                    .map(|input| {
+                        // Call contract
+                        input
+                            .output
+                            .clone()
+                            .as_builder()
+                            .capacity(info.capacity().pack())
+                            .build()
+                    })
+                    .unwrap_or_else(|| {
+                        // Create contract
+                        let contract_lock_script = ALWAYS_SUCCESS_SCRIPT.clone();
+                        let contract_type_script = self
+                            .run_config
+                            .type_script
+                            .clone()
+                            .as_builder()
+                            .args(Bytes::from(address.0.as_bytes().to_vec()).pack())
+                            .build();
+                        let output = CellOutput::new_builder()
+                            .type_(
+                                ScriptOpt::new_builder()
+                                    .set(Some(contract_type_script))
+                                    .build(),
+                            )
+                            .lock(contract_lock_script)
+                            .capacity(0.pack())
+                            .build();
+                        let data_capacity = Capacity::shannons((32 + 32) * ONE_CKB);
+                        let occupied_capacity: u64 = output
+                            .occupied_capacity(data_capacity)
+                            .expect("capacity")
+                            .as_u64();
+                        let output_capacity = occupied_capacity + info.balance as u64;
+                        output.as_builder().capacity(output_capacity.pack()).build()
+                    });
+                (output, info.output_data())
+            };
+            let capacity: u64 = output.capacity().unpack();
+            log::debug!(
+                "[selfdestruct or contract's output.capacity]: {}",
+                HumanCapacity(capacity)
+            );
+            outputs.push(output);
+            outputs_data.push(output_data);
+        }
+        (outputs, outputs_data, contract_output_indices)
+    }
+
+    /// The state changes of the built transaction (call `build_tx` first)
+    pub fn pending_tx(&self, tx: &Transaction) -> PendingTx {
+        let tx_hash: H256 = tx.calc_tx_hash().unpack();
+        let raw_tx = tx.raw();
+        let inputs = raw_tx
+            .inputs()
+            .into_iter()
+            .map(|input| {
+                let out_point = input.previous_output();
+                (out_point.tx_hash().unpack(), out_point.index().unpack())
+            })
+            .collect();
+        let outputs = raw_tx.outputs().into_iter().collect::<Vec<_>>();
+        let cells = outputs
+            .iter()
+            .zip(raw_tx.outputs_data().into_iter())
+            .enumerate()
+            .map(|(index, (output, data))| {
+                (
+                    (tx_hash.clone(), index as u32),
+                    (output.clone(), data.raw_data()),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        // Find the output have same lock and type script
+        let find_output = |expected: &CellOutput| {
+            outputs.iter().position(|output| {
+                output.lock().as_slice() == expected.lock().as_slice()
+                    && output.type_().as_slice() == expected.type_().as_slice()
+            })
+        };
+
+        let type_code_hash = self.run_config.type_script.code_hash();
+        let contracts = self
+            .contracts
+            .iter()
+            .filter(|(_, info)| info.selfdestruct.is_none())
+            .filter_map(|(address, info)| {
+                let output_index = outputs.iter().position(|output| {
+                    output
+                        .type_()
+                        .to_opt()
+                        .map(|script| {
+                            script.code_hash().as_slice() == type_code_hash.as_slice()
+                                && script.args().raw_data().as_ref() == address.0.as_bytes()
+                        })
+                        .unwrap_or(false)
+                })?;
+                let new_storage = info
+                    .tree
+                    .store()
+                    .leaves_map()
+                    .values()
+                    .map(|leaf| (smth256_to_h256(&leaf.key), smth256_to_h256(&leaf.value)))
+                    .collect();
+                Some(ContractChange {
+                    tx_origin: self.tx_origin.clone(),
+                    address: address.clone(),
+                    output_index: output_index as u32,
+                    tx_hash: tx_hash.clone(),
+                    new_storage,
+                    capacity: outputs[output_index].capacity().unpack(),
+                    balance: info.balance,
+                    is_create: info.is_create(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let eoa_cells = Some((
+            &self.tx_origin.0,
+            &self.tx_origin_cell,
+            &self.tx_origin_output,
+        ))
+        .into_iter()
+        .chain(self.other_eoa_cells.iter().filter_map(|(address, cell)| {
+            self.other_eoa_outputs
+                .get(address)
+                .map(|output| (address, cell, output))
+        }))
+        .filter_map(|(address, cell, (output, _))| {
+            let output_index = find_output(output)?;
+            let new_cell = value::EoaLiveCell::new(
+                tx_hash.clone(),
+                output_index as u32,
+                cell.lock_args.clone(),
+                cell.capacity(),
+                cell.balance(),
+            );
+            Some((address.clone(), new_cell))
+        })
+        .chain(
+            self.new_eoa_accounts
+                .iter()
+                .filter_map(|(address, balance)| {
+                    let eoa_lock_args = Bytes::from(address.as_bytes().to_vec());
+                    let output_index = outputs.iter().position(|output| {
+                        output.lock().code_hash() == self.run_config.eoa_lock_script.code_hash()
+                            && output.lock().args().raw_data() == eoa_lock_args
+                    })?;
+                    let new_cell = value::EoaLiveCell::new(
+                        tx_hash.clone(),
+                        output_index as u32,
+                        address.clone(),
+                        outputs[output_index].capacity().unpack(),
+                        *balance,
+                    );
+                    Some((address.clone(), new_cell))
+                }),
+        )
+        .collect();
+        let metas = contracts
+            .iter()
+            .filter(|change| change.is_create)
+            .filter_map(|change| {
+                let info = self.get_contract_info(&change.address)?;
+                Some(ContractMeta {
+                    address: change.address.clone(),
+                    code: info.code.clone(),
+                    tx_hash: tx_hash.clone(),
+                    output_index: change.output_index,
+                    balance: change.balance,
+                    destructed: false,
+                })
+            })
+            .collect();
+        let eth_nonce = self
+            .eth_tx
+            .as_ref()
+            .map(|eth_tx| (self.tx_origin_cell.lock_args.clone(), eth_tx.nonce + 1));
+        PendingTx {
+            tx_hash,
+            inputs,
+            contracts,
+            metas,
+            eoa_cells,
+            eth_nonce,
+            cells,
+            timestamp: Instant::now(),
+        }
+    }
+
     // Add CALLCODE/DELEGATECALL program for callee
     pub fn add_special_call(&mut self, program: Program) -> Result<(), Box<dyn StdError>> {
         let info_address = ContractAddress(program.destination.clone());
@@ -712,12 +1122,9 @@ impl CsalRunContext {
                 Ok((input_opt, tree, info.balance))
             })
             .unwrap_or_else(|| {
-                let change = self.loader.load_latest_contract_change(
-                    info_address.clone(),
-                    None,
-                    false,
-                    false,
-                )?;
+                let change = self
+                    .loader
+                    .load_latest_contract_state(info_address.clone())?;
                 let (output, data) = self
                     .loader
                     .load_contract_live_cell(change.tx_hash.clone(), change.output_index)?;
@@ -768,12 +1175,9 @@ impl CsalRunContext {
                     Ok((input_opt, tree, info.balance))
                 })
                 .unwrap_or_else(|| {
-                    let change = self.loader.load_latest_contract_change(
-                        info_address.clone(),
-                        None,
-                        false,
-                        false,
-                    )?;
+                    let change = self
+                        .loader
+                        .load_latest_contract_state(info_address.clone())?;
                     let (output, data) = self
                         .loader
                         .load_contract_live_cell(change.tx_hash.clone(), change.output_index)?;
@@ -812,7 +1216,11 @@ impl CsalRunContext {
         let saved_execute_index = self.current_contract_info().execute_index;
         let config = Config::from(&self.run_config);
         if program.is_create() || !program.input.is_empty() {
-            let _result = match run_with_context(&config, &new_tree, &program_data, self) {
+            let saved_vm_cycles = std::mem::replace(&mut self.vm_cycles, 0);
+            let result = run_with_context(&config, &new_tree, &program_data, self);
+            self.cycles += self.vm_cycles;
+            self.vm_cycles = saved_vm_cycles;
+            let _result = match result {
                 Ok(result) => result,
                 Err(err) => {
                     log::warn!("Error: {:?}", err);
@@ -849,8 +1257,7 @@ impl CsalRunContext {
         Ok(())
     }
 
-    pub fn handle_transfer(&mut self, program: &Program) -> Result<(), String> {
-        let destination = self.destination(&program, self.contracts.len() as u64);
+    pub fn handle_transfer(&mut self, program: &Program) -> Result<(), TransferError> {
         // value transfer
         log::debug!(
             "handle_transfer() tx_origin: {:x}, program.sender: {:x}",
@@ -864,12 +1271,15 @@ impl CsalRunContext {
 
         if self.tx_origin.0 == program.sender {
             // Transfer from EoA account (tx_origin)
-            self.tx_origin_cell.sub_balance(program.value as u64)?;
+            self.tx_origin_cell
+                .sub_balance(program.value as u64)
+                .map_err(TransferError::InsufficientBalance)?;
         } else {
             // Transfer from contract account
             self.get_contract_info_mut(&ContractAddress(program.sender.clone()))
-                .ok_or_else(|| format!("sender {:x} must exists", program.sender))?
-                .sub_balance(program.value)?;
+                .ok_or_else(|| TransferError::SenderNotFound(program.sender.clone()))?
+                .sub_balance(program.value)
+                .map_err(TransferError::InsufficientBalance)?;
         }
         // TODO: how to handle special call (CALLCODE/DELEGATECALL)?
         if let Some(dest_info) =
@@ -884,50 +1294,153 @@ impl CsalRunContext {
                 dest_info.balance + program.value
             );
             dest_info.add_balance(program.value);
+        } else if let Ok(cell_mut) = self.get_eoa_cell_mut(&program.destination) {
+            // Transfer to EoA account
+            log::debug!(
+                "add {} wei to eoa account {:x}",
+                program.value,
+                program.destination
+            );
+            cell_mut.add_balance(program.value);
+        } else if self.run_config.create_eoa_recipient {
+            // Transfer to a new EoA cell, the occupied capacity is paid by tx_origin
+            log::debug!(
+                "add {} wei to new eoa account {:x}",
+                program.value,
+                program.destination
+            );
+            *self
+                .new_eoa_accounts
+                .entry(program.destination.clone())
+                .or_default() += program.value;
         } else {
-            // Transfer to EoA account, if the EoA cell not exists, return error
-            if let Ok(cell_mut) = self.get_eoa_cell_mut(&program.destination) {
-                log::debug!(
-                    "add {} wei to eoa account {:x}",
-                    program.value,
-                    program.destination
-                );
-                cell_mut.add_balance(program.value);
-            } else {
-                panic!(
-                    "[ERROR NOT FOUND] add {} wei to address {:x}",
-                    program.value, destination
+            return Err(TransferError::RecipientNotFound(
+                program.destination.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The output of the new EoA cell for an unknown recipient. The recipient
+    /// address is the lock args (Ethereum address mode), the type id args is
+    /// derived from the first input and the output index.
+    pub fn new_eoa_output(&self, address: &H160, output_index: u64, balance: u64) -> CellOutput {
+        let type_id_args = {
+            let mut blake2b = new_blake2b();
+            blake2b.update(self.first_cell_input().0.as_slice());
+            blake2b.update(&output_index.to_le_bytes());
+            let mut ret = [0; 32];
+            blake2b.finalize(&mut ret);
+            Bytes::from(ret.to_vec())
+        };
+        let type_script = Script::new_builder()
+            .code_hash(TYPE_ID_CODE_HASH.pack())
+            .hash_type(ScriptHashType::Type.into())
+            .args(type_id_args.pack())
+            .build();
+        let lock_script = self
+            .run_config
+            .eoa_lock_script
+            .clone()
+            .as_builder()
+            .args(Bytes::from(address.as_bytes().to_vec()).pack())
+            .build();
+        let output = CellOutput::new_builder()
+            .type_(ScriptOpt::new_builder().set(Some(type_script)).build())
+            .lock(lock_script)
+            .build();
+        let occupied_capacity = output
+            .occupied_capacity(Capacity::zero())
+            .expect("capacity")
+            .as_u64();
+        output
+            .as_builder()
+            .capacity((occupied_capacity + balance).pack())
+            .build()
+    }
+
+    // Add the selfdestructed contract's capacity to the beneficiary (contract or EoA
+    // account), the transaction is rejected if the beneficiary is not an account
+    pub fn add_selfdestruct_balance(
+        &mut self,
+        beneficiary: &H160,
+        capacity: u64,
+    ) -> Result<(), String> {
+        let contract_address = ContractAddress(beneficiary.clone());
+        if self.get_contract_index(&contract_address).is_none() {
+            if let Ok(meta) = self.loader.load_contract_meta(contract_address.clone()) {
+                if meta.destructed {
+                    return Err(format!(
+                        "Selfdestruct beneficiary contract already destructed: {:x}",
+                        beneficiary
+                    ));
+                }
+                // The beneficiary contract is not touched in current transaction
+                let change = self
+                    .loader
+                    .load_latest_contract_state(contract_address.clone())?;
+                let (output, data) = self
+                    .loader
+                    .load_contract_live_cell(change.tx_hash.clone(), change.output_index)?;
+                let input = ContractInput::new(change.out_point(), output, data);
+                let mut info = ContractInfo::new(
+                    contract_address.clone(),
+                    Some(input),
+                    change.balance,
+                    change.merkle_tree(),
                 );
+                info.code = meta.code;
+                self.contracts.push((contract_address.clone(), info));
             }
         }
-        Ok(())
+        if let Some(info) = self.get_contract_info_mut(&contract_address) {
+            log::debug!(
+                "add selfdestruct capacity {} to contract {:x}",
+                capacity,
+                beneficiary
+            );
+            info.add_balance(capacity);
+            Ok(())
+        } else {
+            self.get_eoa_cell_mut(beneficiary)
+                .map(|eoa_cell| eoa_cell.add_balance(capacity))
+                .map_err(|err| format!("Get EoA account error: {}", err))
+        }
     }
 
     pub fn set_entrance_program(&mut self, program: Program) -> Result<(), Box<dyn StdError>> {
-        if program.kind.is_call() {
-            let latest_change = self.loader.load_latest_contract_change(
-                ContractAddress(program.destination.clone()),
-                None,
-                false,
-                false,
-            )?;
-
+        log::info!("> tx_origin: {:x}", program.sender);
+        let eoa_live_cell = self
+            .loader
+            .load_eoa_live_cell_value(program.sender.clone())?;
+        let mut out_points = vec![(eoa_live_cell.tx_hash.clone(), eoa_live_cell.output_index)];
+        let latest_change_opt = if program.kind.is_call() {
+            let latest_change = self
+                .loader
+                .load_latest_contract_state(ContractAddress(program.destination.clone()))?;
+            out_points.push((latest_change.tx_hash.clone(), latest_change.output_index));
+            Some(latest_change)
+        } else {
+            None
+        };
+        // Load the EoA cell and the contract cell in one batch request
+        let mut live_cells = self.loader.load_live_cells(out_points)?.into_iter();
+        let (output, output_data) = live_cells.next().expect("eoa live cell");
+        if let Some(latest_change) = latest_change_opt {
             let out_point = OutPoint::new(latest_change.tx_hash.pack(), latest_change.output_index);
-            let (contract_live_cell, latest_contract_data) = self.loader.load_contract_live_cell(
-                latest_change.tx_hash.clone(),
-                latest_change.output_index,
-            )?;
+            let (contract_live_cell, latest_contract_data) =
+                live_cells.next().expect("contract live cell");
             self.first_contract_input = Some(ContractInput::new(
                 out_point,
                 contract_live_cell,
                 latest_contract_data,
             ));
         }
-        log::info!("> tx_origin: {:x}", program.sender);
         self.tx_origin = EoaAddress(program.sender.clone());
-        let (eoa_live_cell, output, output_data) =
-            self.loader.load_eoa_live_cell(program.sender.clone())?;
         self.tx_origin_cell = eoa_live_cell;
+        for (_, cell) in &self.tx_origin_merged_cells {
+            self.tx_origin_cell.add_balance(cell.capacity());
+        }
         self.tx_origin_output = (output, output_data);
         self.entrance_program = Some(program);
         Ok(())
@@ -966,6 +1479,11 @@ impl CsalRunContext {
     pub fn entrance_info(&self) -> &ContractInfo {
         &self.contracts[0].1
     }
+    /// The VM cycles consumed by the executed programs
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
     pub fn created_contracts(&self) -> Vec<ContractAddress> {
         self.contracts
             .iter()
@@ -1005,6 +1523,11 @@ impl CsalRunContext {
             })
     }
 
+    /// The address is a contract of current transaction or an indexed contract
+    pub fn is_contract(&self, address: &ContractAddress) -> bool {
+        self.get_contract_index(address).is_some()
+            || self.loader.load_contract_meta(address.clone()).is_ok()
+    }
     pub fn get_contract_index(&self, address: &ContractAddress) -> Option<usize> {
         self.contracts.iter().position(|(addr, _)| addr == address)
     }
@@ -1032,6 +1555,13 @@ impl CsalRunContext {
         if address == &self.tx_origin.0 {
             return Ok(&mut self.tx_origin_cell);
         }
+        if self
+            .tx_origin_merged_cells
+            .iter()
+            .any(|(merged_address, _)| merged_address == address)
+        {
+            return Err(format!("EoA account merged into tx_origin: {:x}", address));
+        }
 
         if !self.other_eoa_cells.contains_key(address) {
             let (cell, output, output_data) = self.loader.load_eoa_live_cell(address.clone())?;
@@ -1045,6 +1575,7 @@ impl CsalRunContext {
 
 impl<Mac: SupportMachine> RunContext<Mac> for CsalRunContext {
     fn ecall(&mut self, machine: &mut Mac) -> Result<bool, VMError> {
+        self.vm_cycles = machine.cycles();
         let code = machine.registers()[A7].to_u64();
         match code {
             // ckb_debug
@@ -1164,16 +1695,16 @@ impl<Mac: SupportMachine> RunContext<Mac> for CsalRunContext {
                     VMError::IO(std::io::ErrorKind::InvalidInput)
                 })?;
                 let capacity = self.current_contract_info().capacity();
-                // FIXME: selfdestruct beneficiary is a contract account
-                let mut error_message = None;
-                match self.get_eoa_cell_mut(&address) {
-                    Ok(eoa_cell) => eoa_cell.add_balance(capacity),
-                    Err(err) => {
-                        error_message = Some(format!("Get EoA account error: {}", err));
-                    }
-                }
-                self.error_message = error_message;
-                if self.error_message.is_some() {
+                // The capacity of the contract selfdestructed to itself is
+                // refunded to tx_origin (the same in indexer and validator)
+                let beneficiary = if address == self.current_contract_address().0 {
+                    log::debug!("selfdestruct to itself: {:x}", address);
+                    self.tx_origin.0.clone()
+                } else {
+                    address.clone()
+                };
+                if let Err(err) = self.add_selfdestruct_balance(&beneficiary, capacity) {
+                    self.error_message = Some(err);
                     return Err(VMError::IO(std::io::ErrorKind::InvalidInput));
                 }
                 self.current_contract_info_mut().selfdestruct = Some((address, capacity));
@@ -1217,7 +1748,13 @@ impl<Mac: SupportMachine> RunContext<Mac> for CsalRunContext {
                     return Err(VMError::Unexpected);
                 }
 
-                let dest_is_eoa = self.get_eoa_cell_mut(&destination).is_ok();
+                // A plain value transfer to an address without code is a transfer
+                // to EoA account, the recipient may not exist yet.
+                let dest_is_eoa = self.get_eoa_cell_mut(&destination).is_ok()
+                    || self.new_eoa_accounts.contains_key(&destination)
+                    || (kind == CallKind::CALL
+                        && input_data.is_empty()
+                        && !self.is_contract(&ContractAddress(destination.clone())));
                 let (code, input) = match kind {
                     CallKind::CREATE | CallKind::CREATE2 => {
                         (Bytes::from(input_data), Bytes::default())
@@ -1258,7 +1795,7 @@ impl<Mac: SupportMachine> RunContext<Mac> for CsalRunContext {
                     if program.is_transfer_only() && dest_is_eoa {
                         log::debug!("transfer to eoa account");
                         if let Err(err) = self.handle_transfer(&program) {
-                            error_message = Some(err);
+                            error_message = Some(err.to_string());
                         }
                         self.error_message = error_message.take();
                         if self.error_message.is_some() {
@@ -1431,8 +1968,7 @@ impl<Mac: SupportMachine> RunContext<Mac> for CsalRunContext {
                 let number = self.tip_block.number();
                 let timestamp = self.tip_block.timestamp() / 1000;
                 let difficulty = self.tip_block.difficulty();
-                // TODO: config chain ID
-                let chain_id = U256::one();
+                let chain_id = U256::from(self.run_config.chain_id);
                 let secp_blake160_code_hash: Byte32 =
                     h256!("0x9bd7e06f3ecf4be0f2fcd2188b23f1b9fcc88e5d4b65a8637b17723bbda3cce8")
                         .pack();
@@ -1553,3 +2089,315 @@ fn print_proof(proof: &RunProofResult) {
         hex::encode(&proof.write_old_proof[..])
     );
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock_chain::MockChain;
+    use crate::storage::Key;
+    use crate::types::AddressMode;
+    use ckb_types::h160;
+    use rocksdb::DB;
+    use std::sync::Arc;
+
+    fn new_context(db: DB, run_config: RunConfig) -> CsalRunContext {
+        let chain = MockChain::new(Vec::new());
+        let loader = Loader::new(Arc::new(db), Box::new(chain.clone()));
+        CsalRunContext::new(loader, run_config, chain.tip())
+    }
+
+    fn contract_info(address: &ContractAddress, input_capacity: Option<u64>) -> ContractInfo {
+        let input = input_capacity.map(|capacity| {
+            let output = CellOutput::new_builder().capacity(capacity.pack()).build();
+            ContractInput::new(OutPoint::default(), output, Bytes::default())
+        });
+        let mut info = ContractInfo::new(address.clone(), input, 100, Default::default());
+        info.code = Bytes::from(vec![0x60]);
+        info
+    }
+
+    #[test]
+    fn test_contract_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut context = new_context(DB::open_default(dir.path()).unwrap(), Default::default());
+        let called = ContractAddress(h160!("0xc1"));
+        let refunded = ContractAddress(h160!("0xc2"));
+        let destructed = ContractAddress(h160!("0xc3"));
+        let created = ContractAddress(h160!("0xc4"));
+        let beneficiary = h160!("0xe1");
+
+        let mut called_info = contract_info(&called, Some(1000 * ONE_CKB));
+        called_info.add_balance(5);
+        // Selfdestruct to itself, the capacity is refunded to tx_origin
+        let mut refunded_info = contract_info(&refunded, Some(300 * ONE_CKB));
+        refunded_info.selfdestruct = Some((refunded.0.clone(), 300 * ONE_CKB));
+        let mut destructed_info = contract_info(&destructed, Some(400 * ONE_CKB));
+        destructed_info.selfdestruct = Some((beneficiary.clone(), 400 * ONE_CKB));
+        let created_info = contract_info(&created, None);
+        context.contracts = vec![
+            (called.clone(), called_info),
+            (refunded, refunded_info),
+            (destructed, destructed_info),
+            (created.clone(), created_info),
+        ];
+
+        let mut eoa_cell = value::EoaLiveCell::new(
+            H256::default(),
+            0,
+            beneficiary.clone(),
+            200 * ONE_CKB,
+            100 * ONE_CKB,
+        );
+        eoa_cell.add_balance(400 * ONE_CKB);
+        let mut other_eoa_cells = BTreeMap::default();
+        other_eoa_cells.insert(beneficiary.clone(), eoa_cell);
+        let mut other_eoa_outputs = BTreeMap::default();
+        other_eoa_outputs.insert(
+            beneficiary,
+            (CellOutput::default(), Bytes::from(vec![0x01])),
+        );
+
+        let (outputs, outputs_data, contract_output_indices) =
+            context.contract_outputs(&mut other_eoa_cells, &mut other_eoa_outputs);
+        assert_eq!(
+            contract_output_indices,
+            vec![Some(0), None, Some(1), Some(2)]
+        );
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs_data.len(), 3);
+        // The called contract keeps its cell with the new balance
+        let capacity: u64 = outputs[0].capacity().unpack();
+        assert_eq!(capacity, 1000 * ONE_CKB + 5);
+        // The output of the EoA beneficiary takes the place of the destructed contract
+        let capacity: u64 = outputs[1].capacity().unpack();
+        assert_eq!(capacity, 600 * ONE_CKB);
+        assert_eq!(outputs_data[1], Bytes::from(vec![0x01]));
+        assert!(other_eoa_cells.is_empty());
+        assert!(other_eoa_outputs.is_empty());
+        // The created contract
+        let type_script = outputs[2].type_().to_opt().unwrap();
+        assert_eq!(type_script.args().raw_data(), created.0.as_bytes());
+        let capacity: u64 = outputs[2].capacity().unpack();
+        assert_eq!(capacity, contract_cell_min_capacity() + 100);
+    }
+
+    #[test]
+    fn test_transfer_to_unknown_recipient() {
+        let tx_origin = h160!("0xe1");
+        // Neither a contract nor an EoA account
+        let recipient = h160!("0xc5");
+        let program = Program::new_call(
+            EoaAddress(tx_origin.clone()),
+            tx_origin.clone(),
+            recipient.clone(),
+            Bytes::default(),
+            Bytes::default(),
+            10 * ONE_CKB,
+            false,
+        );
+        let new_context_with = |create_eoa_recipient: bool| {
+            let dir = tempfile::tempdir().unwrap();
+            let run_config = RunConfig {
+                address_mode: AddressMode::Ethereum,
+                create_eoa_recipient,
+                ..Default::default()
+            };
+            let mut context = new_context(DB::open_default(dir.path()).unwrap(), run_config);
+            context.tx_origin = EoaAddress(tx_origin.clone());
+            context.tx_origin_cell = value::EoaLiveCell::new(
+                H256::default(),
+                0,
+                tx_origin.clone(),
+                200 * ONE_CKB,
+                100 * ONE_CKB,
+            );
+            (dir, context)
+        };
+
+        let (_dir, mut context) = new_context_with(false);
+        assert_eq!(
+            context.handle_transfer(&program),
+            Err(TransferError::RecipientNotFound(recipient.clone()))
+        );
+        assert!(context.new_eoa_accounts.is_empty());
+
+        let (_dir, mut context) = new_context_with(true);
+        context.handle_transfer(&program).unwrap();
+        context.handle_transfer(&program).unwrap();
+        assert_eq!(context.tx_origin_cell.balance(), 80 * ONE_CKB);
+        assert_eq!(
+            context.new_eoa_accounts.get(&recipient),
+            Some(&(20 * ONE_CKB))
+        );
+        let output = context.new_eoa_output(&recipient, 1, 20 * ONE_CKB);
+        assert_eq!(output.lock().args().raw_data(), recipient.as_bytes());
+        let type_script = output.type_().to_opt().unwrap();
+        assert_eq!(type_script.code_hash(), TYPE_ID_CODE_HASH.pack());
+        let occupied: u64 = context.new_eoa_output(&recipient, 1, 0).capacity().unpack();
+        let capacity: u64 = output.capacity().unpack();
+        assert_eq!(capacity, occupied + 20 * ONE_CKB);
+    }
+
+    #[test]
+    fn test_add_selfdestruct_balance() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut context = new_context(DB::open_default(dir.path()).unwrap(), Default::default());
+        let tx_origin = h160!("0xe1");
+        context.tx_origin = EoaAddress(tx_origin.clone());
+        context.tx_origin_cell = value::EoaLiveCell::new(
+            H256::default(),
+            0,
+            tx_origin.clone(),
+            200 * ONE_CKB,
+            100 * ONE_CKB,
+        );
+        let contract = ContractAddress(h160!("0xc1"));
+        context.contracts = vec![(
+            contract.clone(),
+            contract_info(&contract, Some(300 * ONE_CKB)),
+        )];
+
+        context.add_selfdestruct_balance(&contract.0, 5).unwrap();
+        assert_eq!(context.contracts[0].1.balance, 105);
+        // The refund of the contract selfdestructed to itself
+        context
+            .add_selfdestruct_balance(&tx_origin, 300 * ONE_CKB)
+            .unwrap();
+        assert_eq!(context.tx_origin_cell.balance(), 400 * ONE_CKB);
+        assert_eq!(context.tx_origin_cell.capacity(), 500 * ONE_CKB);
+        // The beneficiary is not an account
+        assert!(context
+            .add_selfdestruct_balance(&h160!("0xdead"), 5)
+            .is_err());
+    }
+
+    #[test]
+    fn test_tx_origin_merged_cells() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DB::open_default(dir.path()).unwrap();
+        // Ethereum address mode: the address is the lock args
+        let tx_origin = h160!("0xe1");
+        for (index, balance) in vec![300 * ONE_CKB, 500 * ONE_CKB].into_iter().enumerate() {
+            let cell = value::EoaLiveCell::new(
+                H256::default(),
+                index as u32,
+                tx_origin.clone(),
+                balance + 100 * ONE_CKB,
+                balance,
+            );
+            let value_bytes = bincode::serialize(&cell).unwrap();
+            let owner_key = Key::EoaOwnerCell {
+                lock_args: tx_origin.clone(),
+                address: Some(tx_origin.clone()),
+                out_point: Some(cell.out_point()),
+            };
+            db.put(&Bytes::from(&owner_key), &value_bytes).unwrap();
+            let key = Key::EoaLiveCell {
+                address: tx_origin.clone(),
+                out_point: Some(cell.out_point()),
+            };
+            db.put(&Bytes::from(&key), &value_bytes).unwrap();
+            let cell_data = value::CellData::new(&CellOutput::default(), Bytes::default());
+            db.put(
+                &Bytes::from(&Key::CellData(cell.out_point())),
+                &bincode::serialize(&cell_data).unwrap(),
+            )
+            .unwrap();
+        }
+        let run_config = RunConfig {
+            address_mode: AddressMode::Ethereum,
+            ..Default::default()
+        };
+        let mut context = new_context(db, run_config);
+
+        // None of the cells have enough balance
+        let (sender, merged_cells) = context
+            .loader
+            .select_eoa_address(tx_origin.clone(), 700 * ONE_CKB)
+            .unwrap();
+        assert_eq!(sender, tx_origin);
+        context.tx_origin_merged_cells = merged_cells;
+        let program = Program::new_create(
+            EoaAddress(sender.clone()),
+            sender,
+            Bytes::from(vec![0x60]),
+            700 * ONE_CKB,
+        );
+        context.set_entrance_program(program).unwrap();
+        // The capacity of the merged cell (with the occupied capacity) is added
+        assert_eq!(context.tx_origin_cell.output_index, 1);
+        assert_eq!(context.tx_origin_cell.balance(), 900 * ONE_CKB);
+        assert_eq!(context.tx_origin_cell.capacity(), 1000 * ONE_CKB);
+        context.tx_origin_cell.sub_balance(700 * ONE_CKB).unwrap();
+    }
+
+    #[test]
+    fn test_merge_eoa_cells() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DB::open_default(dir.path()).unwrap();
+        let owner = h160!("0xa1");
+        let target_output = CellOutput::new_builder()
+            .lock(
+                Script::new_builder()
+                    .args(Bytes::from(owner.as_bytes().to_vec()).pack())
+                    .build(),
+            )
+            .build();
+        for (index, balance) in vec![300 * ONE_CKB, 500 * ONE_CKB].into_iter().enumerate() {
+            let address = H160::from_slice(&[index as u8 + 1; 20]).unwrap();
+            let cell = value::EoaLiveCell::new(
+                H256::default(),
+                index as u32,
+                owner.clone(),
+                balance + 100 * ONE_CKB,
+                balance,
+            );
+            let owner_key = Key::EoaOwnerCell {
+                lock_args: owner.clone(),
+                address: Some(address),
+                out_point: Some(cell.out_point()),
+            };
+            db.put(
+                &Bytes::from(&owner_key),
+                &bincode::serialize(&cell).unwrap(),
+            )
+            .unwrap();
+            // The nonce of the smaller cell is kept in the merged cell
+            let data = if index == 0 {
+                eoa_cell_data(3)
+            } else {
+                Bytes::default()
+            };
+            let cell_data = value::CellData::new(&target_output, data);
+            db.put(
+                &Bytes::from(&Key::CellData(cell.out_point())),
+                &bincode::serialize(&cell_data).unwrap(),
+            )
+            .unwrap();
+        }
+        let chain = MockChain::new(Vec::new());
+        let loader = Loader::new(Arc::new(db), Box::new(chain));
+        let mut runner = Runner::new(loader, RunConfig::default());
+
+        let tx = runner.merge_eoa_cells(owner).unwrap();
+        let raw_tx = tx.raw();
+        assert_eq!(raw_tx.inputs().len(), 2);
+        // The cell with largest balance is kept
+        let first_input: (H256, u32) = {
+            let out_point = raw_tx.inputs().get(0).unwrap().previous_output();
+            (out_point.tx_hash().unpack(), out_point.index().unpack())
+        };
+        assert_eq!(first_input, (H256::default(), 1));
+        assert_eq!(raw_tx.outputs().len(), 1);
+        let capacity: u64 = raw_tx.outputs().get(0).unwrap().capacity().unpack();
+        assert_eq!(capacity, 1000 * ONE_CKB - MERGE_TX_FEE);
+        assert_eq!(
+            raw_tx.outputs_data().get(0).unwrap().raw_data(),
+            eoa_cell_data(3)
+        );
+        let witness = WitnessArgs::from_slice(&tx.witnesses().get(0).unwrap().raw_data()).unwrap();
+        assert_eq!(witness.lock().to_opt().unwrap().raw_data().len(), 65);
+
+        assert!(runner.merge_eoa_cells(h160!("0xa2")).is_err());
+    }
+}
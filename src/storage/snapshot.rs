@@ -0,0 +1,335 @@
+use bincode::serialize;
+use ckb_hash::new_blake2b;
+use ckb_types::{bytes::Bytes, H256};
+use rocksdb::{WriteBatch, DB};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::migration::SCHEMA_VERSION;
+use super::{db_get, value, Key, KeyType};
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"PJSNAP01";
+// magic + schema version + block number + block hash + record count + checksum
+const HEADER_LEN: usize = 8 + 4 + 8 + 32 + 8 + 32;
+const IMPORT_BATCH_SIZE: u64 = 10_000;
+
+/// The snapshot file header
+///
+///   magic | schema_version(u32) | number(u64) | hash | record_count(u64) | checksum
+///
+/// The checksum is blake2b of all the records, each record is:
+///
+///   key_len(u32) | key | value_len(u32) | value
+///
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SnapshotHeader {
+    pub schema_version: u32,
+    pub number: u64,
+    pub hash: H256,
+    pub record_count: u64,
+    pub checksum: H256,
+}
+
+impl SnapshotHeader {
+    fn serialize(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(HEADER_LEN);
+        data.extend(&SNAPSHOT_MAGIC[..]);
+        data.extend(&self.schema_version.to_le_bytes());
+        data.extend(&self.number.to_le_bytes());
+        data.extend(self.hash.as_bytes());
+        data.extend(&self.record_count.to_le_bytes());
+        data.extend(self.checksum.as_bytes());
+        data
+    }
+
+    fn deserialize(data: &[u8; HEADER_LEN]) -> Result<SnapshotHeader, String> {
+        if &data[0..8] != SNAPSHOT_MAGIC {
+            return Err(String::from("Invalid snapshot file"));
+        }
+        let mut u32_buf = [0u8; 4];
+        let mut u64_buf = [0u8; 8];
+        u32_buf.copy_from_slice(&data[8..12]);
+        let schema_version = u32::from_le_bytes(u32_buf);
+        u64_buf.copy_from_slice(&data[12..20]);
+        let number = u64::from_le_bytes(u64_buf);
+        let hash = H256::from_slice(&data[20..52]).expect("deserialize hash");
+        u64_buf.copy_from_slice(&data[52..60]);
+        let record_count = u64::from_le_bytes(u64_buf);
+        let checksum = H256::from_slice(&data[60..92]).expect("deserialize checksum");
+        Ok(SnapshotHeader {
+            schema_version,
+            number,
+            hash,
+            record_count,
+            checksum,
+        })
+    }
+}
+
+/// Export the current state (contract metas, latest contract changes, EoA
+/// cells, live cells, cell data, token balances and the last block) to a
+/// snapshot file.
+pub fn export_snapshot<P: AsRef<Path>>(db: &DB, path: P) -> Result<SnapshotHeader, String> {
+    let last: value::Last = db_get(db, &Bytes::from(&Key::Last))?
+        .ok_or_else(|| String::from("Nothing indexed, can not export snapshot"))?;
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    let mut writer = BufWriter::new(file);
+    // Header placeholder, rewrite it when all records are written
+    writer
+        .write_all(&[0u8; HEADER_LEN])
+        .map_err(|err| err.to_string())?;
+
+    let mut blake2b = new_blake2b();
+    let mut record_count: u64 = 0;
+    let mut write_record = |key: &[u8], value: &[u8]| -> Result<(), String> {
+        let mut record = Vec::with_capacity(8 + key.len() + value.len());
+        record.extend(&(key.len() as u32).to_le_bytes());
+        record.extend(key);
+        record.extend(&(value.len() as u32).to_le_bytes());
+        record.extend(value);
+        blake2b.update(&record);
+        record_count += 1;
+        writer.write_all(&record).map_err(|err| err.to_string())
+    };
+
+    let snapshot = db.snapshot();
+    for key_type in &[
+        KeyType::ContractMeta,
+        KeyType::ContractChange,
+        KeyType::LockLiveCell,
+        KeyType::EoaLiveCell,
+        KeyType::LiveCellMap,
+        KeyType::EoaOwnerCell,
+        KeyType::EthNonce,
+        KeyType::CellData,
+        KeyType::ContractAbi,
+        KeyType::TokenHolder,
+        KeyType::HolderToken,
+        KeyType::TokenOwner,
+    ] {
+        let key_prefix = [key_type.clone() as u8];
+        // Only the latest change of each contract is exported
+        let mut last_change: Option<(Box<[u8]>, Box<[u8]>)> = None;
+        let mut iter = snapshot.raw_iterator();
+        iter.seek(&key_prefix);
+        while iter.valid() {
+            if let Some((key_bytes, value_bytes)) = iter
+                .key()
+                .filter(|key| key.starts_with(&key_prefix))
+                .and_then(|key| iter.value().map(|value| (key, value)))
+            {
+                if *key_type == KeyType::ContractChange {
+                    // 1 byte key type + 20 bytes address
+                    if let Some((prev_key, prev_value)) = last_change.as_ref() {
+                        if prev_key[0..21] != key_bytes[0..21] {
+                            write_record(prev_key, prev_value)?;
+                        }
+                    }
+                    last_change = Some((key_bytes.into(), value_bytes.into()));
+                } else if *key_type == KeyType::CellData {
+                    // Skip the consumed cells not pruned yet
+                    let map_key = [&[KeyType::LiveCellMap as u8][..], &key_bytes[1..]].concat();
+                    if snapshot
+                        .get(&map_key)
+                        .map_err(|err| err.to_string())?
+                        .is_some()
+                    {
+                        write_record(key_bytes, value_bytes)?;
+                    }
+                } else {
+                    write_record(key_bytes, value_bytes)?;
+                }
+            } else {
+                break;
+            }
+            iter.next();
+        }
+        if let Some((key_bytes, value_bytes)) = last_change {
+            write_record(&key_bytes, &value_bytes)?;
+        }
+    }
+
+    let mut checksum = [0u8; 32];
+    blake2b.finalize(&mut checksum);
+    let header = SnapshotHeader {
+        schema_version: SCHEMA_VERSION,
+        number: last.number,
+        hash: last.hash,
+        record_count,
+        checksum: H256::from_slice(&checksum[..]).unwrap(),
+    };
+    let mut file = writer.into_inner().map_err(|err| err.to_string())?;
+    file.seek(SeekFrom::Start(0))
+        .map_err(|err| err.to_string())?;
+    file.write_all(&header.serialize())
+        .map_err(|err| err.to_string())?;
+    Ok(header)
+}
+
+/// Import a snapshot file into an empty database, the indexer will continue
+/// from the snapshot block.
+pub fn import_snapshot<P: AsRef<Path>>(db: &DB, path: P) -> Result<SnapshotHeader, String> {
+    if db_get::<_, value::Last>(db, &Bytes::from(&Key::Last))?.is_some() {
+        return Err(String::from(
+            "Can not import snapshot, the database is not empty",
+        ));
+    }
+    let header = read_snapshot(&path, |_, _| Ok(()))?;
+    if header.schema_version != SCHEMA_VERSION {
+        return Err(format!(
+            "Snapshot schema version {} not match current version {}",
+            header.schema_version, SCHEMA_VERSION
+        ));
+    }
+
+    let mut batch = WriteBatch::default();
+    let mut batch_count: u64 = 0;
+    read_snapshot(&path, |key, value| {
+        batch.put(key, value);
+        batch_count += 1;
+        if batch_count >= IMPORT_BATCH_SIZE {
+            db.write(std::mem::take(&mut batch))
+                .map_err(|err| err.to_string())?;
+            batch_count = 0;
+        }
+        Ok(())
+    })?;
+
+    // The snapshot block is the start block, it can not be rolled back
+    let last = value::Last {
+        number: header.number,
+        hash: header.hash.clone(),
+    };
+    let start_block = value::StartBlock {
+        number: header.number,
+        hash: header.hash.clone(),
+    };
+    let block_summary = value::BlockSummary {
+        contracts: Vec::new(),
+    };
+    batch.put(
+        &Bytes::from(&Key::BlockMap(header.number)),
+        &serialize(&value::BlockMap(header.hash.clone())).unwrap(),
+    );
+    batch.put(
+        &Bytes::from(&Key::BlockSummary(header.number)),
+        &serialize(&block_summary).unwrap(),
+    );
+    batch.put(
+        &Bytes::from(&Key::StartBlock),
+        &serialize(&start_block).unwrap(),
+    );
+    batch.put(&Bytes::from(&Key::Last), &serialize(&last).unwrap());
+    db.write(batch).map_err(|err| err.to_string())?;
+    Ok(header)
+}
+
+// Read all records and verify the checksum
+fn read_snapshot<P, F>(path: P, mut f: F) -> Result<SnapshotHeader, String>
+where
+    P: AsRef<Path>,
+    F: FnMut(&[u8], &[u8]) -> Result<(), String>,
+{
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(file);
+    let mut header_data = [0u8; HEADER_LEN];
+    reader
+        .read_exact(&mut header_data)
+        .map_err(|err| err.to_string())?;
+    let header = SnapshotHeader::deserialize(&header_data)?;
+
+    let mut blake2b = new_blake2b();
+    let mut read_bytes = |blake2b: &mut ckb_hash::Blake2b| -> Result<Vec<u8>, String> {
+        let mut len_buf = [0u8; 4];
+        reader
+            .read_exact(&mut len_buf)
+            .map_err(|err| err.to_string())?;
+        let mut data = vec![0u8; u32::from_le_bytes(len_buf) as usize];
+        reader
+            .read_exact(&mut data)
+            .map_err(|err| err.to_string())?;
+        blake2b.update(&len_buf);
+        blake2b.update(&data);
+        Ok(data)
+    };
+    for _ in 0..header.record_count {
+        let key = read_bytes(&mut blake2b)?;
+        let value = read_bytes(&mut blake2b)?;
+        match KeyType::try_from(key.first().copied().unwrap_or_default())? {
+            KeyType::ContractMeta
+            | KeyType::ContractChange
+            | KeyType::LockLiveCell
+            | KeyType::EoaLiveCell
+            | KeyType::LiveCellMap
+            | KeyType::EoaOwnerCell
+            | KeyType::EthNonce
+            | KeyType::CellData
+            | KeyType::ContractAbi => {}
+            key_type => {
+                return Err(format!("Unexpected key type in snapshot: {:?}", key_type));
+            }
+        }
+        Key::try_from(key.as_slice())?;
+        f(&key, &value)?;
+    }
+    let mut checksum = [0u8; 32];
+    blake2b.finalize(&mut checksum);
+    if &checksum[..] != header.checksum.as_bytes() {
+        return Err(String::from("Snapshot checksum not match"));
+    }
+    Ok(header)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::ContractAddress;
+    use ckb_types::h160;
+
+    #[test]
+    fn test_snapshot_export_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let db1 = DB::open_default(dir.path().join("db1")).unwrap();
+        let db2 = DB::open_default(dir.path().join("db2")).unwrap();
+        let snapshot_path = dir.path().join("snapshot");
+
+        let address = ContractAddress(h160!("0xab"));
+        let change_key = |number| Key::ContractChange {
+            address: address.clone(),
+            number: Some(number),
+            tx_index: Some(0),
+            output_index: Some(0),
+        };
+        let last = value::Last {
+            number: 3,
+            hash: H256::default(),
+        };
+        db1.put(&Bytes::from(&Key::Last), &serialize(&last).unwrap())
+            .unwrap();
+        db1.put(&Bytes::from(&change_key(1)), b"change-1").unwrap();
+        db1.put(&Bytes::from(&change_key(2)), b"change-2").unwrap();
+        db1.put(&Bytes::from(&Key::ContractMeta(address.clone())), b"meta")
+            .unwrap();
+
+        let exported = export_snapshot(&db1, &snapshot_path).unwrap();
+        assert_eq!(exported.number, 3);
+        assert_eq!(exported.record_count, 2);
+        let imported = import_snapshot(&db2, &snapshot_path).unwrap();
+        assert_eq!(exported, imported);
+
+        assert!(db2.get(&Bytes::from(&change_key(1))).unwrap().is_none());
+        assert!(db2.get(&Bytes::from(&change_key(2))).unwrap().is_some());
+        assert!(db2
+            .get(&Bytes::from(&Key::ContractMeta(address)))
+            .unwrap()
+            .is_some());
+        let start_block: value::StartBlock = db_get(&db2, &Bytes::from(&Key::StartBlock))
+            .unwrap()
+            .unwrap();
+        assert_eq!(start_block.number, 3);
+        // Import again is refused
+        assert!(import_snapshot(&db2, &snapshot_path).is_err());
+    }
+}
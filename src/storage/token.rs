@@ -0,0 +1,407 @@
+use bincode::{deserialize, serialize};
+use ckb_types::{bytes::Bytes, h256, H160, H256, U256};
+use rocksdb::{WriteBatch, DB};
+use std::collections::HashMap;
+
+use super::processor::{BlockProcessor, ProcessedBlock};
+use super::{db_get, value, Key};
+use crate::types::{ContractAddress, ContractChange, TokenKind};
+
+pub const TOKEN_TRACKER_NAME: &str = "tokens";
+
+/// keccak256("Transfer(address,address,uint256)")
+pub const TRANSFER_EVENT_TOPIC: H256 =
+    h256!("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+/// A `Transfer` event of ERC20 or ERC721 token
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TokenTransferEvent {
+    pub kind: TokenKind,
+    pub from: H160,
+    pub to: H160,
+    /// The amount (ERC20) or the token id (ERC721)
+    pub value: H256,
+}
+
+fn word_to_address(word: &[u8]) -> Option<H160> {
+    if word.len() != 32 || word[0..12].iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    H160::from_slice(&word[12..32]).ok()
+}
+
+/// Parse the log as a `Transfer` event:
+///
+///   ERC20:  Transfer(address indexed, address indexed, uint256)
+///   ERC721: Transfer(address indexed, address indexed, uint256 indexed)
+///   ERC721 draft (KittyCore): Transfer(address, address, uint256)
+///
+pub fn parse_transfer_event(topics: &[H256], data: &[u8]) -> Option<TokenTransferEvent> {
+    if topics.first() != Some(&TRANSFER_EVENT_TOPIC) {
+        return None;
+    }
+    let (kind, from, to, value) = match (topics.len(), data.len()) {
+        (3, 32) => (
+            TokenKind::Erc20,
+            topics[1].as_bytes(),
+            topics[2].as_bytes(),
+            data,
+        ),
+        (4, 0) => (
+            TokenKind::Erc721,
+            topics[1].as_bytes(),
+            topics[2].as_bytes(),
+            topics[3].as_bytes(),
+        ),
+        (1, 96) => (
+            TokenKind::Erc721,
+            &data[0..32],
+            &data[32..64],
+            &data[64..96],
+        ),
+        _ => return None,
+    };
+    Some(TokenTransferEvent {
+        kind,
+        from: word_to_address(from)?,
+        to: word_to_address(to)?,
+        value: H256::from_slice(value).ok()?,
+    })
+}
+
+/// Index ERC20/ERC721 balances, owners and transfers from the `Transfer` events
+pub struct TokenTracker;
+
+impl BlockProcessor for TokenTracker {
+    fn name(&self) -> &str {
+        TOKEN_TRACKER_NAME
+    }
+
+    fn process(
+        &mut self,
+        db: &DB,
+        batch: &mut WriteBatch,
+        block: &ProcessedBlock,
+    ) -> Result<Bytes, String> {
+        let delta = index_token_transfers(db, batch, block.number, block.contract_changes)?;
+        if delta.is_empty() {
+            return Ok(Bytes::new());
+        }
+        Ok(Bytes::from(serialize(&delta).unwrap()))
+    }
+
+    fn rollback(
+        &self,
+        _db: &DB,
+        batch: &mut WriteBatch,
+        number: u64,
+        undo: &[u8],
+    ) -> Result<(), String> {
+        let delta: value::TokenDelta = deserialize(undo).map_err(|err| err.to_string())?;
+        rollback_token_delta(batch, number, delta);
+        Ok(())
+    }
+}
+
+/// Index the token transfers of the contract changes in a block, the writes
+/// are put into the batch. Return the delta for rollback.
+pub fn index_token_transfers(
+    db: &DB,
+    batch: &mut WriteBatch,
+    number: u64,
+    changes: &[ContractChange],
+) -> Result<value::TokenDelta, String> {
+    let zero_address = H160::default();
+    let mut delta = value::TokenDelta::default();
+    let mut balances: HashMap<(ContractAddress, H160), Option<value::TokenBalance>> =
+        HashMap::default();
+    let mut owners: HashMap<(ContractAddress, H256), Option<H160>> = HashMap::default();
+    // tx_index => next transfer index
+    let mut transfer_indexes: HashMap<u32, u32> = HashMap::default();
+
+    for change in changes {
+        let token = &change.address;
+        for (topics, data) in &change.logs {
+            let event = match parse_transfer_event(topics, data) {
+                Some(event) => event,
+                None => continue,
+            };
+            log::debug!(
+                "token transfer: token={:x}, from={:x}, to={:x}",
+                token.0,
+                event.from,
+                event.to
+            );
+            let amount = match event.kind {
+                TokenKind::Erc20 => U256::from_be_bytes(&event.value.0),
+                TokenKind::Erc721 => U256::one(),
+            };
+            for (holder, incoming) in &[(&event.from, false), (&event.to, true)] {
+                if *holder == &zero_address {
+                    continue;
+                }
+                let key = (token.clone(), (*holder).clone());
+                if !balances.contains_key(&key) {
+                    let old_balance: Option<value::TokenBalance> = db_get(
+                        db,
+                        &Bytes::from(&Key::TokenHolder {
+                            token: token.clone(),
+                            holder: Some((*holder).clone()),
+                        }),
+                    )?;
+                    delta
+                        .balances
+                        .push((token.clone(), (*holder).clone(), old_balance.clone()));
+                    balances.insert(key.clone(), old_balance);
+                }
+                let entry = balances.get_mut(&key).expect("balance loaded");
+                let balance = entry
+                    .as_ref()
+                    .map(value::TokenBalance::balance)
+                    .unwrap_or_else(U256::zero);
+                let new_balance = if *incoming {
+                    balance.checked_add(&amount).unwrap_or_else(|| {
+                        log::warn!("Token balance overflow: {:x} {:x}", token.0, holder);
+                        balance
+                    })
+                } else {
+                    balance.checked_sub(&amount).unwrap_or_else(|| {
+                        log::warn!("Token balance not enough: {:x} {:x}", token.0, holder);
+                        U256::zero()
+                    })
+                };
+                *entry = if new_balance.is_zero() {
+                    None
+                } else {
+                    Some(value::TokenBalance::new(event.kind, &new_balance))
+                };
+            }
+
+            if event.kind == TokenKind::Erc721 {
+                let key = (token.clone(), event.value.clone());
+                if !owners.contains_key(&key) {
+                    let old_owner: Option<value::TokenOwner> = db_get(
+                        db,
+                        &Bytes::from(&Key::TokenOwner {
+                            token: token.clone(),
+                            token_id: event.value.clone(),
+                        }),
+                    )?;
+                    let old_owner = old_owner.map(|owner| owner.0);
+                    delta
+                        .owners
+                        .push((token.clone(), event.value.clone(), old_owner));
+                }
+                let new_owner = Some(event.to.clone()).filter(|to| to != &zero_address);
+                owners.insert(key, new_owner);
+            }
+
+            let transfer_index = transfer_indexes.entry(change.tx_index).or_insert(0);
+            for (address, counterparty, incoming) in &[
+                (&event.from, &event.to, false),
+                (&event.to, &event.from, true),
+            ] {
+                if *address == &zero_address {
+                    continue;
+                }
+                let key = Key::TokenTransfer {
+                    address: (*address).clone(),
+                    number: Some(number),
+                    tx_index: Some(change.tx_index),
+                    transfer_index: Some(*transfer_index),
+                };
+                let transfer = value::TokenTransfer {
+                    token: token.clone(),
+                    kind: event.kind,
+                    tx_hash: change.tx_hash.clone(),
+                    counterparty: (*counterparty).clone(),
+                    value: event.value.clone(),
+                    incoming: *incoming,
+                };
+                batch.put(&Bytes::from(&key), &serialize(&transfer).unwrap());
+                if !delta.transfers.contains(*address) {
+                    delta.transfers.push((*address).clone());
+                }
+            }
+            *transfer_index += 1;
+        }
+    }
+
+    for ((token, holder), balance_opt) in balances {
+        let token_key = Key::TokenHolder {
+            token: token.clone(),
+            holder: Some(holder.clone()),
+        };
+        let holder_key = Key::HolderToken {
+            holder,
+            token: Some(token),
+        };
+        put_or_delete(batch, &token_key, balance_opt.as_ref());
+        put_or_delete(batch, &holder_key, balance_opt.as_ref());
+    }
+    for ((token, token_id), owner_opt) in owners {
+        let key = Key::TokenOwner { token, token_id };
+        put_or_delete(batch, &key, owner_opt.map(value::TokenOwner).as_ref());
+    }
+    Ok(delta)
+}
+
+/// Revert the token tracker writes of the block
+pub fn rollback_token_delta(batch: &mut WriteBatch, number: u64, delta: value::TokenDelta) {
+    for (token, holder, balance_opt) in delta.balances {
+        let token_key = Key::TokenHolder {
+            token: token.clone(),
+            holder: Some(holder.clone()),
+        };
+        let holder_key = Key::HolderToken {
+            holder,
+            token: Some(token),
+        };
+        put_or_delete(batch, &token_key, balance_opt.as_ref());
+        put_or_delete(batch, &holder_key, balance_opt.as_ref());
+    }
+    for (token, token_id, owner_opt) in delta.owners {
+        let key = Key::TokenOwner { token, token_id };
+        put_or_delete(batch, &key, owner_opt.map(value::TokenOwner).as_ref());
+    }
+    for address in delta.transfers {
+        let transfer_start_key = Key::TokenTransfer {
+            address: address.clone(),
+            number: Some(number),
+            tx_index: None,
+            transfer_index: None,
+        };
+        let transfer_end_key = Key::TokenTransfer {
+            address,
+            number: Some(number + 1),
+            tx_index: None,
+            transfer_index: None,
+        };
+        batch.delete_range(
+            &Bytes::from(&transfer_start_key),
+            &Bytes::from(&transfer_end_key),
+        );
+    }
+}
+
+fn put_or_delete<T: serde::Serialize>(batch: &mut WriteBatch, key: &Key, value_opt: Option<&T>) {
+    let key_bytes = Bytes::from(key);
+    match value_opt {
+        Some(value) => batch.put(&key_bytes, &serialize(value).unwrap()),
+        None => batch.delete(&key_bytes),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ckb_types::h160;
+
+    fn address_word(address: &H160) -> H256 {
+        let mut word = [0u8; 32];
+        word[12..32].copy_from_slice(address.as_bytes());
+        H256(word)
+    }
+
+    fn transfer_change(
+        token: &ContractAddress,
+        number: u64,
+        logs: Vec<(Vec<H256>, Bytes)>,
+    ) -> ContractChange {
+        ContractChange {
+            address: token.clone(),
+            number,
+            tx_index: 1,
+            tx_hash: h256!("0x01"),
+            logs,
+            ..Default::default()
+        }
+    }
+
+    fn load_balance(db: &DB, token: &ContractAddress, holder: &H160) -> Option<U256> {
+        db_get::<_, value::TokenBalance>(
+            db,
+            &Bytes::from(&Key::HolderToken {
+                holder: holder.clone(),
+                token: Some(token.clone()),
+            }),
+        )
+        .unwrap()
+        .map(|balance| balance.balance())
+    }
+
+    #[test]
+    fn test_index_token_transfers() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DB::open_default(dir.path()).unwrap();
+        let erc20 = ContractAddress(h160!("0xe20"));
+        let kitty = ContractAddress(h160!("0xe721"));
+        let alice = h160!("0xa1");
+        let bob = h160!("0xb0b");
+        let amount = |value: u64| Bytes::from(U256::from(value).to_be_bytes().to_vec());
+        let erc20_topics = |from: &H160, to: &H160| {
+            vec![TRANSFER_EVENT_TOPIC, address_word(from), address_word(to)]
+        };
+
+        // Block 1: mint 100 to alice, alice sends 30 to bob, mint kitty 7 to bob
+        let mut kitty_data = Vec::new();
+        kitty_data.extend(address_word(&H160::default()).as_bytes());
+        kitty_data.extend(address_word(&bob).as_bytes());
+        kitty_data.extend(h256!("0x07").as_bytes());
+        let changes = vec![
+            transfer_change(
+                &erc20,
+                1,
+                vec![
+                    (erc20_topics(&H160::default(), &alice), amount(100)),
+                    (erc20_topics(&alice, &bob), amount(30)),
+                ],
+            ),
+            transfer_change(
+                &kitty,
+                1,
+                vec![(vec![TRANSFER_EVENT_TOPIC], Bytes::from(kitty_data))],
+            ),
+        ];
+        let mut batch = WriteBatch::default();
+        let delta1 = index_token_transfers(&db, &mut batch, 1, &changes).unwrap();
+        db.write(batch).unwrap();
+        assert_eq!(load_balance(&db, &erc20, &alice), Some(U256::from(70u64)));
+        assert_eq!(load_balance(&db, &erc20, &bob), Some(U256::from(30u64)));
+        assert_eq!(load_balance(&db, &kitty, &bob), Some(U256::one()));
+        // Both alice and bob have transfers
+        assert_eq!(delta1.transfers.len(), 2);
+
+        // Block 2: bob sends all ERC20 tokens back to alice
+        let changes = vec![transfer_change(
+            &erc20,
+            2,
+            vec![(erc20_topics(&bob, &alice), amount(30))],
+        )];
+        let mut batch = WriteBatch::default();
+        let delta2 = index_token_transfers(&db, &mut batch, 2, &changes).unwrap();
+        db.write(batch).unwrap();
+        assert_eq!(load_balance(&db, &erc20, &alice), Some(U256::from(100u64)));
+        assert_eq!(load_balance(&db, &erc20, &bob), None);
+
+        let mut batch = WriteBatch::default();
+        rollback_token_delta(&mut batch, 2, delta2);
+        db.write(batch).unwrap();
+        assert_eq!(load_balance(&db, &erc20, &alice), Some(U256::from(70u64)));
+        assert_eq!(load_balance(&db, &erc20, &bob), Some(U256::from(30u64)));
+
+        let mut batch = WriteBatch::default();
+        rollback_token_delta(&mut batch, 1, delta1);
+        db.write(batch).unwrap();
+        assert_eq!(load_balance(&db, &erc20, &alice), None);
+        assert_eq!(load_balance(&db, &kitty, &bob), None);
+        let owner_key = Key::TokenOwner {
+            token: kitty,
+            token_id: h256!("0x07"),
+        };
+        assert!(db.get(&Bytes::from(&owner_key)).unwrap().is_none());
+        let mut iter = db.raw_iterator();
+        iter.seek_to_first();
+        assert!(!iter.valid());
+    }
+}
@@ -1,3 +1,4 @@
+use ckb_hash::{blake2b_256, new_blake2b};
 use ckb_simple_account_layer::{CkbBlake2bHasher, Config};
 use ckb_types::{
     bytes::{BufMut, Bytes, BytesMut},
@@ -13,6 +14,7 @@ use serde::{Deserialize, Serialize};
 use sparse_merkle_tree::{default_store::DefaultStore, SparseMerkleTree, H256 as SmtH256};
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use tiny_keccak::keccak256;
 
 use crate::storage::{value, Key};
 
@@ -60,6 +62,9 @@ lazy_static::lazy_static! {
     };
 }
 
+/// The default chain id of polyjuice (EIP-155 and the `CHAINID` opcode)
+pub const DEFAULT_CHAIN_ID: u64 = 1;
+
 #[derive(Debug, Clone)]
 pub struct RunConfig {
     pub generator: Bytes,
@@ -72,6 +77,85 @@ pub struct RunConfig {
     // Lock script for EoA account
     pub eoa_lock_dep: packed::CellDep,
     pub eoa_lock_script: packed::Script,
+    // How the EoA lock args and address derived from public key
+    pub address_mode: AddressMode,
+    // Create an EoA cell for the unknown recipient of a value transfer, or
+    // reject the transfer (Ethereum address mode only)
+    pub create_eoa_recipient: bool,
+    // The chain id of the signed Ethereum transactions and `CHAINID` opcode,
+    // must match the validator of the deployment
+    pub chain_id: u64,
+}
+
+impl Default for RunConfig {
+    fn default() -> RunConfig {
+        RunConfig {
+            generator: Bytes::default(),
+            type_dep: packed::CellDep::default(),
+            type_script: packed::Script::default(),
+            lock_dep: packed::CellDep::default(),
+            lock_script: packed::Script::default(),
+            eoa_lock_dep: packed::CellDep::default(),
+            eoa_lock_script: packed::Script::default(),
+            address_mode: AddressMode::default(),
+            create_eoa_recipient: false,
+            chain_id: DEFAULT_CHAIN_ID,
+        }
+    }
+}
+
+/// The EoA address derivation of a deployment
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressMode {
+    /// The lock args is secp256k1_blake160 of the public key, the EoA address
+    /// is `blake2b(type_args ++ lock_args)[0..20]`
+    Ckb,
+    /// The lock args is the Ethereum address of the public key
+    /// (`keccak256(uncompressed_pubkey)[12..]`), which is also the EoA
+    /// address. One address may own many EoA cells (with different type id).
+    Ethereum,
+}
+
+impl Default for AddressMode {
+    fn default() -> AddressMode {
+        AddressMode::Ckb
+    }
+}
+
+impl AddressMode {
+    /// The EoA lock args of the public key
+    pub fn lock_args(self, pubkey: &secp256k1::PublicKey) -> H160 {
+        let hash = match self {
+            AddressMode::Ckb => blake2b_256(&pubkey.serialize()[..])[0..20].to_vec(),
+            AddressMode::Ethereum => {
+                keccak256(&pubkey.serialize_uncompressed()[1..])[12..].to_vec()
+            }
+        };
+        H160::from_slice(&hash).expect("convert to h160")
+    }
+
+    pub fn is_valid_lock_args(self, lock_args: &[u8]) -> bool {
+        match self {
+            AddressMode::Ckb => true,
+            AddressMode::Ethereum => lock_args.len() == 20,
+        }
+    }
+
+    /// The address of an EoA cell
+    pub fn eoa_address(self, type_args: &[u8], lock_args: &[u8]) -> H160 {
+        match self {
+            AddressMode::Ckb => {
+                let mut blake2b = new_blake2b();
+                blake2b.update(type_args);
+                blake2b.update(lock_args);
+                let mut result = [0u8; 32];
+                blake2b.finalize(&mut result);
+                H160::from_slice(&result[0..20]).expect("convert to h160")
+            }
+            AddressMode::Ethereum => H160::from_slice(lock_args).unwrap_or_default(),
+        }
+    }
 }
 
 /// A contract account's cell data
@@ -213,10 +297,10 @@ pub struct ContractChange {
     pub is_create: bool,
 }
 
-/// The EOA account address.
-/// Just the secp256k1_blake160 lock args, can be calculated from signature.
+/// The EOA account address, derived by the `AddressMode` of the deployment.
 ///
-///     address = blake2b(pubkey)[0..20]
+///     ckb:      address = blake2b(type_args ++ lock_args)[0..20]
+///     ethereum: address = lock_args = keccak256(uncompressed_pubkey)[12..]
 ///
 #[derive(Default, Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct EoaAddress(pub H160);
@@ -241,6 +325,89 @@ pub struct LogInfo {
     pub data: Bytes,
 }
 
+/// A value transfer from/to an EoA account
+#[derive(Default, Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TransferInfo {
+    pub block_number: u64,
+    pub tx_index: u32,
+    /// The transfer index in current transaction
+    pub transfer_index: u32,
+    pub tx_hash: H256,
+    /// The sender (incoming) or the destination (outgoing) of the transfer
+    pub counterparty: H160,
+    pub value: u64,
+    pub incoming: bool,
+}
+
+/// The position of the next transfer to load
+#[derive(Default, Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TransferCursor {
+    pub block_number: u64,
+    pub tx_index: u32,
+    pub transfer_index: u32,
+}
+
+/// The token standard detected from the `Transfer` event
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Erc20,
+    Erc721,
+}
+
+/// The token balance of a holder, the balance of ERC721 token is the number
+/// of owned tokens. The integers are decimal strings.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TokenBalanceInfo {
+    pub token: ContractAddress,
+    pub holder: H160,
+    pub kind: TokenKind,
+    pub balance: String,
+}
+
+/// A token transfer from/to an account
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TokenTransferInfo {
+    pub block_number: u64,
+    pub tx_index: u32,
+    /// The transfer index in current transaction
+    pub transfer_index: u32,
+    pub tx_hash: H256,
+    pub token: ContractAddress,
+    pub kind: TokenKind,
+    /// The sender (incoming) or the destination (outgoing) of the transfer,
+    /// zero address for mint (incoming) and burn (outgoing)
+    pub counterparty: H160,
+    /// The amount (ERC20) or the token id (ERC721)
+    pub value: String,
+    pub incoming: bool,
+}
+
+/// The indexing progress of the polyjuice server
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SyncStatus {
+    /// The last indexed block (`None` before the start block is indexed)
+    pub indexed_number: Option<u64>,
+    pub indexed_hash: Option<H256>,
+    /// The tip block number of CKB
+    pub tip_number: u64,
+    /// The number of blocks the indexer is behind the CKB tip
+    pub lag: u64,
+    /// The last block rolled back by the indexer since the server started
+    pub last_rollback: Option<RollbackEvent>,
+    /// Whether the indexer thread made progress recently
+    pub indexer_alive: bool,
+}
+
+/// A block rolled back by the indexer
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RollbackEvent {
+    pub number: u64,
+    pub hash: H256,
+    /// Unix timestamp in milliseconds
+    pub timestamp: u64,
+}
+
 impl From<&RunConfig> for Config {
     fn from(cfg: &RunConfig) -> Config {
         let mut config = Config::default();
@@ -954,6 +1121,29 @@ mod test {
     use ckb_simple_account_layer::RunProofResult;
     use ckb_types::h160;
 
+    #[test]
+    fn test_ethereum_address_mode() {
+        // The well known address of private key 1, the validator built with
+        // `ETH_ADDRESS_MODE` must recover the same lock args
+        let mut privkey_bytes = [0u8; 32];
+        privkey_bytes[31] = 1;
+        let privkey = secp256k1::SecretKey::from_slice(&privkey_bytes).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&SECP256K1, &privkey);
+        let lock_args = AddressMode::Ethereum.lock_args(&pubkey);
+        assert_eq!(
+            lock_args,
+            h160!("0x7e5f4552091a69125d5dfcb7b8c2659029395bdf")
+        );
+        assert_eq!(
+            AddressMode::Ethereum.eoa_address(&[3u8; 32], lock_args.as_bytes()),
+            lock_args
+        );
+        assert_ne!(
+            AddressMode::Ckb.eoa_address(&[3u8; 32], lock_args.as_bytes()),
+            lock_args
+        );
+    }
+
     #[test]
     fn test_serde_program() {
         let program1 = Program::new_create(